@@ -0,0 +1,248 @@
+use std::path::Path;
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use super::stability::{period_phases, run_until_periodic};
+use super::{
+    load_rle, parse_rle, GameOfLifeError, RunOptions, SimConfig, Simulation, TerminationReason,
+};
+
+// One run of a batch.
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+    pub name: String,
+    pub config: SimConfig,
+}
+
+// What the report says about one entry.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BatchResult {
+    pub name: String,
+    pub generations: u32,
+    pub population: usize,
+    // Generation from which the grid repeats, if it settled within the run.
+    pub stabilized_at: Option<u32>,
+    pub reason: TerminationReason,
+}
+
+// One line of the report: the entry's result, or why it could not run. A failing
+// entry does not stop the others.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum BatchOutcome {
+    Ran(BatchResult),
+    Failed { name: String, error: String },
+}
+
+// Reads one entry per line as space-separated key=value pairs: name, width, height,
+// generations, rule, seed, density, rle (an inline pattern body) and the bare word
+// empty. Missing keys take their `SimConfig` defaults, except generations, which
+// defaults to `generations`. Blank lines and lines starting with `#` are skipped.
+pub fn parse_batch(text: &str, generations: u32) -> Result<Vec<BatchEntry>, GameOfLifeError> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = |message: String| {
+            GameOfLifeError::ParseError(format!("batch line {}: {}", number + 1, message))
+        };
+        let mut name = format!("line{}", number + 1);
+        let mut config = SimConfig {
+            generations,
+            ..SimConfig::default()
+        };
+        for field in line.split_whitespace() {
+            if field == "empty" {
+                config.options.empty = true;
+                continue;
+            }
+            let Some((key, value)) = field.split_once('=') else {
+                return Err(invalid(format!("expected key=value, got {:?}", field)));
+            };
+            let number = |value: &str| {
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid {} {:?}", key, value)))
+            };
+            match key {
+                "name" => name = value.to_string(),
+                "width" => config.width = Some(number(value)?),
+                "height" => config.height = Some(number(value)?),
+                "generations" => config.generations = number(value)?,
+                "seed" => {
+                    let seed = value
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid seed {:?}", value)))?;
+                    config.seed = Some(seed);
+                }
+                "density" => {
+                    config.density = value
+                        .parse()
+                        .map_err(|_| invalid(format!("invalid density {:?}", value)))?
+                }
+                "rule" => config.rule = Some(value.parse().map_err(invalid)?),
+                "rle" => {
+                    let pattern = parse_rle(value).map_err(|err| invalid(err.to_string()))?;
+                    config.options.pattern = Some(pattern);
+                }
+                _ => return Err(invalid(format!("unknown key {:?}", key))),
+            }
+        }
+        entries.push(BatchEntry { name, config });
+    }
+    Ok(entries)
+}
+
+// One entry per `.rle` file in `dir`, named after the file, on a grid the size of the
+// pattern plus `margin` dead cells on every side, under the rule in its header. Both
+// are filled in when the entry runs.
+pub fn load_batch_dir(
+    dir: &Path,
+    generations: u32,
+    margin: u32,
+) -> Result<Vec<BatchEntry>, GameOfLifeError> {
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(|err| GameOfLifeError::io(dir, err))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| GameOfLifeError::io(dir, err))?;
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == "rle"));
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| {
+            let pattern = load_rle(&path)?.with_margin(margin);
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            Ok(BatchEntry {
+                name: name.into_owned(),
+                config: SimConfig {
+                    generations,
+                    options: RunOptions {
+                        pattern: Some(pattern),
+                        ..RunOptions::default()
+                    },
+                    ..SimConfig::default()
+                },
+            })
+        })
+        .collect()
+}
+
+// Runs the entry's grid, rule and seed for its generations, watching for the first
+// repeated state. The other `RunOptions` do not apply.
+pub fn run_entry(entry: &BatchEntry) -> Result<BatchResult, GameOfLifeError> {
+    let config = &entry.config;
+    config.validate()?;
+    let (width, height) = config.grid_size();
+    let mut sim = Simulation::new(width, height, config.rules()?, config.seed_fn());
+    // Once the run repeats itself, the state at the last generation is one of the
+    // period's phases, so there is no need to step all the way there.
+    let (cycle, live) = run_until_periodic(&mut sim, config.generations);
+    let live = match cycle {
+        Some(cycle) => {
+            let mut phases = period_phases(&mut sim, live, cycle);
+            phases.swap_remove(((config.generations - cycle.since) % cycle.period) as usize)
+        }
+        None => live,
+    };
+    let stabilized_at = cycle.map(|cycle| cycle.since);
+    Ok(BatchResult {
+        name: entry.name.clone(),
+        generations: config.generations,
+        population: live.len(),
+        stabilized_at,
+        reason: TerminationReason::finished(live.len()),
+    })
+}
+
+// Runs the entries in parallel; outcomes are in entry order.
+pub fn run_batch(entries: &[BatchEntry]) -> Vec<BatchOutcome> {
+    entries
+        .par_iter()
+        .map(|entry| match run_entry(entry) {
+            Ok(result) => BatchOutcome::Ran(result),
+            Err(err) => BatchOutcome::Failed {
+                name: entry.name.clone(),
+                error: err.to_string(),
+            },
+        })
+        .collect()
+}
+
+// Writes the outcomes as one pretty-printed JSON array.
+pub fn write_report(outcomes: &[BatchOutcome], path: &Path) -> Result<(), GameOfLifeError> {
+    let json = serde_json::to_string_pretty(outcomes)
+        .map_err(|err| GameOfLifeError::EncodeError(format!("cannot encode report: {}", err)))?;
+    std::fs::write(path, json + "\n").map_err(|err| GameOfLifeError::io(path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_batch_report_has_one_entry_per_config() {
+        let entries = parse_batch(
+            "# a blinker away from the edges, then nothing at all\n\
+             name=blinker width=5 height=5 rle=$b3o!\n\
+             \n\
+             name=empty width=4 height=3 generations=7 empty\n\
+             name=cloudy width=4 height=4 seed=1 density=NaN\n",
+            10,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 3);
+        let path = std::env::temp_dir().join(format!(
+            "ecs_without_bevy_batch_{}.json",
+            std::process::id()
+        ));
+        write_report(&run_batch(&entries), &path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let report = serde_json::from_str::<serde_json::Value>(&text).unwrap();
+        assert_eq!(
+            report,
+            serde_json::json!([
+                {
+                    "name": "blinker",
+                    "generations": 10,
+                    "population": 3,
+                    "stabilized_at": 0,
+                    "reason": "completed",
+                },
+                {
+                    "name": "empty",
+                    "generations": 7,
+                    "population": 0,
+                    "stabilized_at": 0,
+                    "reason": "extinct",
+                },
+                {
+                    "name": "cloudy",
+                    "error": "density must be between 0 and 1, got NaN",
+                },
+            ])
+        );
+        assert!(parse_batch("width=ten", 10).is_err());
+        assert!(parse_batch("colour=red", 10).is_err());
+    }
+
+    #[test]
+    fn test_settled_entry_reports_the_last_generations_phase() {
+        // A beacon has 8 live cells in one phase and 6 in the other.
+        for (generations, population) in [(1, 6), (9, 6), (10, 8)] {
+            let line = format!(
+                "name=beacon width=6 height=6 generations={} rle=$b2o$b2o$3b2o$3b2o!",
+                generations
+            );
+            let entries = parse_batch(&line, 10).unwrap();
+            let result = run_entry(&entries[0]).unwrap();
+            assert_eq!(result.population, population, "generation {}", generations);
+            assert_eq!(result.stabilized_at, (generations >= 2).then_some(0));
+        }
+    }
+}
@@ -0,0 +1,461 @@
+use bevy_ecs::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+use super::NEIGHBOR_OFFSETS;
+
+// What lies beyond the edges of the grid when counting neighbors.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BoundaryMode {
+    // Everything outside the grid is permanently dead.
+    #[default]
+    Dead,
+    // Opposite edges are joined, so the grid is a torus.
+    Toroidal,
+    // Left and right edges are joined as on a torus, but a cell leaving through the
+    // top or bottom comes back mirrored left to right.
+    KleinBottle,
+    // Both pairs of edges are joined with a flip: crossing the top or bottom mirrors
+    // the column, crossing the left or right mirrors the row. Corner cells end up as
+    // their own diagonal neighbors.
+    ProjectivePlane,
+}
+
+impl BoundaryMode {
+    // Maps a possibly out-of-bounds coordinate to the grid cell it refers to, or
+    // `None` if it lies outside a dead boundary.
+    pub fn resolve(self, x: i32, y: i32, width: u32, height: u32) -> Option<(i32, i32)> {
+        let (width, height) = (width as i32, height as i32);
+        if width == 0 || height == 0 {
+            return None;
+        }
+        match self {
+            BoundaryMode::Dead => (x >= 0 && y >= 0 && x < width && y < height).then_some((x, y)),
+            BoundaryMode::Toroidal => Some((x.rem_euclid(width), y.rem_euclid(height))),
+            BoundaryMode::KleinBottle => {
+                let x = flip_if(y.div_euclid(height), x, width);
+                Some((x.rem_euclid(width), y.rem_euclid(height)))
+            }
+            BoundaryMode::ProjectivePlane => {
+                let flipped_x = flip_if(y.div_euclid(height), x, width);
+                let flipped_y = flip_if(x.div_euclid(width), y, height);
+                Some((flipped_x.rem_euclid(width), flipped_y.rem_euclid(height)))
+            }
+        }
+    }
+
+    // The cells around (x, y) with the index of their offset in `NEIGHBOR_OFFSETS`,
+    // None past a dead edge. On grids less than three cells wide or tall, and at the
+    // corners of a projective plane, several offsets wrap onto the same cell, which is
+    // then only listed for the first of them so that it is counted once.
+    pub fn neighbors(
+        self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> impl Iterator<Item = (usize, Option<(i32, i32)>)> {
+        let cells = NEIGHBOR_OFFSETS.map(|(dx, dy)| self.resolve(x + dx, y + dy, width, height));
+        let corner = (x == 0 || x == width as i32 - 1) && (y == 0 || y == height as i32 - 1);
+        let overlaps = width < 3 || height < 3 || (self == BoundaryMode::ProjectivePlane && corner);
+        (0..cells.len())
+            .filter(move |&i| !overlaps || cells[i].is_none() || !cells[..i].contains(&cells[i]))
+            .map(move |i| (i, cells[i]))
+    }
+}
+
+// Mirrors `coord` across an axis of length `size` after an odd number of crossings.
+fn flip_if(crossings: i32, coord: i32, size: i32) -> i32 {
+    if crossings % 2 == 0 {
+        coord
+    } else {
+        size - 1 - coord
+    }
+}
+
+impl FromStr for BoundaryMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "dead" => Ok(BoundaryMode::Dead),
+            "toroidal" | "torus" | "wrap" => Ok(BoundaryMode::Toroidal),
+            "klein" | "klein-bottle" => Ok(BoundaryMode::KleinBottle),
+            "projective" | "projective-plane" => Ok(BoundaryMode::ProjectivePlane),
+            _ => Err(format!(
+                "unknown boundary {:?}, expected dead, toroidal, klein or projective",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BoundaryMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoundaryMode::Dead => write!(f, "dead"),
+            BoundaryMode::Toroidal => write!(f, "toroidal"),
+            BoundaryMode::KleinBottle => write!(f, "klein"),
+            BoundaryMode::ProjectivePlane => write!(f, "projective"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        build_schedule, live_cells, objects, setup_world, spawn_pattern_cells, Rules,
+    };
+    use super::*;
+    use std::collections::HashSet;
+
+    // Glider heading down and to the right.
+    const GLIDER: [(i32, i32); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    fn run(
+        boundary: BoundaryMode,
+        width: u32,
+        height: u32,
+        offset: (i32, i32),
+        generations: u32,
+    ) -> HashSet<(i32, i32)> {
+        let mut world = setup_world(width, height, Rules::default());
+        let live = GLIDER
+            .iter()
+            .map(|(x, y)| (x + offset.0, y + offset.1))
+            .collect();
+        spawn_pattern_cells(&mut world, width, height, &live);
+        world.insert_resource(boundary);
+        let mut schedule = build_schedule();
+        for _ in 0..generations {
+            schedule.run(&mut world);
+        }
+        live_cells(&mut world)
+    }
+
+    fn mirror_x(live: &HashSet<(i32, i32)>, width: i32) -> HashSet<(i32, i32)> {
+        live.iter().map(|&(x, y)| (width - 1 - x, y)).collect()
+    }
+
+    fn mirror_y(live: &HashSet<(i32, i32)>, height: i32) -> HashSet<(i32, i32)> {
+        live.iter().map(|&(x, y)| (x, height - 1 - y)).collect()
+    }
+
+    #[test]
+    fn test_glider_mirrored_across_flipped_top_and_bottom() {
+        // Starts at rows 4-6 of 8 and ends at rows 2-4 after wrapping; columns stay
+        // well inside the grid.
+        let torus = run(BoundaryMode::Toroidal, 20, 8, (4, 4), 24);
+        assert_eq!(objects::classify(&torus), Some("glider"));
+        for boundary in [BoundaryMode::KleinBottle, BoundaryMode::ProjectivePlane] {
+            let flipped = run(boundary, 20, 8, (4, 4), 24);
+            assert_eq!(objects::classify(&flipped), Some("glider"));
+            assert_ne!(flipped, torus);
+            assert_eq!(flipped, mirror_x(&torus, 20));
+        }
+    }
+
+    #[test]
+    fn test_glider_across_left_and_right_edges() {
+        let torus = run(BoundaryMode::Toroidal, 8, 20, (4, 4), 24);
+        assert_eq!(run(BoundaryMode::KleinBottle, 8, 20, (4, 4), 24), torus);
+
+        let projective = run(BoundaryMode::ProjectivePlane, 8, 20, (4, 4), 24);
+        assert_eq!(objects::classify(&projective), Some("glider"));
+        assert_eq!(projective, mirror_y(&torus, 20));
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(BoundaryMode::Dead.resolve(-1, 0, 4, 3), None);
+        assert_eq!(BoundaryMode::Dead.resolve(3, 2, 4, 3), Some((3, 2)));
+        assert_eq!(BoundaryMode::Toroidal.resolve(-1, 0, 4, 3), Some((3, 0)));
+        assert_eq!(BoundaryMode::Toroidal.resolve(4, 3, 4, 3), Some((0, 0)));
+        assert_eq!("Toroidal".parse(), Ok(BoundaryMode::Toroidal));
+        assert_eq!(BoundaryMode::KleinBottle.resolve(-1, 1, 4, 3), Some((3, 1)));
+        assert_eq!(BoundaryMode::KleinBottle.resolve(0, 3, 4, 3), Some((3, 0)));
+        assert_eq!(BoundaryMode::KleinBottle.resolve(1, -1, 4, 3), Some((2, 2)));
+        assert_eq!(
+            BoundaryMode::ProjectivePlane.resolve(-1, 0, 4, 3),
+            Some((3, 2))
+        );
+        assert_eq!(
+            BoundaryMode::ProjectivePlane.resolve(1, 3, 4, 3),
+            Some((2, 0))
+        );
+        assert_eq!("klein".parse(), Ok(BoundaryMode::KleinBottle));
+        assert!("mobius".parse::<BoundaryMode>().is_err());
+    }
+
+    #[test]
+    fn test_neighbors_never_repeat_a_cell() {
+        let modes = [
+            BoundaryMode::Dead,
+            BoundaryMode::Toroidal,
+            BoundaryMode::KleinBottle,
+            BoundaryMode::ProjectivePlane,
+        ];
+        for boundary in modes {
+            for (width, height) in [(1, 1), (2, 5), (5, 2), (3, 3), (4, 6)] {
+                for (x, y) in
+                    (0..width as i32).flat_map(|x| (0..height as i32).map(move |y| (x, y)))
+                {
+                    let cells = boundary
+                        .neighbors(x, y, width, height)
+                        .filter_map(|(_, cell)| cell)
+                        .collect::<Vec<_>>();
+                    let unique = cells.iter().collect::<HashSet<_>>();
+                    assert_eq!(
+                        unique.len(),
+                        cells.len(),
+                        "{} {}x{} at ({}, {})",
+                        boundary,
+                        width,
+                        height,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Neighbor counts on small tori, checked against values worked out by hand for every
+// edge and corner cell.
+#[cfg(test)]
+mod toroidal_neighbor_tests {
+    use super::super::{
+        compute_neighbors, rebuild_cell_positions, setup_world, spawn_pattern_cells,
+        update_neighbors_brute_force_system, Grid, Neighbors, Position, Rules,
+    };
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+    use std::collections::{HashMap, HashSet};
+
+    type Cell = (i32, i32);
+
+    // Counts from the ECS neighbor pass and from `compute_neighbors`, which must agree.
+    fn counts(width: u32, height: u32, live: &HashSet<(i32, i32)>) -> HashMap<(i32, i32), u8> {
+        let mut world = setup_world(width, height, Rules::default());
+        spawn_pattern_cells(&mut world, width, height, live);
+        world.insert_resource(BoundaryMode::Toroidal);
+        world.run_system_once(rebuild_cell_positions);
+        world.run_system_once(update_neighbors_brute_force_system);
+        let ecs = world
+            .query::<(&Position, &Neighbors)>()
+            .iter(&world)
+            .map(|(pos, neighbors)| ((pos.x, pos.y), neighbors.0))
+            .collect::<HashMap<_, _>>();
+        let grid = Grid { width, height };
+        assert_eq!(ecs, compute_neighbors(live, &grid, BoundaryMode::Toroidal));
+        ecs
+    }
+
+    #[test]
+    fn test_single_cell_on_every_edge_and_corner() {
+        // The eight wrapped neighbors of a lone cell on a 4x4 torus, by hand.
+        let cases: [(Cell, [Cell; 8]); 12] = [
+            (
+                (0, 0),
+                [
+                    (3, 3),
+                    (0, 3),
+                    (1, 3),
+                    (3, 0),
+                    (1, 0),
+                    (3, 1),
+                    (0, 1),
+                    (1, 1),
+                ],
+            ),
+            (
+                (3, 0),
+                [
+                    (2, 3),
+                    (3, 3),
+                    (0, 3),
+                    (2, 0),
+                    (0, 0),
+                    (2, 1),
+                    (3, 1),
+                    (0, 1),
+                ],
+            ),
+            (
+                (0, 3),
+                [
+                    (3, 2),
+                    (0, 2),
+                    (1, 2),
+                    (3, 3),
+                    (1, 3),
+                    (3, 0),
+                    (0, 0),
+                    (1, 0),
+                ],
+            ),
+            (
+                (3, 3),
+                [
+                    (2, 2),
+                    (3, 2),
+                    (0, 2),
+                    (2, 3),
+                    (0, 3),
+                    (2, 0),
+                    (3, 0),
+                    (0, 0),
+                ],
+            ),
+            (
+                (1, 0),
+                [
+                    (0, 3),
+                    (1, 3),
+                    (2, 3),
+                    (0, 0),
+                    (2, 0),
+                    (0, 1),
+                    (1, 1),
+                    (2, 1),
+                ],
+            ),
+            (
+                (2, 0),
+                [
+                    (1, 3),
+                    (2, 3),
+                    (3, 3),
+                    (1, 0),
+                    (3, 0),
+                    (1, 1),
+                    (2, 1),
+                    (3, 1),
+                ],
+            ),
+            (
+                (1, 3),
+                [
+                    (0, 2),
+                    (1, 2),
+                    (2, 2),
+                    (0, 3),
+                    (2, 3),
+                    (0, 0),
+                    (1, 0),
+                    (2, 0),
+                ],
+            ),
+            (
+                (2, 3),
+                [
+                    (1, 2),
+                    (2, 2),
+                    (3, 2),
+                    (1, 3),
+                    (3, 3),
+                    (1, 0),
+                    (2, 0),
+                    (3, 0),
+                ],
+            ),
+            (
+                (0, 1),
+                [
+                    (3, 0),
+                    (0, 0),
+                    (1, 0),
+                    (3, 1),
+                    (1, 1),
+                    (3, 2),
+                    (0, 2),
+                    (1, 2),
+                ],
+            ),
+            (
+                (0, 2),
+                [
+                    (3, 1),
+                    (0, 1),
+                    (1, 1),
+                    (3, 2),
+                    (1, 2),
+                    (3, 3),
+                    (0, 3),
+                    (1, 3),
+                ],
+            ),
+            (
+                (3, 1),
+                [
+                    (2, 0),
+                    (3, 0),
+                    (0, 0),
+                    (2, 1),
+                    (0, 1),
+                    (2, 2),
+                    (3, 2),
+                    (0, 2),
+                ],
+            ),
+            (
+                (3, 2),
+                [
+                    (2, 1),
+                    (3, 1),
+                    (0, 1),
+                    (2, 2),
+                    (0, 2),
+                    (2, 3),
+                    (3, 3),
+                    (0, 3),
+                ],
+            ),
+        ];
+        for (cell, neighbors) in cases {
+            let counts = counts(4, 4, &HashSet::from([cell]));
+            for y in 0..4 {
+                for x in 0..4 {
+                    let expected = u8::from(neighbors.contains(&(x, y)));
+                    assert_eq!(
+                        counts[&(x, y)],
+                        expected,
+                        "cell {:?} at ({}, {})",
+                        cell,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_narrow_torus_counts_each_neighbor_once() {
+        // On a 2x2 torus left and right, and up and down, are the same cell.
+        let full = HashSet::from([(0, 0), (1, 0), (0, 1), (1, 1)]);
+        assert!(counts(2, 2, &full).values().all(|&count| count == 3));
+        // On a 1-wide torus a cell's left and right neighbors are the cell itself.
+        let counts = counts(1, 3, &HashSet::from([(0, 1)]));
+        assert_eq!(
+            (counts[&(0, 0)], counts[&(0, 1)], counts[&(0, 2)]),
+            (1, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_counts_on_a_three_row_torus() {
+        // #..#
+        // ....
+        // .#.#
+        let live = HashSet::from([(0, 0), (3, 0), (1, 2), (3, 2)]);
+        let expected = [[3, 2, 3, 2], [4, 2, 3, 3], [4, 1, 3, 2]];
+        let counts = counts(4, 3, &live);
+        for (y, row) in expected.iter().enumerate() {
+            for (x, &count) in row.iter().enumerate() {
+                assert_eq!(counts[&(x as i32, y as i32)], count, "({}, {})", x, y);
+            }
+        }
+    }
+}
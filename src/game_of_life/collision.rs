@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use super::objects::ash_signature;
+use super::stability::{period_phases, run_until_periodic};
+use super::{spawn_pattern_cells, Rules, Simulation};
+
+// Glider heading down and to the right, relative to its bounding box.
+const GLIDER: [(i32, i32); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+// Two gliders heading straight for each other along the diagonal of the largest
+// square centred in the grid: one a quarter of the way along moving down-right, and
+// its 180-degree rotation about the centre moving up-left.
+pub fn place_gliders(width: u32, height: u32) -> HashSet<(i32, i32)> {
+    let side = width.min(height) as i32;
+    let left = (width as i32 - side) / 2;
+    let top = (height as i32 - side) / 2;
+    let start = side / 4;
+    GLIDER
+        .iter()
+        .flat_map(|&(x, y)| {
+            let (x, y) = (x + start, y + start);
+            [(x, y), (side - 1 - x, side - 1 - y)]
+        })
+        .map(|(x, y)| (x + left, y + top))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollisionResult {
+    // Generation from which the debris repeats, if it settled within the cap.
+    pub stabilized_at: Option<u32>,
+    pub live: HashSet<(i32, i32)>,
+    // `ash_signature` of the debris, over every phase once it has settled.
+    pub ash: u32,
+}
+
+// Runs the head-on glider collision until the grid becomes periodic or `cap`
+// generations have passed.
+pub fn run_collision(width: u32, height: u32, cap: u32) -> CollisionResult {
+    let gliders = place_gliders(width, height);
+    let mut sim = Simulation::new(width, height, Rules::default(), move |world, w, h| {
+        spawn_pattern_cells(world, w, h, &gliders)
+    });
+    match run_until_periodic(&mut sim, cap) {
+        (Some(cycle), live) => {
+            let phases = period_phases(&mut sim, live.clone(), cycle);
+            CollisionResult {
+                stabilized_at: Some(cycle.since),
+                live,
+                ash: ash_signature(&phases),
+            }
+        }
+        (None, live) => CollisionResult {
+            stabilized_at: None,
+            ash: ash_signature(std::slice::from_ref(&live)),
+            live,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::objects::{classify, connected_components, Connectivity};
+    use super::*;
+
+    #[test]
+    fn test_gliders_collide_and_settle() {
+        let live = place_gliders(40, 30);
+        let gliders = connected_components(&live, Connectivity::Eight);
+        assert_eq!(gliders.len(), 2);
+        assert!(gliders.iter().all(|g| classify(g) == Some("glider")));
+        assert!(live.contains(&(13, 7)) && live.contains(&(26, 22)));
+        let mirrored = live.iter().map(|&(x, y)| (39 - x, 29 - y)).collect();
+        assert_eq!(live, mirrored);
+        assert!(live.iter().all(|&(x, y)| (x - 5 - y).abs() <= 2));
+
+        let result = run_collision(40, 30, 500);
+        let settled = result.stabilized_at.unwrap();
+        // Ten empty columns separate the gliders at the start and the gap shrinks by
+        // two every four generations, so they cannot have collided by generation 16.
+        assert!(settled > 20, "settled at {}", settled);
+        assert!(result
+            .live
+            .iter()
+            .all(|&(x, y)| (10..30).contains(&x) && (5..25).contains(&y)));
+    }
+}
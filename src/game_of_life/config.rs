@@ -0,0 +1,234 @@
+use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::{BoundaryMode, GameOfLifeError, Rules, SimConfig};
+
+// Reads a string field through the type's `FromStr`, so the file accepts the same
+// spellings as the command line.
+fn parse_field<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr<Err = String>,
+{
+    let Some(text) = Option::<String>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    text.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+// Settings for a run read from `--config`. Every field is optional; the ones that are
+// set replace the defaults and are in turn replaced by flags given on the command line.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub generations: Option<u32>,
+    #[serde(default, deserialize_with = "parse_field")]
+    pub rule: Option<Rules>,
+    #[serde(default, deserialize_with = "parse_field")]
+    pub boundary: Option<BoundaryMode>,
+    pub seed: Option<u64>,
+    pub density: Option<f64>,
+    #[serde(default)]
+    pub outputs: ConfigOutputs,
+}
+
+// Where a configured run writes its results, as with the flags of the same names.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigOutputs {
+    pub render: Option<bool>,
+    pub jsonl: Option<PathBuf>,
+    pub record: Option<PathBuf>,
+    pub apng: Option<PathBuf>,
+    pub save_snapshot: Option<PathBuf>,
+    pub save_pbm: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    pub fn parse(text: &str) -> Result<Self, GameOfLifeError> {
+        serde_json::from_str(text).map_err(|err| GameOfLifeError::ParseError(err.to_string()))
+    }
+
+    // The settings of `config` that came from the command line, to lay over a loaded
+    // file. Flags with a default (generations, boundary, density and render) are only
+    // taken when `given` says they were passed; the others are unset unless passed.
+    pub fn from_command_line(config: &SimConfig, given: impl Fn(&str) -> bool) -> Self {
+        let options = &config.options;
+        ConfigFile {
+            width: config.width,
+            height: config.height,
+            generations: given("generations").then_some(config.generations),
+            rule: config.rule.clone(),
+            boundary: given("boundary").then_some(options.boundary),
+            seed: config.seed,
+            density: given("density").then_some(config.density),
+            outputs: ConfigOutputs {
+                render: given("render").then_some(options.render),
+                jsonl: options.jsonl.clone(),
+                record: options.record.clone(),
+                apng: options.apng.clone(),
+                save_snapshot: options.save_snapshot.clone(),
+                save_pbm: options.save_pbm.clone(),
+            },
+        }
+    }
+
+    // Overwrites the fields of `config` that are set here.
+    pub fn apply(&self, config: &mut SimConfig) {
+        let options = &mut config.options;
+        let outputs = &self.outputs;
+        config.width = self.width.or(config.width);
+        config.height = self.height.or(config.height);
+        config.generations = self.generations.unwrap_or(config.generations);
+        config.rule = self.rule.clone().or(config.rule.take());
+        config.seed = self.seed.or(config.seed);
+        config.density = self.density.unwrap_or(config.density);
+        options.boundary = self.boundary.unwrap_or(options.boundary);
+        options.render = outputs.render.unwrap_or(options.render);
+        options.jsonl = outputs.jsonl.clone().or(options.jsonl.take());
+        options.record = outputs.record.clone().or(options.record.take());
+        options.apng = outputs.apng.clone().or(options.apng.take());
+        options.save_snapshot = outputs
+            .save_snapshot
+            .clone()
+            .or(options.save_snapshot.take());
+        options.save_pbm = outputs.save_pbm.clone().or(options.save_pbm.take());
+    }
+}
+
+pub fn load_config(path: &Path) -> Result<ConfigFile, GameOfLifeError> {
+    let text = std::fs::read_to_string(path).map_err(|err| GameOfLifeError::io(path, err))?;
+    ConfigFile::parse(&text)
+}
+
+// `base` with the config file applied over it, then the settings given on the command
+// line, which win.
+pub fn merge(mut base: SimConfig, file: &ConfigFile, cli: &ConfigFile) -> SimConfig {
+    file.apply(&mut base);
+    cli.apply(&mut base);
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Backend, RunOptions};
+    use super::*;
+
+    #[test]
+    fn test_config_file_merges_under_cli_flags() {
+        let path = std::env::temp_dir().join(format!(
+            "ecs_without_bevy_config_{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"{
+                "width": 64,
+                "height": 48,
+                "generations": 500,
+                "rule": "B36/S23",
+                "boundary": "toroidal",
+                "seed": 7,
+                "outputs": { "render": true, "record": "run.rec" }
+            }"#,
+        )
+        .unwrap();
+        let file = load_config(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let cli = ConfigFile {
+            generations: Some(20),
+            density: Some(0.25),
+            ..ConfigFile::default()
+        };
+
+        let config = merge(SimConfig::default(), &file, &cli);
+        assert_eq!(config.grid_size(), (64, 48));
+        assert_eq!(config.generations, 20);
+        assert_eq!(config.rules().unwrap(), "B36/S23".parse().unwrap());
+        assert_eq!(config.options.boundary, BoundaryMode::Toroidal);
+        assert_eq!((config.seed, config.density), (Some(7), 0.25));
+        assert!(config.options.render);
+        assert_eq!(config.options.record, Some(PathBuf::from("run.rec")));
+        assert_eq!(config.options.jsonl, None);
+    }
+
+    #[test]
+    fn test_config_rejects_unknown_and_invalid_fields() {
+        let err = ConfigFile::parse(r#"{"widht": 10}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `widht`"), "{}", err);
+        let err = ConfigFile::parse(r#"{"outputs": {"gif": "a.gif"}}"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `gif`"), "{}", err);
+        let err = ConfigFile::parse(r#"{"rule": "B3/X23"}"#).unwrap_err();
+        assert!(err.to_string().contains("invalid rulestring"), "{}", err);
+        assert!(ConfigFile::parse(r#"{"width": -1}"#).is_err());
+        assert_eq!(ConfigFile::parse("{}").unwrap(), ConfigFile::default());
+    }
+
+    #[test]
+    fn test_command_line_keeps_only_given_defaults() {
+        let config = SimConfig {
+            width: Some(30),
+            generations: 50,
+            density: 0.3,
+            options: RunOptions {
+                boundary: BoundaryMode::Toroidal,
+                render: true,
+                jsonl: Some(PathBuf::from("run.jsonl")),
+                ..RunOptions::default()
+            },
+            ..SimConfig::default()
+        };
+        let cli = ConfigFile::from_command_line(&config, |id| id == "density");
+        assert_eq!(
+            cli,
+            ConfigFile {
+                width: Some(30),
+                density: Some(0.3),
+                outputs: ConfigOutputs {
+                    jsonl: Some(PathBuf::from("run.jsonl")),
+                    ..ConfigOutputs::default()
+                },
+                ..ConfigFile::default()
+            }
+        );
+
+        let file = ConfigFile::parse(r#"{"generations": 9, "boundary": "dead"}"#).unwrap();
+        let given = ConfigFile::from_command_line(&config, |_| true);
+        let merged = merge(config.clone(), &file, &given);
+        assert_eq!(merged.generations, 50);
+        assert_eq!(merged.options.boundary, BoundaryMode::Toroidal);
+        let merged = merge(config.clone(), &file, &cli);
+        assert_eq!(merged.generations, 9);
+        assert_eq!(merged.options.boundary, BoundaryMode::Dead);
+        assert!(merged.options.render);
+    }
+
+    #[test]
+    fn test_merged_outputs_are_checked_against_the_backend() {
+        let config = SimConfig {
+            width: Some(8),
+            height: Some(8),
+            options: RunOptions {
+                backend: Backend::Soa,
+                ..RunOptions::default()
+            },
+            ..SimConfig::default()
+        };
+        let cli = ConfigFile::from_command_line(&config, |_| false);
+        assert!(merge(config.clone(), &ConfigFile::default(), &cli)
+            .validate()
+            .is_ok());
+
+        let file =
+            ConfigFile::parse(r#"{"outputs": {"render": true, "jsonl": "run.jsonl"}}"#).unwrap();
+        let err = merge(config, &file, &cli).validate().unwrap_err();
+        assert!(matches!(err, GameOfLifeError::InvalidConfig(_)));
+        assert_eq!(
+            err.to_string(),
+            "--backend soa does not support --render, --jsonl"
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub enum GameOfLifeError {
+    // The grid has no cells, or more than a `u32` cell index can number.
+    InvalidDimensions {
+        width: u32,
+        height: u32,
+    },
+    // The grid exceeds `DENSE_CELL_LIMIT` and the run was not forced.
+    GridTooLarge {
+        cells: u64,
+        limit: u64,
+    },
+    // A seed pattern does not fit inside the grid.
+    PatternTooLarge {
+        pattern: (u32, u32),
+        grid: (u32, u32),
+    },
+    // A setting is out of range or cannot be combined with the others.
+    InvalidConfig(String),
+    // A rule, pattern, script, snapshot or bitmap could not be parsed.
+    ParseError(String),
+    // A snapshot, report or image could not be serialized or encoded for writing.
+    EncodeError(String),
+    IoError {
+        path: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl GameOfLifeError {
+    pub fn io(path: &Path, source: io::Error) -> Self {
+        GameOfLifeError::IoError {
+            path: path.to_path_buf(),
+            source,
+        }
+    }
+
+    // Process exit code for the binary: 2 for a bad configuration (like clap's usage
+    // errors), 3 for unparsable input and 4 for output that could not be encoded or
+    // written and other I/O failures.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            GameOfLifeError::InvalidDimensions { .. }
+            | GameOfLifeError::GridTooLarge { .. }
+            | GameOfLifeError::PatternTooLarge { .. }
+            | GameOfLifeError::InvalidConfig(_) => 2,
+            GameOfLifeError::ParseError(_) => 3,
+            GameOfLifeError::EncodeError(_) | GameOfLifeError::IoError { .. } => 4,
+        }
+    }
+}
+
+impl fmt::Display for GameOfLifeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameOfLifeError::InvalidDimensions { width, height } => {
+                write!(
+                    f,
+                    "invalid grid size {}x{}, both sides must be at least 1 and the grid at most {} cells",
+                    width,
+                    height,
+                    u32::MAX
+                )
+            }
+            GameOfLifeError::GridTooLarge { cells, limit } => write!(
+                f,
+                "grid has {} cells, more than the {} cell limit for dense spawning \
+                 (one entity per cell, there is no sparse mode); use a smaller grid or pass --force",
+                cells, limit
+            ),
+            GameOfLifeError::PatternTooLarge { pattern, grid } => write!(
+                f,
+                "pattern is {}x{} but the grid is only {}x{}",
+                pattern.0, pattern.1, grid.0, grid.1
+            ),
+            GameOfLifeError::InvalidConfig(message) => write!(f, "{}", message),
+            GameOfLifeError::ParseError(message) => write!(f, "{}", message),
+            GameOfLifeError::EncodeError(message) => write!(f, "{}", message),
+            GameOfLifeError::IoError { path, source } => {
+                write!(f, "{}: {}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GameOfLifeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GameOfLifeError::IoError { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::script::parse_script;
+    use super::super::{initialize, load_rle, parse_rle, RunOptions, SimConfig};
+    use super::*;
+
+    #[test]
+    fn test_error_variants() {
+        let err = initialize(SimConfig {
+            width: Some(0),
+            height: Some(5),
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            GameOfLifeError::InvalidDimensions {
+                width: 0,
+                height: 5
+            }
+        ));
+
+        let options = RunOptions {
+            pattern: Some(parse_rle("bo$2bo$3o!").unwrap()),
+            ..Default::default()
+        };
+        let err = initialize(SimConfig {
+            width: Some(2),
+            height: Some(2),
+            options,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            GameOfLifeError::PatternTooLarge {
+                pattern: (3, 3),
+                grid: (2, 2)
+            }
+        ));
+
+        assert!(matches!(
+            parse_rle("2q!"),
+            Err(GameOfLifeError::ParseError(_))
+        ));
+        assert!(matches!(
+            parse_script("1 flip 0 0"),
+            Err(GameOfLifeError::ParseError(_))
+        ));
+
+        let err = load_rle(Path::new("/nonexistent/pattern.rle")).unwrap_err();
+        assert!(matches!(err, GameOfLifeError::IoError { .. }));
+        assert_eq!(err.exit_code(), 4);
+        assert!(err.to_string().starts_with("/nonexistent/pattern.rle"));
+
+        let err = GameOfLifeError::EncodeError("cannot encode snapshot".to_string());
+        assert_eq!(err.exit_code(), 4);
+        assert_eq!(err.to_string(), "cannot encode snapshot");
+    }
+}
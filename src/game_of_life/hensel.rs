@@ -0,0 +1,190 @@
+use bevy_ecs::prelude::*;
+use std::str::FromStr;
+
+// Representative neighbor masks for the Hensel letters supported so far, using the
+// `NEIGHBOR_OFFSETS` bit order (0 = N, clockwise to 7 = NW).
+const LETTERS: [(u32, char, u8); 8] = [
+    (1, 'c', 0b0000_0010), // NE
+    (1, 'e', 0b0000_0001), // N
+    (2, 'c', 0b1000_0010), // NW, NE
+    (2, 'e', 0b0000_0101), // N, E
+    (2, 'k', 0b0000_1001), // N, SE
+    (2, 'a', 0b0000_0011), // N, NE
+    (2, 'i', 0b0001_0001), // N, S
+    (2, 'n', 0b0010_0010), // NE, SW
+];
+
+fn rotate(mask: u8) -> u8 {
+    mask.rotate_left(2)
+}
+
+fn reflect(mask: u8) -> u8 {
+    // Mirror left-right: N and S stay, NE<->NW, E<->W, SE<->SW.
+    const MIRROR: [usize; 8] = [0, 7, 6, 5, 4, 3, 2, 1];
+    (0..8)
+        .filter(|bit| mask & (1 << bit) != 0)
+        .fold(0, |out, bit| out | 1 << MIRROR[bit])
+}
+
+// Every rotation and reflection of `mask`.
+fn symmetries(mask: u8) -> Vec<u8> {
+    let mut masks = Vec::with_capacity(8);
+    let mut current = mask;
+    for _ in 0..4 {
+        masks.push(current);
+        masks.push(reflect(current));
+        current = rotate(current);
+    }
+    masks
+}
+
+fn letter_masks(count: u32, letter: char) -> Option<Vec<u8>> {
+    LETTERS
+        .iter()
+        .find(|(n, l, _)| *n == count && *l == letter)
+        .map(|(_, _, mask)| symmetries(*mask))
+}
+
+// Parses one B or S part such as `2c3` or `2-a` into a table indexed by neighbor mask.
+fn parse_part(part: &str, rule: &str) -> Result<[bool; 256], String> {
+    let mut table = [false; 256];
+    let mut chars = part.chars().peekable();
+    while let Some(c) = chars.next() {
+        let count = c
+            .to_digit(10)
+            .filter(|n| *n <= 8)
+            .ok_or_else(|| format!("invalid neighbor count {:?} in rule {:?}", c, rule))?;
+
+        let negate = chars.next_if_eq(&'-').is_some();
+        let mut letters = Vec::new();
+        while let Some(letter) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+            letters.push(letter);
+        }
+
+        let mut selected = [false; 256];
+        for letter in &letters {
+            let masks = letter_masks(count, *letter).ok_or_else(|| {
+                format!(
+                    "unsupported Hensel letter {}{} in rule {:?}",
+                    count, letter, rule
+                )
+            })?;
+            for mask in masks {
+                selected[mask as usize] = true;
+            }
+        }
+
+        for mask in 0..=255u8 {
+            if mask.count_ones() != count {
+                continue;
+            }
+            let included = letters.is_empty() || selected[mask as usize] != negate;
+            if included {
+                table[mask as usize] = true;
+            }
+        }
+    }
+    Ok(table)
+}
+
+// Non-totalistic rule in (a subset of) Hensel notation, e.g. `B2c3/S23`. Birth and
+// survival depend on the arrangement of live neighbors, not just their number, so
+// the neighbor pass must run with `RuleSymmetry::Anisotropic` to provide masks.
+// Letters are supported for 1 and 2 neighbors; other counts accept digits only.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct HenselRules {
+    pub birth: [bool; 256],
+    pub survival: [bool; 256],
+}
+
+impl HenselRules {
+    pub fn born(&self, mask: u8) -> bool {
+        self.birth[mask as usize]
+    }
+
+    pub fn survives(&self, mask: u8) -> bool {
+        self.survival[mask as usize]
+    }
+}
+
+impl FromStr for HenselRules {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim().to_ascii_lowercase();
+        let mut birth = None;
+        let mut survival = None;
+        for part in rule.split('/') {
+            if let Some(rest) = part.strip_prefix('b') {
+                birth = Some(parse_part(rest, s)?);
+            } else if let Some(rest) = part.strip_prefix('s') {
+                survival = Some(parse_part(rest, s)?);
+            } else {
+                return Err(format!(
+                    "invalid rulestring {:?}, expected e.g. B2c3/S23",
+                    s
+                ));
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(HenselRules { birth, survival }),
+            _ => Err(format!("rulestring {:?} needs both B and S parts", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetry_classes() {
+        // Each 1- and 2-neighbor class has the expected number of arrangements,
+        // and together they cover every mask exactly once.
+        let mut seen = [0; 256];
+        for (_, _, mask) in LETTERS {
+            let mut class = symmetries(mask);
+            class.sort();
+            class.dedup();
+            for m in class {
+                seen[m as usize] += 1;
+            }
+        }
+        for mask in 0..=255u8 {
+            let expected = u32::from(matches!(mask.count_ones(), 1 | 2));
+            assert_eq!(seen[mask as usize], expected, "mask {:08b}", mask);
+        }
+    }
+
+    #[test]
+    fn test_parse_letters() {
+        let rules: HenselRules = "B2c/S1e".parse().unwrap();
+        assert!(rules.born(0b1000_0010));
+        assert!(rules.born(0b0010_1000));
+        assert!(!rules.born(0b0000_0101));
+        assert!(rules.survives(0b0000_0100));
+        assert!(!rules.survives(0b0000_1000));
+
+        let rules: HenselRules = "B2-a3/S".parse().unwrap();
+        assert!(!rules.born(0b0000_0011));
+        assert!(rules.born(0b0001_0001));
+        assert!(rules.born(0b0000_0111));
+    }
+
+    #[test]
+    fn test_parse_totalistic_digits() {
+        let rules: HenselRules = "B3/S23".parse().unwrap();
+        for mask in 0..=255u8 {
+            assert_eq!(rules.born(mask), mask.count_ones() == 3);
+            assert_eq!(rules.survives(mask), matches!(mask.count_ones(), 2 | 3));
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("B3q/S23".parse::<HenselRules>().is_err());
+        assert!("B2x/S".parse::<HenselRules>().is_err());
+        assert!("B2c".parse::<HenselRules>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use bevy_ecs::prelude::*;
+use std::collections::{HashSet, VecDeque};
+
+use super::Background;
+
+// One remembered generation: its live cells and, under B0 rules, the background.
+pub type Frame = (u32, HashSet<(i32, i32)>, Option<Background>);
+
+// The last `limit` generations, oldest first. Pushing past the limit drops the
+// oldest, so memory stays bounded however long the run is.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    limit: usize,
+    frames: VecDeque<Frame>,
+}
+
+impl History {
+    pub fn new(limit: usize) -> Self {
+        History {
+            limit,
+            frames: VecDeque::new(),
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn push(
+        &mut self,
+        generation: u32,
+        live: HashSet<(i32, i32)>,
+        background: Option<Background>,
+    ) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.frames.len() == self.limit {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((generation, live, background));
+    }
+
+    // Forgets every generation, keeping the limit.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    // Removes and returns the newest generation.
+    pub fn pop(&mut self) -> Option<Frame> {
+        self.frames.pop_back()
+    }
+
+    // Generations held, oldest first.
+    pub fn generations(&self) -> impl Iterator<Item = u32> + '_ {
+        self.frames.iter().map(|&(generation, ..)| generation)
+    }
+
+    // How many generations ago `live` was last seen over the same background, if it
+    // is still in the window and no later than `generation`.
+    pub fn period(
+        &self,
+        generation: u32,
+        live: &HashSet<(i32, i32)>,
+        background: Option<Background>,
+    ) -> Option<u32> {
+        self.frames
+            .iter()
+            .rev()
+            .find(|(_, earlier, outside)| earlier == live && *outside == background)
+            .and_then(|&(earlier, ..)| generation.checked_sub(earlier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interactive::Session;
+    use super::super::{build_schedule, setup_world, spawn_pattern_cells, Rules};
+    use super::*;
+
+    #[test]
+    fn test_history_keeps_last_n_and_reverses_within_them() {
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut world = setup_world(12, 12, Rules::default());
+        spawn_pattern_cells(&mut world, 12, 12, &glider);
+        world.insert_resource(History::new(4));
+        let mut schedule = build_schedule();
+        let mut session = Session::new(&mut world, &mut schedule);
+        let mut states = Vec::new();
+        for _ in 0..10 {
+            states.push(session.live_cells());
+            session.step();
+        }
+
+        let history = session.history();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history.generations().collect::<Vec<_>>(), vec![6, 7, 8, 9]);
+        for generation in (6..10).rev() {
+            assert!(session.reverse());
+            assert_eq!(session.generation(), generation);
+            assert_eq!(session.live_cells(), states[generation as usize]);
+        }
+        assert!(!session.reverse());
+        assert_eq!(session.generation(), 6);
+    }
+
+    #[test]
+    fn test_reverse_restores_b0_background() {
+        let mut world = setup_world(6, 5, "B0/S5".parse().unwrap());
+        spawn_pattern_cells(&mut world, 6, 5, &HashSet::new());
+        let mut schedule = build_schedule();
+        let mut session = Session::new(&mut world, &mut schedule);
+        session.step();
+        assert_eq!(session.live_cells().len(), 30);
+        session.step();
+        assert!(session.live_cells().is_empty());
+
+        assert!(session.reverse());
+        assert_eq!(session.live_cells().len(), 30);
+        // With the background alive again every cell sees eight neighbors and the
+        // next step empties the grid, as it did the first time.
+        session.step();
+        assert!(session.live_cells().is_empty());
+        assert!(session.reverse());
+        assert!(session.reverse());
+        session.step();
+        assert_eq!(session.live_cells().len(), 30);
+    }
+
+    #[test]
+    fn test_history_finds_period_in_window() {
+        let mut history = History::new(3);
+        let horizontal = HashSet::from([(0, 1), (1, 1), (2, 1)]);
+        let vertical = HashSet::from([(1, 0), (1, 1), (1, 2)]);
+        history.push(0, horizontal.clone(), None);
+        history.push(1, vertical.clone(), None);
+        assert_eq!(history.period(2, &horizontal, None), Some(2));
+        assert_eq!(history.period(2, &vertical, None), Some(1));
+        assert_eq!(history.period(2, &HashSet::new(), None), None);
+        assert_eq!(history.period(0, &vertical, None), None);
+        let lit = Some(Background { alive: true });
+        assert_eq!(history.period(2, &vertical, lit), None);
+        assert!(History::new(0).is_empty());
+        assert!(History::new(usize::MAX).is_empty());
+    }
+}
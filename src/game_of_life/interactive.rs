@@ -0,0 +1,268 @@
+use bevy_ecs::prelude::*;
+use bevy_ecs::system::RunSystemOnce;
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use super::{
+    live_cells, rebuild_cell_positions, render, save_snapshot, update_neighbors_brute_force_system,
+    ActivityRegions, Background, CellBundle, CellsChanged, GenerationCounter, Grid, History, Index,
+    LastChanged, Position, RunSummary, State, TerminationReason,
+};
+
+// How many earlier generations `r` can step back through, unless the world already
+// has a `History`.
+pub const HISTORY_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Step,
+    Jump(u32),
+    Reverse,
+    Save(Option<PathBuf>),
+    Quit,
+}
+
+impl std::str::FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.split_whitespace().collect::<Vec<_>>();
+        match parts[..] {
+            [] => Ok(Command::Step),
+            ["g", n] => n
+                .parse()
+                .map(Command::Jump)
+                .map_err(|_| format!("invalid step count {:?}", n)),
+            ["r"] => Ok(Command::Reverse),
+            ["s"] => Ok(Command::Save(None)),
+            ["s", path] => Ok(Command::Save(Some(PathBuf::from(path)))),
+            ["q"] => Ok(Command::Quit),
+            _ => Err(format!("unknown command {:?}", s.trim())),
+        }
+    }
+}
+
+// Steps a world on demand, keeping earlier generations in the world's `History` so
+// it can also step backwards.
+pub struct Session<'a> {
+    world: &'a mut World,
+    schedule: &'a mut Schedule,
+}
+
+impl<'a> Session<'a> {
+    pub fn new(world: &'a mut World, schedule: &'a mut Schedule) -> Self {
+        world.get_resource_or_insert_with(|| History::new(HISTORY_LIMIT));
+        Session { world, schedule }
+    }
+
+    pub fn history(&self) -> &History {
+        self.world.resource::<History>()
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.world.resource::<GenerationCounter>().0
+    }
+
+    pub fn live_cells(&mut self) -> HashSet<(i32, i32)> {
+        live_cells(self.world)
+    }
+
+    pub fn step(&mut self) {
+        let (generation, live) = (self.generation(), self.live_cells());
+        let background = self.world.get_resource::<Background>().copied();
+        self.world
+            .resource_mut::<History>()
+            .push(generation, live, background);
+        self.schedule.run(self.world);
+    }
+
+    pub fn jump(&mut self, steps: u32) {
+        for _ in 0..steps {
+            self.step();
+        }
+    }
+
+    // Restores the previous generation. Returns false once the history is exhausted.
+    pub fn reverse(&mut self) -> bool {
+        let Some((generation, live, background)) = self.world.resource_mut::<History>().pop()
+        else {
+            return false;
+        };
+
+        let mut missing = live.clone();
+        let mut query = self.world.query::<(&Position, &mut State)>();
+        for (pos, mut state) in query.iter_mut(self.world) {
+            state.0 = missing.remove(&(pos.x, pos.y));
+        }
+        // Cells compaction has despawned since this generation need their entities
+        // back.
+        let width = self.world.resource::<Grid>().width;
+        self.world
+            .spawn_batch(missing.into_iter().map(move |(x, y)| CellBundle {
+                position: Position { x, y },
+                state: State(true),
+                last_changed: LastChanged(generation),
+                index: Index(y as u32 * width + x as u32),
+                ..Default::default()
+            }));
+        match background {
+            Some(background) => self.world.insert_resource(background),
+            None => {
+                self.world.remove_resource::<Background>();
+            }
+        }
+        self.world.resource_mut::<GenerationCounter>().0 = generation;
+        self.world.resource_mut::<CellsChanged>().0 = true;
+        self.world.remove_resource::<ActivityRegions>();
+        self.world.run_system_once(rebuild_cell_positions);
+        self.world
+            .run_system_once(update_neighbors_brute_force_system);
+        true
+    }
+
+    // Applies one command, returning false when the session should end.
+    pub fn apply(&mut self, command: Command) -> bool {
+        match command {
+            Command::Step => self.step(),
+            Command::Jump(steps) => self.jump(steps),
+            Command::Reverse => {
+                if !self.reverse() {
+                    println!("No earlier generation in the history");
+                }
+            }
+            Command::Save(path) => {
+                let path = path.unwrap_or_else(|| {
+                    PathBuf::from(format!("snapshot_{:05}.json", self.generation()))
+                });
+                match save_snapshot(self.world, &path) {
+                    Ok(()) => println!("Saved snapshot to {}", path.display()),
+                    Err(err) => eprintln!("Failed to save snapshot: {}", err),
+                }
+            }
+            Command::Quit => return false,
+        }
+        true
+    }
+
+    fn print_frame(&mut self) {
+        let live = self.live_cells();
+        println!("Generation {:?}:", self.generation());
+        println!("{}", render(&live, self.world.resource::<Grid>()));
+    }
+}
+
+// Reads commands from stdin until `q` or end of input, printing the grid after each.
+// Either way the user ended the session, so the summary's reason is `Quit`.
+pub fn run(world: &mut World, schedule: &mut Schedule) -> RunSummary {
+    let first = world.resource::<GenerationCounter>().0;
+    let mut session = Session::new(world, schedule);
+    session.print_frame();
+    println!("Enter: step, g N: jump N steps, r: reverse, s [file]: save snapshot, q: quit");
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        match line.parse::<Command>() {
+            Ok(command) => {
+                let show = !matches!(command, Command::Save(_) | Command::Quit);
+                if !session.apply(command) {
+                    break;
+                }
+                if show {
+                    session.print_frame();
+                }
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+
+    let generation = session.generation();
+    let population = session.live_cells().len();
+    RunSummary {
+        generations: generation.saturating_sub(first),
+        population,
+        reason: TerminationReason::Quit,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{build_schedule, setup_world, spawn_pattern_cells, Rules};
+    use super::*;
+
+    #[test]
+    fn test_reverse_restores_previous_generation() {
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut world = setup_world(8, 8, Rules::default());
+        spawn_pattern_cells(&mut world, 8, 8, &glider);
+        let mut schedule = build_schedule();
+        let mut session = Session::new(&mut world, &mut schedule);
+
+        session.step();
+        let first = session.live_cells();
+        session.jump(2);
+        assert_eq!(session.generation(), 3);
+
+        assert!(session.reverse());
+        assert!(session.reverse());
+        assert_eq!(session.generation(), 1);
+        assert_eq!(session.live_cells(), first);
+
+        // Stepping again from the restored state reproduces the same future.
+        session.step();
+        session.step();
+        let mut replay = setup_world(8, 8, Rules::default());
+        spawn_pattern_cells(&mut replay, 8, 8, &glider);
+        let mut replay_schedule = build_schedule();
+        for _ in 0..3 {
+            replay_schedule.run(&mut replay);
+        }
+        assert_eq!(session.live_cells(), live_cells(&mut replay));
+
+        assert!(session.reverse());
+        assert!(session.reverse());
+        assert!(session.reverse());
+        assert_eq!(session.live_cells(), glider);
+        assert!(!session.reverse());
+    }
+
+    #[test]
+    fn test_reverse_respawns_compacted_cells() {
+        use super::super::Compaction;
+
+        let blinker = HashSet::from([(2, 1), (2, 2), (2, 3)]);
+        let mut world = setup_world(6, 6, Rules::default());
+        spawn_pattern_cells(&mut world, 6, 6, &blinker);
+        world.insert_resource(Compaction::every(1));
+        let mut schedule = build_schedule();
+        let mut session = Session::new(&mut world, &mut schedule);
+
+        session.jump(2);
+        assert!(session.reverse());
+        assert!(session.reverse());
+        assert_eq!(session.live_cells(), blinker);
+
+        // The restored blinker keeps oscillating, so its neighbors are right too.
+        session.step();
+        assert_eq!(
+            session.live_cells(),
+            HashSet::from([(1, 2), (2, 2), (3, 2)])
+        );
+        session.step();
+        assert_eq!(session.live_cells(), blinker);
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!("".parse(), Ok(Command::Step));
+        assert_eq!("g 10".parse(), Ok(Command::Jump(10)));
+        assert_eq!("r".parse(), Ok(Command::Reverse));
+        assert_eq!(
+            "s out.json".parse(),
+            Ok(Command::Save(Some("out.json".into())))
+        );
+        assert!("g ten".parse::<Command>().is_err());
+    }
+}
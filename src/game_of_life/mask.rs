@@ -0,0 +1,57 @@
+use bevy_ecs::prelude::*;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::GameOfLifeError;
+
+// Coordinates cells may live on. Cells outside the mask are held dead and never
+// count as anyone's neighbor, which allows non-rectangular arenas.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct GridMask {
+    pub playable: HashSet<(i32, i32)>,
+}
+
+impl GridMask {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.playable.contains(&(x, y))
+    }
+}
+
+// Plaintext mask: one row per line, `.` or space marks a blocked cell and any other
+// character a playable one. Lines starting with `!` are comments.
+pub fn parse_mask(text: &str) -> GridMask {
+    let playable = text
+        .lines()
+        .filter(|line| !line.starts_with('!'))
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c != '.' && *c != ' ')
+                .map(move |(x, _)| (x as i32, y as i32))
+        })
+        .collect();
+    GridMask { playable }
+}
+
+pub fn load_mask(path: &Path) -> Result<GridMask, GameOfLifeError> {
+    let text = fs::read_to_string(path).map_err(|err| GameOfLifeError::io(path, err))?;
+    Ok(parse_mask(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mask() {
+        let mask = parse_mask("! ring\n.#.\n# #\n.#.\n");
+        assert_eq!(
+            mask.playable,
+            HashSet::from([(1, 0), (0, 1), (2, 1), (1, 2)])
+        );
+        assert!(mask.contains(0, 1));
+        assert!(!mask.contains(1, 1));
+    }
+}
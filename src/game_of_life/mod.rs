@@ -1,7 +1,61 @@
 use bevy_ecs::prelude::*;
+use bevy_ecs::system::RunSystemOnce;
 use integer_sqrt::IntegerSquareRoot;
-use std::collections::HashMap;
-use std::time::Instant;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub mod batch;
+pub mod boundary;
+pub mod collision;
+pub mod config;
+pub mod error;
+pub mod hensel;
+pub mod history;
+pub mod interactive;
+pub mod mask;
+pub mod objects;
+pub mod output;
+pub mod pbm;
+#[cfg(feature = "image")]
+pub mod png;
+pub mod progress;
+pub mod record;
+pub mod rle;
+pub mod rules;
+pub mod script;
+pub mod simulation;
+pub mod snapshot;
+pub mod soa;
+pub mod stability;
+
+pub use boundary::BoundaryMode;
+pub use error::GameOfLifeError;
+pub use hensel::HenselRules;
+pub use history::History;
+pub use mask::{load_mask, GridMask};
+pub use objects::Connectivity;
+pub use output::{step_with_diff, Diff, OutputSink, StepResult};
+pub use pbm::save_pbm;
+pub use progress::ProgressBar;
+pub use record::{RecordSink, Recording};
+pub use rle::{load_rle, parse_rle, Pattern};
+pub use rules::{next_cell_state, RuleRegion, RuleRegions, RuleSymmetry, Rules};
+pub use script::{load_script, Script};
+pub use simulation::{GenerationsIter, Simulation, SimulationBuilder};
+pub use snapshot::{load_snapshot, save_snapshot, Snapshot};
+pub use soa::{Backend, SoaGrid};
+pub use stability::{generations_to_stabilize, survey, PeriodDetector, SoupResult};
 
 #[derive(Component, PartialEq, Eq, Copy, Clone, Debug, Default)]
 pub struct Position {
@@ -10,9 +64,34 @@ pub struct Position {
 }
 
 impl Position {
-    pub fn distance(self, p: Position) -> i32 {
-        let x = self.x - p.x;
-        let y = self.y - p.y;
+    // Euclidean distance rounded down to an integer. Prefer `distance_squared` when
+    // comparing distances, since it is exact.
+    pub fn distance(self, p: Position) -> i64 {
+        self.distance_squared(p).integer_sqrt()
+    }
+
+    // Worked out in `i64`, which any two cells of a grid fit in. Saturates only for
+    // points most of the `i32` range apart on both axes.
+    pub fn distance_squared(self, p: Position) -> i64 {
+        let x = self.x as i64 - p.x as i64;
+        let y = self.y as i64 - p.y as i64;
+
+        x.saturating_mul(x).saturating_add(y.saturating_mul(y))
+    }
+
+    pub fn distance_f64(self, p: Position) -> f64 {
+        (self.distance_squared(p) as f64).sqrt()
+    }
+
+    // Like `distance`, but on a `width` x `height` torus, where the shorter way round
+    // each axis may cross an edge.
+    pub fn toroidal_distance(self, p: Position, width: u32, height: u32) -> i64 {
+        let wrap = |d: i64, size: u32| {
+            let d = d.rem_euclid(size as i64);
+            d.min(size as i64 - d)
+        };
+        let x = wrap(self.x as i64 - p.x as i64, width);
+        let y = wrap(self.y as i64 - p.y as i64, height);
 
         (x * x + y * y).integer_sqrt()
     }
@@ -37,20 +116,436 @@ pub struct Grid {
     pub height: u32,
 }
 
+impl Grid {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32
+    }
+}
+
 #[derive(Component, Debug, Default, PartialEq, Eq)]
 pub struct Neighbors(u8);
 
+// Occupied neighbor directions, bit `i` standing for `NEIGHBOR_OFFSETS[i]`. Only
+// maintained while `RuleSymmetry::Anisotropic` is active.
+#[derive(Component, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct NeighborMask(pub u8);
+
+// Generation in which the cell last flipped state, 0 if it still has its seeded state.
+#[derive(Component, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct LastChanged(pub u32);
+
+// Number of computed generations in which the cell was alive. Unlike `LastChanged`
+// this counts live steps rather than flips. Lost for dead cells removed by compaction.
+#[derive(Component, Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct TimesAlive(pub u32);
+
+// Row-major grid index (`y * width + x`) assigned at spawn, giving cells a fixed
+// order independent of how the ECS happens to store them.
+#[derive(Component, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Index(pub u32);
+
 #[derive(Resource)]
 pub struct Generations(u32);
 
-#[derive(Resource)]
+// Number of generations simulated so far.
+#[derive(Resource, Debug, Default, PartialEq, Eq)]
+pub struct GenerationCounter(pub u32);
+
+// Moore neighborhood offsets, clockwise starting from north (y grows downward).
+pub const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+];
+
+// Set by `--quiet`: lines printed with `status!` are dropped.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+// `println!` for timings, summaries and other status lines a pipeline can do
+// without. Rendered frames, requested reports and errors are printed directly.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::game_of_life::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+
+// Which of the eight surrounding cells count as neighbors. Offsets outside the
+// neighborhood get weight 0.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Neighborhood {
+    // All eight.
+    #[default]
+    Moore,
+    // The four orthogonal neighbors.
+    VonNeumann,
+    // The four diagonal neighbors.
+    DiagonalOnly,
+}
+
+impl Neighborhood {
+    pub fn includes(self, (dx, dy): (i32, i32)) -> bool {
+        match self {
+            Neighborhood::Moore => true,
+            Neighborhood::VonNeumann => dx == 0 || dy == 0,
+            Neighborhood::DiagonalOnly => dx != 0 && dy != 0,
+        }
+    }
+
+    // `weights` with the offsets outside the neighborhood zeroed.
+    pub fn weights(self, weights: [u8; 8]) -> [u8; 8] {
+        std::array::from_fn(|i| {
+            if self.includes(NEIGHBOR_OFFSETS[i]) {
+                weights[i]
+            } else {
+                0
+            }
+        })
+    }
+}
+
+impl std::str::FromStr for Neighborhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "moore" => Ok(Neighborhood::Moore),
+            "von-neumann" => Ok(Neighborhood::VonNeumann),
+            "diagonal" => Ok(Neighborhood::DiagonalOnly),
+            _ => Err(format!(
+                "neighborhood must be moore, von-neumann or diagonal, got {:?}",
+                s
+            )),
+        }
+    }
+}
+
+// Present when a live cell counts itself as one of its neighbors, for
+// inner-totalistic rules. Births are unaffected since the cell is dead.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct IncludeSelf;
+
+// State of the cells beyond the edges of a dead-boundary grid. Under a B0 rule the
+// empty background is born everywhere at once, and from then on lives or dies as a
+// cell with every neighbor alive would, so it can flip every generation. Cells on
+// the grid edges count it as neighbors. Only inserted for rules with B0.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Background {
+    pub alive: bool,
+}
+
+impl Background {
+    pub fn for_rules(rules: &Rules) -> Option<Self> {
+        rules.born(0).then_some(Background::default())
+    }
+
+    // The background a generation on, where `full_count` is what a cell sees when all
+    // of its neighbors are alive.
+    pub fn next(self, rules: &Rules, full_count: u8) -> Self {
+        let count = if self.alive { full_count } else { 0 };
+        Background {
+            alive: next_cell_state(self.alive, count, rules),
+        }
+    }
+}
+
+// Per-offset weights, in `NEIGHBOR_OFFSETS` order, summed into `Neighbors` for
+// weighted-life rules. Without this resource every neighbor counts as 1.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NeighborWeights([u8; 8]);
+
+impl NeighborWeights {
+    // Rejects weights summing to more than 8, since `Rules` only has entries for
+    // counts up to 8 and a larger sum could never be born or survive.
+    pub fn new(weights: [u8; 8]) -> Result<Self, String> {
+        let total = weights.iter().map(|&w| w as u32).sum::<u32>();
+        if total > 8 {
+            return Err(format!(
+                "neighbor weights {:?} sum to {}, more than the 8 a rule can count to",
+                weights, total
+            ));
+        }
+        Ok(NeighborWeights(weights))
+    }
+
+    pub fn weights(self) -> [u8; 8] {
+        self.0
+    }
+}
+
+impl Default for NeighborWeights {
+    fn default() -> Self {
+        NeighborWeights([1; 8])
+    }
+}
+
+// Set from outside the simulation (e.g. a Ctrl-C handler) to stop the run loop.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct Interrupted(pub Arc<AtomicBool>);
+
+impl Interrupted {
+    pub fn set(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+// Lets systems end a run early: `run_generations` stops once `should_stop` is set.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Control {
+    pub should_stop: bool,
+}
+
+// Wall-clock budget for `run_generations`; no further steps start once it is spent.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeLimit(pub Duration);
+
+// Cell states of the bounded grid in a flat row-major buffer (`y * width + x`), so
+// neighbor lookups are plain indexing instead of hashing.
+#[derive(Resource, Default)]
 struct CellPositions {
-    map: HashMap<(i32, i32), bool>,
+    cells: Vec<bool>,
+    width: u32,
+    height: u32,
+}
+
+impl CellPositions {
+    fn is_alive(&self, x: i32, y: i32) -> bool {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 {
+            return false;
+        }
+        self.cells[(y as u32 * self.width + x as u32) as usize]
+    }
+
+    // `weighted_neighbors` over this grid's cells.
+    fn neighborhood(
+        &self,
+        x: i32,
+        y: i32,
+        boundary: BoundaryMode,
+        weights: [u8; 8],
+        mask: Option<&GridMask>,
+        outside: bool,
+    ) -> (u8, u8) {
+        let grid = Grid {
+            width: self.width,
+            height: self.height,
+        };
+        weighted_neighbors(
+            |x, y| self.is_alive(x, y),
+            x,
+            y,
+            &grid,
+            boundary,
+            weights,
+            mask,
+            outside,
+        )
+    }
+}
+
+// Weighted live neighbor count of (x, y) and the directions they lie in, as bits in
+// `NEIGHBOR_OFFSETS` order. Neighbors beyond a dead boundary are alive when `outside`
+// is. The neighbor system and `live_neighbors` both count through this.
+#[allow(clippy::too_many_arguments)]
+fn weighted_neighbors(
+    is_alive: impl Fn(i32, i32) -> bool,
+    x: i32,
+    y: i32,
+    grid: &Grid,
+    boundary: BoundaryMode,
+    weights: [u8; 8],
+    mask: Option<&GridMask>,
+    outside: bool,
+) -> (u8, u8) {
+    let mut count: u8 = 0;
+    let mut directions: u8 = 0;
+    for (i, cell) in boundary.neighbors(x, y, grid.width, grid.height) {
+        let weight = weights[i];
+        let Some((nx, ny)) = cell else {
+            if outside {
+                count = count.saturating_add(weight);
+                directions |= 1 << i;
+            }
+            continue;
+        };
+
+        if mask.is_some_and(|mask| !mask.contains(nx, ny)) {
+            continue;
+        }
+
+        if is_alive(nx, ny) {
+            count = count.saturating_add(weight);
+            directions |= 1 << i;
+        }
+    }
+    (count, directions)
 }
 
 #[derive(Resource)]
 struct CellsChanged(bool);
 
+// Writes a snapshot to `dir/autosave_NNNNN.json` every `every` generations and at
+// generation `last`, if set, so the final state is saved even off the interval.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct AutosaveConfig {
+    pub every: u32,
+    pub dir: PathBuf,
+    pub last: Option<u32>,
+}
+
+impl AutosaveConfig {
+    pub fn path(&self, generation: u32) -> PathBuf {
+        self.dir.join(format!("autosave_{:05}.json", generation))
+    }
+}
+
+// Despawns dead cell entities every `every` generations to reclaim memory once a
+// pattern has shrunk. After the first compaction, births on coordinates without an
+// entity spawn a new one.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Compaction {
+    pub every: u32,
+    pub compacted: bool,
+}
+
+impl Compaction {
+    pub fn every(every: u32) -> Self {
+        Compaction {
+            every,
+            compacted: false,
+        }
+    }
+}
+
+// Skips cells in regions of `region_size` squares where nothing has changed nearby.
+// A region stays awake for `keep_active` generations after a cell in it or in an
+// adjacent region last changed. 1 is the smallest value that never misses a change,
+// since a flip can only affect the next generation; 0 lets regions fall asleep
+// immediately and freezes the grid.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActivityConfig {
+    pub region_size: u32,
+    pub keep_active: u32,
+}
+
+impl Default for ActivityConfig {
+    fn default() -> Self {
+        ActivityConfig {
+            region_size: 8,
+            keep_active: 1,
+        }
+    }
+}
+
+// Generation each region last had a cell change in, and which regions are evaluated
+// in the coming generation.
+#[derive(Resource)]
+struct ActivityRegions {
+    region_size: u32,
+    columns: u32,
+    rows: u32,
+    last_change: Vec<u32>,
+    awake: Vec<bool>,
+}
+
+impl ActivityRegions {
+    fn new(grid: &Grid, region_size: u32, generation: u32) -> Self {
+        let region_size = region_size.max(1);
+        let columns = grid.width.div_ceil(region_size);
+        let rows = grid.height.div_ceil(region_size);
+        let regions = (columns * rows) as usize;
+        ActivityRegions {
+            region_size,
+            columns,
+            rows,
+            last_change: vec![generation; regions],
+            awake: vec![true; regions],
+        }
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let (column, row) = (x / self.region_size as i32, y / self.region_size as i32);
+        if x < 0 || y < 0 || column >= self.columns as i32 || row >= self.rows as i32 {
+            return None;
+        }
+        Some((row as u32 * self.columns + column as u32) as usize)
+    }
+
+    fn mark(&mut self, x: i32, y: i32, generation: u32) {
+        if let Some(index) = self.index(x, y) {
+            self.last_change[index] = generation;
+        }
+    }
+
+    fn is_awake(&self, x: i32, y: i32) -> bool {
+        self.index(x, y).is_none_or(|index| self.awake[index])
+    }
+}
+
+// Present in `--deterministic` runs: systems run one at a time on a single-threaded
+// executor and the neighbor pass iterates sequentially.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct Deterministic;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SystemTiming {
+    pub total: Duration,
+    pub calls: u32,
+}
+
+// Time spent in each core system, collected while this resource exists (`--profile`).
+#[derive(Resource, Debug, Default)]
+pub struct ProfileStats {
+    pub timings: BTreeMap<&'static str, SystemTiming>,
+}
+
+impl ProfileStats {
+    pub fn record(&mut self, system: &'static str, elapsed: Duration) {
+        let timing = self.timings.entry(system).or_default();
+        timing.total += elapsed;
+        timing.calls += 1;
+    }
+}
+
+impl std::fmt::Display for ProfileStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.timings.values().map(|t| t.total).sum::<Duration>();
+        writeln!(f, "System timings:")?;
+        for (system, timing) in &self.timings {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                timing.total.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            writeln!(
+                f,
+                "  {:<24} {:>12?} over {:>6} calls ({:.1}%)",
+                system, timing.total, timing.calls, share
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Component)]
 pub struct Alive;
 
@@ -62,33 +557,47 @@ pub struct CellBundle {
     pub position: Position,
     pub state: State,
     pub neighbors: Neighbors,
+    pub neighbor_mask: NeighborMask,
+    pub last_changed: LastChanged,
+    pub times_alive: TimesAlive,
+    pub index: Index,
 }
 
 fn rebuild_cell_positions(
     query: Query<(&Position, &State)>,
+    grid: Res<Grid>,
     mut cell_positions: ResMut<CellPositions>,
     mut cells_changed: ResMut<CellsChanged>,
+    profile: Option<ResMut<ProfileStats>>,
 ) {
     if !cells_changed.0 {
         return;
     }
 
     let start = Instant::now();
-    cell_positions.map.clear();
+    cell_positions.width = grid.width;
+    cell_positions.height = grid.height;
+    cell_positions.cells.clear();
+    cell_positions
+        .cells
+        .resize((grid.width * grid.height) as usize, false);
     for (pos, state) in query.iter() {
-        cell_positions.map.insert((pos.x, pos.y), state.0);
+        if pos.x >= 0 && pos.x < grid.width as i32 && pos.y >= 0 && pos.y < grid.height as i32 {
+            let index = (pos.y as u32 * grid.width + pos.x as u32) as usize;
+            cell_positions.cells[index] = state.0;
+        }
     }
 
     cells_changed.0 = false;
 
-    let duration = start.elapsed();
-    //println!("Building cell positions took {:?}", duration);
+    if let Some(mut profile) = profile {
+        profile.record("rebuild_cell_positions", start.elapsed());
+    }
 }
 
 // Cell entity - cell is a tuple of Position, State, and Neighbors
 
 pub fn spawn_cells(world: &mut World, width: u32, height: u32) {
-    let start = Instant::now();
     let cells_to_spawn_count = width * height;
     let to_spawn = (0..cells_to_spawn_count).map(|i| {
         let x = i % width;
@@ -101,18 +610,15 @@ pub fn spawn_cells(world: &mut World, width: u32, height: u32) {
         CellBundle {
             position,
             state,
+            index: Index(i),
             ..Default::default()
         }
     });
 
     world.spawn_batch(to_spawn);
-    println!("Spawning {:?} cells", cells_to_spawn_count);
-    let duration = start.elapsed();
-    println!("Spawning cells took {:?}", duration);
 }
 
 pub fn spawn_block_cells(world: &mut World, width: u32, height: u32) {
-    let start = Instant::now();
     let cells_to_spawn_count = width * height;
     let to_spawn = (0..cells_to_spawn_count).map(|i| {
         let x = i % width;
@@ -122,25 +628,18 @@ pub fn spawn_block_cells(world: &mut World, width: u32, height: u32) {
             y: y as i32,
         };
         let state = State(true);
-        println!(
-            "Spawning block cell at position {:?}, with state {:?}",
-            position, state
-        );
         CellBundle {
             position,
             state,
+            index: Index(i),
             ..Default::default()
         }
     });
 
     world.spawn_batch(to_spawn);
-    println!("Spawning {:?} cells", cells_to_spawn_count);
-    let duration = start.elapsed();
-    println!("Spawning cells took {:?}", duration);
 }
 
 pub fn spawn_beehive_cells(world: &mut World, width: u32, height: u32) {
-    let start = Instant::now();
     let cells_to_spawn_count = width * height;
     let to_spawn = (0..cells_to_spawn_count).map(|i| {
         let x = i % width;
@@ -161,18 +660,15 @@ pub fn spawn_beehive_cells(world: &mut World, width: u32, height: u32) {
         CellBundle {
             position,
             state,
+            index: Index(i),
             ..Default::default()
         }
     });
 
     world.spawn_batch(to_spawn);
-    println!("Spawning {:?} cells", cells_to_spawn_count);
-    let duration = start.elapsed();
-    println!("Spawning cells took {:?}", duration);
 }
 
-fn spawn_blinker_cells(world: &mut World, width: u32, height: u32) {
-    let start = Instant::now();
+pub fn spawn_blinker_cells(world: &mut World, width: u32, height: u32) {
     let cells_to_spawn_count = width * height;
     let to_spawn = (0..cells_to_spawn_count).map(|i| {
         let x = i % width;
@@ -190,393 +686,4119 @@ fn spawn_blinker_cells(world: &mut World, width: u32, height: u32) {
         CellBundle {
             position,
             state,
+            index: Index(i),
             ..Default::default()
         }
     });
 
     world.spawn_batch(to_spawn);
-    println!("Spawning {:?} cells", cells_to_spawn_count);
-    let duration = start.elapsed();
-    println!("Spawning cells took {:?}", duration);
 }
 
-fn update_neighbors_brute_force_system(
-    mut query: Query<(&mut Neighbors, &Position)>,
-    grid: Res<Grid>,
-    cell_positions: Res<CellPositions>,
-) {
-    let start = Instant::now();
-    query.par_iter_mut().for_each(|(mut neighbors, pos)| {
-        let mut count = 0;
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-
-                let x = pos.x + dx;
-                let y = pos.y + dy;
+pub fn spawn_empty_cells(world: &mut World, width: u32, height: u32) {
+    let cells_to_spawn_count = width * height;
+    let to_spawn = (0..cells_to_spawn_count).map(|i| {
+        let x = i % width;
+        let y = i / width;
+        let position = Position {
+            x: x as i32,
+            y: y as i32,
+        };
+        CellBundle {
+            position,
+            state: State(false),
+            index: Index(i),
+            ..Default::default()
+        }
+    });
 
-                if x >= 0 && x < (grid.width as i32) && y >= 0 && y < (grid.height as i32) {
-                    if let Some(state) = cell_positions.map.get(&(x, y)) {
-                        if *state {
-                            count += 1;
-                        }
-                    }
-                }
-            }
+    world.spawn_batch(to_spawn);
+}
 
-            neighbors.0 = count;
+pub fn spawn_checkerboard_cells(world: &mut World, width: u32, height: u32) {
+    let cells_to_spawn_count = width * height;
+    let to_spawn = (0..cells_to_spawn_count).map(|i| {
+        let x = i % width;
+        let y = i / width;
+        let position = Position {
+            x: x as i32,
+            y: y as i32,
+        };
+        let state = State((x + y).is_multiple_of(2));
+        CellBundle {
+            position,
+            state,
+            index: Index(i),
+            ..Default::default()
         }
     });
 
-    let duration = start.elapsed();
-    //println!("Updating neighbors (brute force) took {:?}", duration);
+    world.spawn_batch(to_spawn);
 }
 
-fn update_cells_system(
-    mut query: Query<(&mut State, &Neighbors)>,
-    mut cells_changed: ResMut<CellsChanged>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StripeOrientation {
+    // Every other row is alive, starting with row 0.
+    Horizontal,
+    // Every other column is alive, starting with column 0.
+    Vertical,
+}
+
+pub fn spawn_stripes_cells(
+    world: &mut World,
+    width: u32,
+    height: u32,
+    orientation: StripeOrientation,
 ) {
-    let start = Instant::now();
-    for (mut state, neighbors) in query.iter_mut() {
-        let previous_state = state.0;
-        match (state.0, neighbors.0) {
-            (true, 2) | (true, 3) => (),
-            (false, 3) => {
-                state.0 = true;
-            }
-            _ => {
-                state.0 = false;
-            }
+    let cells_to_spawn_count = width * height;
+    let to_spawn = (0..cells_to_spawn_count).map(|i| {
+        let x = i % width;
+        let y = i / width;
+        let position = Position {
+            x: x as i32,
+            y: y as i32,
+        };
+        let state = match orientation {
+            StripeOrientation::Horizontal => State(y.is_multiple_of(2)),
+            StripeOrientation::Vertical => State(x.is_multiple_of(2)),
+        };
+        CellBundle {
+            position,
+            state,
+            index: Index(i),
+            ..Default::default()
         }
+    });
 
-        if state.0 != previous_state {
-            cells_changed.0 = true;
-        }
-    }
-    let duration = start.elapsed();
-    //println!("Updating cells took {:?}", duration);
+    world.spawn_batch(to_spawn);
 }
 
-fn decrease_generation_system(mut generations: ResMut<Generations>) {
-    println!("Decreasing generations to {:?}", generations.0);
-    if generations.0 > 0 {
-        generations.0 -= 1;
-    }
-}
+// Each cell starts alive with probability `density`; the same seed gives the same soup.
+pub fn spawn_random_cells(world: &mut World, width: u32, height: u32, seed: u64, density: f64) {
+    let cells_to_spawn_count = width * height;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let density = density.clamp(0.0, 1.0);
+    let to_spawn = (0..cells_to_spawn_count)
+        .map(|i| {
+            let x = i % width;
+            let y = i / width;
+            let position = Position {
+                x: x as i32,
+                y: y as i32,
+            };
+            let state = State(rng.gen_bool(density));
+            CellBundle {
+                position,
+                state,
+                index: Index(i),
+                ..Default::default()
+            }
+        })
+        .collect::<Vec<_>>();
 
-fn print_all_entities_system(mut query: Query<(Entity, &Position, &State, &Neighbors)>) {
-    println!("Printing all entities");
-    for (entity, position, state, neighbors) in &mut query {
-        println!(
-            "Entity {:?} has position {:?}, state {:?}, and neighbors {:?}",
-            entity, position, state, neighbors
-        );
-    }
+    world.spawn_batch(to_spawn);
 }
 
-pub fn initialize(width: u32, height: u32, generations: u32) {
-    let mut world = World::new();
-    world.insert_resource(Grid { width, height });
-    world.insert_resource(CellPositions {
-        map: HashMap::new(),
+pub fn spawn_pattern_cells(world: &mut World, width: u32, height: u32, live: &HashSet<(i32, i32)>) {
+    let cells_to_spawn_count = width * height;
+    let to_spawn = (0..cells_to_spawn_count).map(|i| {
+        let x = i % width;
+        let y = i / width;
+        let position = Position {
+            x: x as i32,
+            y: y as i32,
+        };
+        let state = State(live.contains(&(position.x, position.y)));
+        CellBundle {
+            position,
+            state,
+            index: Index(i),
+            ..Default::default()
+        }
     });
-    world.insert_resource(CellsChanged(true));
-    spawn_cells(&mut world, width, height);
-    world.insert_resource(Generations { 0: generations });
-    let mut schedule = Schedule::default();
-    schedule.add_systems(((
-        rebuild_cell_positions,
-        update_neighbors_brute_force_system,
-        update_cells_system,
-        rebuild_cell_positions,
-        update_neighbors_brute_force_system,
-    )
-        .chain(),));
 
-    //schedule.add_systems(draw_cells_system);
-
-    let start = Instant::now();
-    for _ in 0..generations {
-        schedule.run(&mut world);
+    world.spawn_batch(to_spawn);
+}
 
-        //println!("Iteration: {:?}", i);
+// Decides which regions the next generation evaluates, creating the region table on
+// first use.
+fn plan_activity_system(world: &mut World) {
+    let Some(config) = world.get_resource::<ActivityConfig>().copied() else {
+        return;
+    };
+    let counter = world.resource::<GenerationCounter>().0;
+    let boundary = world
+        .get_resource::<BoundaryMode>()
+        .copied()
+        .unwrap_or_default();
+    let grid = world.resource::<Grid>();
+    let (width, height) = (grid.width, grid.height);
+    let stale = world
+        .get_resource::<ActivityRegions>()
+        .is_none_or(|regions| {
+            regions.region_size != config.region_size.max(1)
+                || regions.columns != grid.width.div_ceil(regions.region_size)
+                || regions.rows != grid.height.div_ceil(regions.region_size)
+        });
+    if stale {
+        let regions = ActivityRegions::new(grid, config.region_size, counter);
+        world.insert_resource(regions);
     }
 
-    let duration = start.elapsed();
-    println!("Running {:?} generations took {:?}", generations, duration);
+    let generation = counter + 1;
+    let mut regions = world.resource_mut::<ActivityRegions>();
+    let recent = regions
+        .last_change
+        .iter()
+        .map(|last| last + config.keep_active >= generation)
+        .collect::<Vec<_>>();
+    // A region wakes when it or a cell just outside it changed recently. The cells
+    // around it are resolved in cell coordinates: past a flipped edge, or on a torus
+    // that is not a whole number of regions across, they do not line up with the
+    // neighboring regions.
+    let size = regions.region_size as i32;
+    for row in 0..regions.rows as i32 {
+        for column in 0..regions.columns as i32 {
+            let index = (row as u32 * regions.columns + column as u32) as usize;
+            let (left, top) = (column * size, row * size);
+            let right = (left + size).min(width as i32);
+            let bottom = (top + size).min(height as i32);
+            let horizontal = (left - 1..=right).flat_map(|x| [(x, top - 1), (x, bottom)]);
+            let vertical = (top..bottom).flat_map(|y| [(left - 1, y), (right, y)]);
+            let awake = recent[index]
+                || horizontal.chain(vertical).any(|(x, y)| {
+                    boundary
+                        .resolve(x, y, width, height)
+                        .and_then(|(x, y)| regions.index(x, y))
+                        .is_some_and(|neighbor| recent[neighbor])
+                });
+            regions.awake[index] = awake;
+        }
+    }
 }
 
-#[cfg(test)]
-mod tests {
+#[allow(clippy::too_many_arguments)]
+fn update_neighbors_brute_force_system(
+    mut query: Query<(&mut Neighbors, &mut NeighborMask, &Position)>,
+    cell_positions: Res<CellPositions>,
+    boundary: Option<Res<BoundaryMode>>,
+    weights: Option<Res<NeighborWeights>>,
+    neighborhood: Option<Res<Neighborhood>>,
+    include_self: Option<Res<IncludeSelf>>,
+    background: Option<Res<Background>>,
+    mask: Option<Res<GridMask>>,
+    symmetry: Option<Res<RuleSymmetry>>,
+    activity: Option<Res<ActivityRegions>>,
+    deterministic: Option<Res<Deterministic>>,
+    profile: Option<ResMut<ProfileStats>>,
+) {
+    let start = Instant::now();
+    let boundary = boundary.map_or(BoundaryMode::Dead, |b| *b);
+    let weights = neighborhood
+        .map_or(Neighborhood::Moore, |n| *n)
+        .weights(weights.map_or([1; 8], |w| w.0));
+    // With unit weights this is the Moore maximum of 8.
+    let max_count = weights
+        .iter()
+        .fold(0u8, |sum, w| sum.saturating_add(*w))
+        .saturating_add(include_self.is_some() as u8);
+    let anisotropic = symmetry.is_some_and(|s| *s == RuleSymmetry::Anisotropic);
+    let outside = background.is_some_and(|background| background.alive);
+    let update = |(mut neighbors, mut neighbor_mask, pos): (
+        Mut<Neighbors>,
+        Mut<NeighborMask>,
+        &Position,
+    )| {
+        if activity.as_ref().is_some_and(|a| !a.is_awake(pos.x, pos.y)) {
+            return;
+        }
+        let (mut count, directions) =
+            cell_positions.neighborhood(pos.x, pos.y, boundary, weights, mask.as_deref(), outside);
+        if include_self.is_some() && cell_positions.is_alive(pos.x, pos.y) {
+            count = count.saturating_add(1);
+        }
+        debug_assert!(
+            count <= max_count,
+            "cell at {:?} counted {} neighbors, more than the maximum {}",
+            pos,
+            count,
+            max_count
+        );
+        neighbors.0 = count;
+        if anisotropic {
+            neighbor_mask.0 = directions;
+        }
+    };
+    if deterministic.is_some() {
+        query.iter_mut().for_each(update);
+    } else {
+        query.par_iter_mut().for_each(update);
+    }
+
+    if let Some(mut profile) = profile {
+        profile.record("update_neighbors", start.elapsed());
+    }
+}
+
+// The resources that decide a cell's next state, shared by the update pass, the
+// compacted births and `Preview::next`.
+struct Transition<'a> {
+    rules: &'a Rules,
+    regions: Option<&'a RuleRegions>,
+    hensel: Option<&'a HenselRules>,
+    mask: Option<&'a GridMask>,
+}
+
+impl<'a> Transition<'a> {
+    fn from_world(world: &'a World) -> Self {
+        Transition {
+            rules: world.resource::<Rules>(),
+            regions: world.get_resource::<RuleRegions>(),
+            hensel: world.get_resource::<HenselRules>(),
+            mask: world.get_resource::<GridMask>(),
+        }
+    }
+
+    fn next(&self, alive: bool, neighbors: u8, directions: u8, x: i32, y: i32) -> bool {
+        if self.mask.is_some_and(|mask| !mask.contains(x, y)) {
+            false
+        } else if let Some(hensel) = self.hensel {
+            if alive {
+                hensel.survives(directions)
+            } else {
+                hensel.born(directions)
+            }
+        } else {
+            let rules = self
+                .regions
+                .and_then(|regions| regions.rules_at(x, y))
+                .unwrap_or(self.rules);
+            next_cell_state(alive, neighbors, rules)
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn update_cells_system(
+    mut query: Query<(
+        &mut State,
+        &mut LastChanged,
+        &Neighbors,
+        &NeighborMask,
+        &Position,
+    )>,
+    mut cells_changed: ResMut<CellsChanged>,
+    counter: Option<Res<GenerationCounter>>,
+    rules: Res<Rules>,
+    regions: Option<Res<RuleRegions>>,
+    hensel: Option<Res<HenselRules>>,
+    mask: Option<Res<GridMask>>,
+    mut activity: Option<ResMut<ActivityRegions>>,
+    profile: Option<ResMut<ProfileStats>>,
+) {
+    let start = Instant::now();
+    // The generation being computed; the counter advances after this system.
+    let generation = counter.map_or(0, |counter| counter.0) + 1;
+    let transition = Transition {
+        rules: &rules,
+        regions: regions.as_deref(),
+        hensel: hensel.as_deref(),
+        mask: mask.as_deref(),
+    };
+    for (mut state, mut last_changed, neighbors, neighbor_mask, pos) in query.iter_mut() {
+        if activity.as_ref().is_some_and(|a| !a.is_awake(pos.x, pos.y)) {
+            continue;
+        }
+        let previous_state = state.0;
+        state.0 = transition.next(state.0, neighbors.0, neighbor_mask.0, pos.x, pos.y);
+
+        if state.0 != previous_state {
+            cells_changed.0 = true;
+            last_changed.0 = generation;
+            if let Some(activity) = activity.as_mut() {
+                activity.mark(pos.x, pos.y, generation);
+            }
+        }
+    }
+    if let Some(mut profile) = profile {
+        profile.record("update_cells", start.elapsed());
+    }
+}
+
+// Births on coordinates that lost their entity to compaction. Uses the states from
+// before `update_cells_system`, like the neighbor pass.
+fn compacted_births(world: &mut World) -> Vec<(i32, i32)> {
+    if !world
+        .get_resource::<Compaction>()
+        .is_some_and(|compaction| compaction.compacted)
+    {
+        return Vec::new();
+    }
+
+    let occupied = world
+        .query::<&Position>()
+        .iter(world)
+        .map(|pos| (pos.x, pos.y))
+        .collect::<HashSet<_>>();
+    let cell_positions = world.resource::<CellPositions>();
+    let boundary = world
+        .get_resource::<BoundaryMode>()
+        .copied()
+        .unwrap_or_default();
+    let weights = world
+        .get_resource::<Neighborhood>()
+        .copied()
+        .unwrap_or_default()
+        .weights(
+            world
+                .get_resource::<NeighborWeights>()
+                .copied()
+                .unwrap_or_default()
+                .0,
+        );
+    let transition = Transition::from_world(world);
+    let (width, height) = (cell_positions.width, cell_positions.height);
+
+    let mut candidates = HashSet::new();
+    for (i, _) in cell_positions
+        .cells
+        .iter()
+        .enumerate()
+        .filter(|(_, alive)| **alive)
+    {
+        let (x, y) = ((i as u32 % width) as i32, (i as u32 / width) as i32);
+        for (dx, dy) in NEIGHBOR_OFFSETS {
+            if let Some(cell) = boundary.resolve(x + dx, y + dy, width, height) {
+                if !occupied.contains(&cell) {
+                    candidates.insert(cell);
+                }
+            }
+        }
+    }
+
+    candidates
+        .into_iter()
+        .filter(|&(x, y)| {
+            let (count, directions) =
+                cell_positions.neighborhood(x, y, boundary, weights, transition.mask, false);
+            transition.next(false, count, directions, x, y)
+        })
+        .collect()
+}
+
+fn spawn_births_system(world: &mut World) {
+    let births = compacted_births(world);
+    if births.is_empty() {
+        return;
+    }
+
+    let generation = world.resource::<GenerationCounter>().0 + 1;
+    let width = world.resource::<Grid>().width;
+    if let Some(mut activity) = world.get_resource_mut::<ActivityRegions>() {
+        for &(x, y) in &births {
+            activity.mark(x, y, generation);
+        }
+    }
+    world.spawn_batch(births.into_iter().map(move |(x, y)| CellBundle {
+        position: Position { x, y },
+        state: State(true),
+        last_changed: LastChanged(generation),
+        index: Index(y as u32 * width + x as u32),
+        ..Default::default()
+    }));
+    world.resource_mut::<CellsChanged>().0 = true;
+}
+
+fn count_times_alive_system(mut query: Query<(&State, &mut TimesAlive)>) {
+    for (state, mut times_alive) in query.iter_mut() {
+        if state.0 {
+            times_alive.0 += 1;
+        }
+    }
+}
+
+fn compact_system(world: &mut World) {
+    let Some(compaction) = world.get_resource::<Compaction>().copied() else {
+        return;
+    };
+    let generation = world.resource::<GenerationCounter>().0;
+    if !generation.is_multiple_of(compaction.every) {
+        return;
+    }
+
+    let dead = world
+        .query::<(Entity, &State)>()
+        .iter(world)
+        .filter(|(_, state)| !state.0)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    for entity in dead {
+        world.despawn(entity);
+    }
+    world.resource_mut::<Compaction>().compacted = true;
+}
+
+// Moves the background on with the cells. Runs after the births, which like the
+// neighbor pass see the background the generation started with.
+fn advance_background_system(
+    background: Option<ResMut<Background>>,
+    rules: Res<Rules>,
+    weights: Option<Res<NeighborWeights>>,
+    neighborhood: Option<Res<Neighborhood>>,
+    include_self: Option<Res<IncludeSelf>>,
+) {
+    let Some(mut background) = background else {
+        return;
+    };
+    let weights = neighborhood
+        .map_or(Neighborhood::Moore, |n| *n)
+        .weights(weights.map_or([1; 8], |w| w.0));
+    let full_count = weights
+        .iter()
+        .fold(0u8, |sum, w| sum.saturating_add(*w))
+        .saturating_add(include_self.is_some() as u8);
+    *background = background.next(&rules, full_count);
+}
+
+fn advance_generation_system(mut counter: ResMut<GenerationCounter>) {
+    counter.0 += 1;
+}
+
+fn autosave_system(world: &mut World) {
+    let Some(config) = world.get_resource::<AutosaveConfig>() else {
+        return;
+    };
+    let generation = world.resource::<GenerationCounter>().0;
+    let due = generation.is_multiple_of(config.every) || config.last == Some(generation);
+    if !due {
+        return;
+    }
+
+    let path = config.path(generation);
+    if let Err(err) = save_snapshot(world, &path) {
+        eprintln!("Autosave failed: {}", err);
+    }
+}
+
+pub fn decrease_generation_system(mut generations: ResMut<Generations>) {
+    status!("Decreasing generations to {:?}", generations.0);
+    if generations.0 > 0 {
+        generations.0 -= 1;
+    }
+}
+
+pub fn print_all_entities_system(mut query: Query<(Entity, &Position, &State, &Neighbors)>) {
+    status!("Printing all entities");
+    for (entity, position, state, neighbors) in &mut query {
+        status!(
+            "Entity {:?} has position {:?}, state {:?}, and neighbors {:?}",
+            entity,
+            position,
+            state,
+            neighbors
+        );
+    }
+}
+
+pub fn live_cells(world: &mut World) -> HashSet<(i32, i32)> {
+    let mut query = world.query::<(&Position, &State)>();
+    query
+        .iter(world)
+        .filter(|(_, state)| state.0)
+        .map(|(pos, _)| (pos.x, pos.y))
+        .collect()
+}
+
+// The canonical order for cell lists that get hashed, compared or written out: by
+// row, then column.
+pub fn sort_cells(cells: impl IntoIterator<Item = (i32, i32)>) -> Vec<(i32, i32)> {
+    let mut cells = cells.into_iter().collect::<Vec<_>>();
+    cells.sort_unstable_by_key(|&(x, y)| (y, x));
+    cells
+}
+
+// The live cells in `sort_cells` order.
+pub fn sorted_live(world: &mut World) -> Vec<(i32, i32)> {
+    sort_cells(live_cells(world))
+}
+
+// Every cell's position and state, in grid index order.
+pub fn cells_by_index(world: &mut World) -> Vec<(Position, bool)> {
+    let mut query = world.query::<(&Index, &Position, &State)>();
+    let mut cells = query
+        .iter(world)
+        .map(|(index, pos, state)| (*index, *pos, state.0))
+        .collect::<Vec<_>>();
+    cells.sort_unstable_by_key(|(index, _, _)| *index);
+    cells
+        .into_iter()
+        .map(|(_, pos, alive)| (pos, alive))
+        .collect()
+}
+
+pub fn cell_state_at(world: &mut World, x: i32, y: i32) -> Option<bool> {
+    let mut query = world.query::<(&Position, &State)>();
+    query
+        .iter(world)
+        .find(|(pos, _)| pos.x == x && pos.y == y)
+        .map(|(_, state)| state.0)
+}
+
+pub fn neighbor_count_at(world: &mut World, x: i32, y: i32) -> Option<u8> {
+    let mut query = world.query::<(&Position, &Neighbors)>();
+    query
+        .iter(world)
+        .find(|(pos, _)| pos.x == x && pos.y == y)
+        .map(|(_, neighbors)| neighbors.0)
+}
+
+// Neighbor count of every grid coordinate for the given live set, computed without
+// the ECS. Gives the same counts as the neighbor system with unit weights and no mask.
+pub fn compute_neighbors(
+    live: &HashSet<(i32, i32)>,
+    grid: &Grid,
+    boundary: BoundaryMode,
+) -> HashMap<(i32, i32), u8> {
+    let mut counts = HashMap::with_capacity((grid.width * grid.height) as usize);
+    for y in 0..grid.height as i32 {
+        for x in 0..grid.width as i32 {
+            counts.insert((x, y), live_neighbors(live, x, y, grid, boundary));
+        }
+    }
+    counts
+}
+
+// Live Moore neighbors of (x, y) in `live`, with edges handled by `boundary`.
+pub fn live_neighbors(
+    live: &HashSet<(i32, i32)>,
+    x: i32,
+    y: i32,
+    grid: &Grid,
+    boundary: BoundaryMode,
+) -> u8 {
+    let is_alive = |x, y| live.contains(&(x, y));
+    weighted_neighbors(is_alive, x, y, grid, boundary, [1; 8], None, false).0
+}
+
+// Smallest and largest `Neighbors` count among the live cells. None when there are
+// no live cells or the counts are stale because cells changed since they were taken.
+pub fn neighbor_range(world: &mut World) -> Option<(u8, u8)> {
+    if world.resource::<CellsChanged>().0 {
+        return None;
+    }
+    let mut query = world.query::<(&State, &Neighbors)>();
+    query
+        .iter(world)
+        .filter(|(state, _)| state.0)
+        .fold(None, |range, (_, neighbors)| {
+            let n = neighbors.0;
+            Some(range.map_or((n, n), |(min, max): (u8, u8)| (min.min(n), max.max(n))))
+        })
+}
+
+pub fn last_changed_at(world: &mut World, x: i32, y: i32) -> Option<u32> {
+    let mut query = world.query::<(&Position, &LastChanged)>();
+    query
+        .iter(world)
+        .find(|(pos, _)| pos.x == x && pos.y == y)
+        .map(|(_, last_changed)| last_changed.0)
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ThreadedRunStats {
+    pub simulated: u32,
+    pub sent: u32,
+    pub dropped: u32,
+}
+
+// Steps the simulation on the calling thread while `render_fn` consumes live cell
+// snapshots on a separate thread. If the renderer is still busy with the previous
+// frame, the new snapshot is dropped instead of stalling the simulation.
+pub fn run_threaded<F>(
+    world: &mut World,
+    schedule: &mut Schedule,
+    generations: u32,
+    mut render_fn: F,
+) -> ThreadedRunStats
+where
+    F: FnMut(u32, HashSet<(i32, i32)>) + Send,
+{
+    let (sender, receiver) = mpsc::sync_channel::<(u32, HashSet<(i32, i32)>)>(1);
+
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            for (generation, live) in receiver {
+                render_fn(generation, live);
+            }
+        });
+
+        let mut stats = ThreadedRunStats::default();
+        for generation in 1..=generations {
+            schedule.run(world);
+            stats.simulated += 1;
+
+            match sender.try_send((generation, live_cells(world))) {
+                Ok(()) => stats.sent += 1,
+                Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => {
+                    stats.dropped += 1
+                }
+            }
+        }
+
+        drop(sender);
+        stats
+    })
+}
+
+// Which corner of the frame cell (0, 0) is drawn in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    // Screen coordinates: y grows downward.
+    #[default]
+    TopLeft,
+    // Mathematical coordinates: y grows upward.
+    BottomLeft,
+}
+
+impl Origin {
+    // Rows `min_y..=max_y` in the order they are printed.
+    pub fn rows(self, min_y: i32, max_y: i32) -> Vec<i32> {
+        let mut rows = (min_y..=max_y).collect::<Vec<_>>();
+        if self == Origin::BottomLeft {
+            rows.reverse();
+        }
+        rows
+    }
+}
+
+impl std::str::FromStr for Origin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "top-left" => Ok(Origin::TopLeft),
+            "bottom-left" => Ok(Origin::BottomLeft),
+            _ => Err(format!(
+                "origin must be top-left or bottom-left, got {:?}",
+                s
+            )),
+        }
+    }
+}
+
+pub fn render(live: &HashSet<(i32, i32)>, grid: &Grid) -> String {
+    render_with_origin(live, grid, Origin::TopLeft)
+}
+
+pub fn render_with_origin(live: &HashSet<(i32, i32)>, grid: &Grid, origin: Origin) -> String {
+    let mut output = String::with_capacity(((grid.width + 1) * grid.height) as usize);
+    for y in origin.rows(0, grid.height as i32 - 1) {
+        for x in 0..grid.width as i32 {
+            output.push(if live.contains(&(x, y)) { '#' } else { '.' });
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// Surrounds a rendered frame with `+` corners, `-` along the top and bottom and `|`
+// down the sides, to show where the edges wrap around.
+pub fn frame_render(rendered: &str) -> String {
+    let width = rendered
+        .lines()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let edge = format!("+{}+\n", "-".repeat(width));
+    let mut output = edge.clone();
+    for line in rendered.lines() {
+        output.push('|');
+        output.push_str(line);
+        output.push_str("|\n");
+    }
+    output.push_str(&edge);
+    output
+}
+
+// Smallest and largest x and y over the live cells, or None when there are none.
+pub fn bounding_box(live: &HashSet<(i32, i32)>) -> Option<((i32, i32), (i32, i32))> {
+    let min_x = live.iter().map(|c| c.0).min()?;
+    let max_x = live.iter().map(|c| c.0).max()?;
+    let min_y = live.iter().map(|c| c.1).min()?;
+    let max_y = live.iter().map(|c| c.1).max()?;
+    Some(((min_x, min_y), (max_x, max_y)))
+}
+
+// Width and height of the live cells' bounding box, 0 by 0 when there are none.
+pub fn bbox_size(live: &HashSet<(i32, i32)>) -> (u32, u32) {
+    bounding_box(live).map_or((0, 0), |((min_x, min_y), (max_x, max_y))| {
+        ((max_x - min_x + 1) as u32, (max_y - min_y + 1) as u32)
+    })
+}
+
+// Like `render`, but only the bounding box of the live cells.
+pub fn render_cropped(live: &HashSet<(i32, i32)>, origin: Origin) -> String {
+    let Some(((min_x, min_y), (max_x, max_y))) = bounding_box(live) else {
+        return "(empty)\n".to_string();
+    };
+    let mut output = String::new();
+    for y in origin.rows(min_y, max_y) {
+        for x in min_x..=max_x {
+            output.push(if live.contains(&(x, y)) { '#' } else { '.' });
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// Block of cells each printed character must stand for so a `width` by `height`
+// frame fits in `max` columns and rows.
+pub fn fit_block(width: u32, height: u32, max: (u32, u32)) -> (u32, u32) {
+    (
+        width.div_ceil(max.0.max(1)).max(1),
+        height.div_ceil(max.1.max(1)).max(1),
+    )
+}
+
+// Shrinks live cells onto a coarser grid where each cell stands for a `block` of
+// the original, alive if any cell in the block is.
+pub fn downsample(live: &HashSet<(i32, i32)>, block: (u32, u32)) -> HashSet<(i32, i32)> {
+    let (width, height) = (block.0 as i32, block.1 as i32);
+    live.iter()
+        .map(|&(x, y)| (x.div_euclid(width), y.div_euclid(height)))
+        .collect()
+}
+
+// Moves live cells into a viewport whose top-left corner is `offset`, wrapping
+// around the grid edges, so a pattern straddling a toroidal seam can be recentered.
+pub fn view_cells(
+    live: &HashSet<(i32, i32)>,
+    grid: &Grid,
+    offset: (i32, i32),
+) -> HashSet<(i32, i32)> {
+    if offset == (0, 0) {
+        return live.clone();
+    }
+    let (width, height) = (grid.width as i32, grid.height as i32);
+    live.iter()
+        .map(|&(x, y)| {
+            (
+                (x - offset.0).rem_euclid(width),
+                (y - offset.1).rem_euclid(height),
+            )
+        })
+        .collect()
+}
+
+// A view offset that chases the live cells around a toroidal grid, keeping the centre
+// of their bounding box at the centre of the view. Only the view moves; the cells and
+// their neighborhoods are untouched.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Follow {
+    pub offset: (i32, i32),
+}
+
+impl Follow {
+    pub fn new(offset: (i32, i32)) -> Self {
+        Follow { offset }
+    }
+
+    // Recentres on `live` and returns it as seen through the new offset. The bounding
+    // box is measured in the previous view, where a followed pattern sits in the
+    // middle instead of straddling the grid edges.
+    pub fn update(&mut self, live: &HashSet<(i32, i32)>, grid: &Grid) -> HashSet<(i32, i32)> {
+        let viewed = view_cells(live, grid, self.offset);
+        let Some(((min_x, min_y), (max_x, max_y))) = bounding_box(&viewed) else {
+            return viewed;
+        };
+        let (width, height) = (grid.width as i32, grid.height as i32);
+        let shift = (
+            (min_x + max_x) / 2 - width / 2,
+            (min_y + max_y) / 2 - height / 2,
+        );
+        self.offset = (
+            (self.offset.0 + shift.0).rem_euclid(width),
+            (self.offset.1 + shift.1).rem_euclid(height),
+        );
+        view_cells(live, grid, self.offset)
+    }
+}
+
+// Cells that a step flips: births and deaths.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub births: HashSet<(i32, i32)>,
+    pub deaths: HashSet<(i32, i32)>,
+}
+
+impl Preview {
+    // The cells the world's next step will flip, without advancing it. Follows the
+    // same rules, regions, masks, neighborhoods and background as the step itself,
+    // plus the cells the script sets right after it.
+    pub fn next(world: &mut World) -> Self {
+        world.run_system_once(rebuild_cell_positions);
+        world.run_system_once(update_neighbors_brute_force_system);
+
+        let mut preview = Preview {
+            births: compacted_births(world).into_iter().collect(),
+            ..Preview::default()
+        };
+        let mut query = world.query::<(&State, &Neighbors, &NeighborMask, &Position)>();
+        let transition = Transition::from_world(world);
+        let activity = world.get_resource::<ActivityRegions>();
+        for (state, neighbors, directions, pos) in query.iter(world) {
+            if activity.is_some_and(|a| !a.is_awake(pos.x, pos.y)) {
+                continue;
+            }
+            if transition.next(state.0, neighbors.0, directions.0, pos.x, pos.y) != state.0 {
+                if state.0 {
+                    preview.deaths.insert((pos.x, pos.y));
+                } else {
+                    preview.births.insert((pos.x, pos.y));
+                }
+            }
+        }
+
+        let generation = world.resource::<GenerationCounter>().0 + 1;
+        let scripted = world
+            .get_resource::<Script>()
+            .and_then(|script| script.mutations.get(&generation));
+        for mutation in scripted.into_iter().flatten() {
+            if let script::Mutation::Set { x, y, alive } = *mutation {
+                if !world.resource::<Grid>().contains(x, y) {
+                    continue;
+                }
+                preview.births.remove(&(x, y));
+                preview.deaths.remove(&(x, y));
+                if alive != world.resource::<CellPositions>().is_alive(x, y) {
+                    if alive {
+                        preview.births.insert((x, y));
+                    } else {
+                        preview.deaths.insert((x, y));
+                    }
+                }
+            }
+        }
+        preview
+    }
+
+    // The cells that did flip between two consecutive frames.
+    pub fn between(current: &HashSet<(i32, i32)>, next: &HashSet<(i32, i32)>) -> Self {
+        Preview {
+            births: next.difference(current).copied().collect(),
+            deaths: current.difference(next).copied().collect(),
+        }
+    }
+}
+
+// Like `render`, but live cells about to die are drawn as `x` and dead cells about
+// to be born as `+`.
+pub fn render_preview(
+    live: &HashSet<(i32, i32)>,
+    grid: &Grid,
+    preview: &Preview,
+    origin: Origin,
+) -> String {
+    let mut output = String::with_capacity(((grid.width + 1) * grid.height) as usize);
+    for y in origin.rows(0, grid.height as i32 - 1) {
+        for x in 0..grid.width as i32 {
+            output.push(if preview.deaths.contains(&(x, y)) {
+                'x'
+            } else if preview.births.contains(&(x, y)) {
+                '+'
+            } else if live.contains(&(x, y)) {
+                '#'
+            } else {
+                '.'
+            });
+        }
+        output.push('\n');
+    }
+    output
+}
+
+// The live cells of a whole grid, printable as the same ASCII frame `render` draws.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridSnapshot {
+    pub width: u32,
+    pub height: u32,
+    pub live: HashSet<(i32, i32)>,
+}
+
+impl From<&mut World> for GridSnapshot {
+    fn from(world: &mut World) -> Self {
+        let live = live_cells(world);
+        let grid = world.resource::<Grid>();
+        GridSnapshot {
+            width: grid.width,
+            height: grid.height,
+            live,
+        }
+    }
+}
+
+impl std::fmt::Display for GridSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let grid = Grid {
+            width: self.width,
+            height: self.height,
+        };
+        write!(f, "{}", render(&self.live, &grid))
+    }
+}
+
+// Redraws only the cells that differ between two frames by moving the terminal
+// cursor (ANSI CUP, 1-based) to each changed cell. Assumes the previous frame was
+// drawn with `render` starting at the top-left corner of the screen.
+pub fn render_diff(previous: &HashSet<(i32, i32)>, current: &HashSet<(i32, i32)>) -> String {
+    let changed = sort_cells(previous.symmetric_difference(current).copied());
+
+    let mut output = String::new();
+    for (x, y) in changed {
+        let glyph = if current.contains(&(x, y)) { '#' } else { '.' };
+        output.push_str(&format!("\x1b[{};{}H{}", y + 1, x + 1, glyph));
+    }
+    output
+}
+
+// Progress bar on stderr showing generation/total and the current population. Hidden
+// unless enabled and stderr is a terminal, so captured output stays clean.
+#[cfg(feature = "term")]
+pub fn progress_bar(enabled: bool, generations: u32) -> ProgressBar {
+    use std::io::IsTerminal;
+
+    if !enabled || !std::io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+
+    let progress = ProgressBar::new(generations as u64);
+    progress.set_style(
+        indicatif::ProgressStyle::with_template("{bar:40} {pos}/{len} generations, {msg}").unwrap(),
+    );
+    progress
+}
+
+// Without the `term` feature there is nothing to draw with.
+#[cfg(not(feature = "term"))]
+pub fn progress_bar(_enabled: bool, _generations: u32) -> ProgressBar {
+    ProgressBar::hidden()
+}
+
+// Columns and rows of the terminal stdout is attached to, or None when it is not a
+// terminal.
+#[cfg(feature = "term")]
+pub fn terminal_size() -> Option<(u32, u32)> {
+    let (rows, columns) = console::Term::stdout().size_checked()?;
+    Some((columns as u32, rows as u32))
+}
+
+#[cfg(not(feature = "term"))]
+pub fn terminal_size() -> Option<(u32, u32)> {
+    None
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    pub render: bool,
+    pub incremental: bool,
+    // With `render`, mark the cells the next generation will flip.
+    pub preview: bool,
+    // With `render`, draw only the bounding box of the live cells.
+    pub crop: bool,
+    // With `render`, frame the grid to show its edges wrap.
+    pub show_border: bool,
+    // With `render`, downsample frames wider or taller than this many columns and rows.
+    pub max_render: Option<(u32, u32)>,
+    pub show_initial: bool,
+    pub dry_run: bool,
+    pub interactive: bool,
+    // With `interactive`, how many generations `r` can step back through.
+    pub history_limit: Option<usize>,
+    pub progress: bool,
+    pub script: Option<Script>,
+    pub hensel: Option<HenselRules>,
+    pub neighborhood: Neighborhood,
+    // Count a live cell among its own neighbors.
+    pub include_self: bool,
+    // Rectangles running their own rule instead of the grid's.
+    pub rule_regions: Vec<RuleRegion>,
+    pub mask: Option<GridMask>,
+    pub snapshot: Option<Snapshot>,
+    pub pattern: Option<Pattern>,
+    pub empty: bool,
+    pub save_snapshot: Option<PathBuf>,
+    pub save_pbm: Option<PathBuf>,
+    // Write a grayscale PNG of how many generations each cell was alive.
+    pub save_times_alive: Option<PathBuf>,
+    pub autosave_every: Option<u32>,
+    pub compact_every: Option<u32>,
+    pub activity: Option<ActivityConfig>,
+    pub bench_json: bool,
+    // Write `GenerationStats` lines to this file, or to stdout for `-`.
+    pub jsonl: Option<PathBuf>,
+    // Write every generation's live cells to this file for `replay`.
+    pub record: Option<PathBuf>,
+    // Write every generation as a frame of an animated PNG, shown `apng_delay_ms` each.
+    pub apng: Option<PathBuf>,
+    pub apng_delay_ms: u64,
+    // Render and export only these generations.
+    pub export_range: Option<RangeInclusive<u32>>,
+    pub force: bool,
+    pub connectivity: Connectivity,
+    pub count_gliders: bool,
+    // Print `gen W H` with the live cells' bounding box every generation, and add it
+    // to the `jsonl` lines.
+    pub bbox_log: bool,
+    // Print a sparkline of the population history at the end of the run.
+    pub stats: bool,
+    pub detect_attractors: bool,
+    pub boundary: BoundaryMode,
+    pub view_offset: (i32, i32),
+    // With `render` on a toroidal grid, keep the live cells centred in view.
+    pub follow: bool,
+    pub origin: Origin,
+    pub profile: bool,
+    pub deterministic: bool,
+    pub time_limit: Option<Duration>,
+    pub backend: Backend,
+    // Dead cells added around the seeded grid on every side.
+    pub border_margin: u32,
+    pub interrupted: Interrupted,
+    pub on_complete: Option<OnComplete>,
+}
+
+impl RunOptions {
+    // The options that are set but that `Backend::Soa` has no support for, named as
+    // their flags. It runs without a schedule or sinks, so every per-generation output
+    // and schedule feature is in here.
+    pub fn unsupported_by_soa(&self) -> Vec<&'static str> {
+        [
+            ("--render", self.render),
+            ("--incremental", self.incremental),
+            ("--preview", self.preview),
+            ("--crop", self.crop),
+            ("--show-border", self.show_border),
+            ("--view-offset", self.view_offset != (0, 0)),
+            ("--follow", self.follow),
+            ("--origin", self.origin != Origin::default()),
+            ("--interactive", self.interactive),
+            ("--history", self.history_limit.is_some()),
+            ("--progress", self.progress),
+            ("--script", self.script.is_some()),
+            ("--hensel-rule", self.hensel.is_some()),
+            (
+                "--neighborhood",
+                self.neighborhood != Neighborhood::default(),
+            ),
+            ("--include-self", self.include_self),
+            ("--rule-region", !self.rule_regions.is_empty()),
+            ("--mask-file", self.mask.is_some()),
+            ("--save-snapshot", self.save_snapshot.is_some()),
+            ("--save-times-alive", self.save_times_alive.is_some()),
+            ("--autosave-every", self.autosave_every.is_some()),
+            ("--compact-every", self.compact_every.is_some()),
+            ("--activity-regions", self.activity.is_some()),
+            ("--jsonl", self.jsonl.is_some()),
+            ("--record", self.record.is_some()),
+            ("--apng", self.apng.is_some()),
+            ("--export-range", self.export_range.is_some()),
+            ("--count-gliders", self.count_gliders),
+            ("--bbox-log", self.bbox_log),
+            ("--stats", self.stats),
+            ("--detect-attractors", self.detect_attractors),
+            ("--profile", self.profile),
+        ]
+        .into_iter()
+        .filter(|&(_, set)| set)
+        .map(|(flag, _)| flag)
+        .collect()
+    }
+}
+
+// Why a run ended.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    // Every requested generation ran.
+    Completed,
+    // No live cells are left. Runs stop at the first empty generation unless a B0
+    // background or a script could still bring cells back.
+    Extinct,
+    Interrupted,
+    // The `TimeLimit` was spent.
+    TimedOut,
+    // A system set `Control::should_stop`.
+    Stopped,
+    // The user ended an interactive session.
+    Quit,
+}
+
+impl TerminationReason {
+    // Reason for a run that was not cut short.
+    pub fn finished(population: usize) -> Self {
+        if population == 0 {
+            TerminationReason::Extinct
+        } else {
+            TerminationReason::Completed
+        }
+    }
+}
+
+// Called with the summary when `initialize_with` returns, whatever ended the run.
+#[derive(Clone)]
+pub struct OnComplete(pub Arc<dyn Fn(&RunSummary) + Send + Sync>);
+
+impl OnComplete {
+    pub fn new(callback: impl Fn(&RunSummary) + Send + Sync + 'static) -> Self {
+        OnComplete(Arc::new(callback))
+    }
+}
+
+impl std::fmt::Debug for OnComplete {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OnComplete")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunSummary {
+    pub generations: u32,
+    pub population: usize,
+    pub reason: TerminationReason,
+}
+
+impl std::fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Completed {} generations with a final population of {}",
+            self.generations, self.population
+        )?;
+        match self.reason {
+            TerminationReason::Completed => Ok(()),
+            TerminationReason::Extinct => write!(f, " (extinct)"),
+            TerminationReason::Interrupted => write!(f, " (interrupted)"),
+            TerminationReason::TimedOut => write!(f, " (time limit reached)"),
+            TerminationReason::Stopped => write!(f, " (stopped)"),
+            TerminationReason::Quit => write!(f, " (quit)"),
+        }
+    }
+}
+
+// Machine-readable timing results printed by `--bench-json`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub generations: u32,
+    pub total_ms: f64,
+    pub ms_per_generation: f64,
+    pub grid_cells: u32,
+    pub final_population: usize,
+}
+
+impl BenchReport {
+    pub fn new(summary: &RunSummary, duration: Duration, grid_cells: u32) -> Self {
+        let total_ms = duration.as_secs_f64() * 1000.0;
+        BenchReport {
+            generations: summary.generations,
+            total_ms,
+            ms_per_generation: total_ms / summary.generations.max(1) as f64,
+            grid_cells,
+            final_population: summary.population,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+// Hash of the live cells that is stable across machines and runs (FNV-1a over the
+// cells sorted by row, then column).
+pub fn state_digest(live: &HashSet<(i32, i32)>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for (x, y) in sort_cells(live.iter().copied()) {
+        for byte in x.to_le_bytes().into_iter().chain(y.to_le_bytes()) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+// Per-generation line written by `--jsonl`.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct GenerationStats {
+    #[serde(rename = "gen")]
+    pub generation: u32,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+    // Number of separate objects among the live cells.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_neighbors: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_neighbors: Option<u8>,
+    // Size of the live cells' bounding box, with `--bbox-log`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox_width: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox_height: Option<u32>,
+    // Gliders in flight, with `--count-gliders`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gliders: Option<usize>,
+}
+
+impl GenerationStats {
+    pub fn between(
+        generation: u32,
+        previous: &HashSet<(i32, i32)>,
+        current: &HashSet<(i32, i32)>,
+    ) -> Self {
+        GenerationStats {
+            generation,
+            population: current.len(),
+            births: current.difference(previous).count(),
+            deaths: previous.difference(current).count(),
+            components: None,
+            min_neighbors: None,
+            max_neighbors: None,
+            bbox_width: None,
+            bbox_height: None,
+            gliders: None,
+        }
+    }
+
+    pub fn write_jsonl(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        serde_json::to_writer(&mut *out, self)?;
+        writeln!(out)
+    }
+}
+
+// Runs `generations` steps, calling `on_frame` after each one with the generation
+// number, which continues from `GenerationCounter` for resumed worlds. With
+// `show_initial` the starting state is emitted before the first step. The loop stops
+// early once the `Interrupted` resource, if present, has been set, once a system has
+// set `Control::should_stop` during this run, once the `TimeLimit` resource's budget
+// has been spent, or once no live cells are left to change.
+pub fn run_generations<F>(
+    world: &mut World,
+    schedule: &mut Schedule,
+    generations: u32,
+    show_initial: bool,
+    mut on_frame: F,
+) -> RunSummary
+where
+    F: FnMut(u32, &mut World),
+{
+    let is_interrupted = |world: &World| {
+        world
+            .get_resource::<Interrupted>()
+            .is_some_and(|i| i.is_set())
+    };
+
+    // A stop requested in an earlier run of this world does not end this one.
+    if let Some(mut control) = world.get_resource_mut::<Control>() {
+        control.should_stop = false;
+    }
+    let first = world
+        .get_resource::<GenerationCounter>()
+        .map_or(0, |counter| counter.0);
+    if show_initial {
+        on_frame(first, world);
+    }
+
+    let start = Instant::now();
+    let time_limit = world.get_resource::<TimeLimit>().copied();
+    // An empty grid stays empty, unless B0 births from nothing or a script adds cells.
+    let can_go_extinct =
+        !world.contains_resource::<Background>() && !world.contains_resource::<Script>();
+    let mut reason = None;
+    let mut completed = 0;
+    for step in 1..=generations {
+        if is_interrupted(world) {
+            reason = Some(TerminationReason::Interrupted);
+            break;
+        }
+        if time_limit.is_some_and(|limit| start.elapsed() >= limit.0) {
+            reason = Some(TerminationReason::TimedOut);
+            break;
+        }
+        schedule.run(world);
+        completed = step;
+        on_frame(first + step, world);
+        if world
+            .get_resource::<Control>()
+            .is_some_and(|control| control.should_stop)
+        {
+            reason = Some(TerminationReason::Stopped);
+            break;
+        }
+        if can_go_extinct && !world.query::<&State>().iter(world).any(|state| state.0) {
+            reason = Some(TerminationReason::Extinct);
+            break;
+        }
+    }
+
+    let population = live_cells(world).len();
+    RunSummary {
+        generations: completed,
+        population,
+        reason: reason.unwrap_or_else(|| {
+            if is_interrupted(world) {
+                TerminationReason::Interrupted
+            } else {
+                TerminationReason::finished(population)
+            }
+        }),
+    }
+}
+
+// Creates a world holding the resources `build_schedule` depends on, without any cells.
+pub fn setup_world(width: u32, height: u32, rules: Rules) -> World {
+    let mut world = World::new();
+    world.insert_resource(Grid { width, height });
+    if let Some(background) = Background::for_rules(&rules) {
+        world.insert_resource(background);
+    }
+    world.insert_resource(rules);
+    world.insert_resource(CellPositions::default());
+    world.insert_resource(CellsChanged(true));
+    world.insert_resource(GenerationCounter::default());
+    world
+}
+
+// Despawns every cell and resets the per-run resources so the world can be seeded
+// again, keeping `Grid`, `Rules` and any optional configuration resources. Run state
+// that `run_seeded` inserts (`Generations`, `TimeLimit`) is removed, and state kept
+// between steps (`Control`, `Background`, `History`, compaction, profiling, the last
+// `step_with_diff` frame) goes back to how a fresh run starts.
+pub fn clear_world(world: &mut World) {
+    let cells = world
+        .query_filtered::<Entity, With<Position>>()
+        .iter(world)
+        .collect::<Vec<_>>();
+    for entity in cells {
+        world.despawn(entity);
+    }
+
+    if let Some(mut cell_positions) = world.get_resource_mut::<CellPositions>() {
+        cell_positions.cells.clear();
+    }
+    world.insert_resource(CellsChanged(true));
+    world.insert_resource(GenerationCounter::default());
+    world.remove_resource::<ActivityRegions>();
+    world.remove_resource::<Generations>();
+    world.remove_resource::<TimeLimit>();
+    world.remove_resource::<output::PreviousLive>();
+    if let Some(mut control) = world.get_resource_mut::<Control>() {
+        *control = Control::default();
+    }
+    if world.contains_resource::<Background>() {
+        world.insert_resource(Background::default());
+    }
+    if let Some(mut history) = world.get_resource_mut::<History>() {
+        history.clear();
+    }
+    if let Some(mut compaction) = world.get_resource_mut::<Compaction>() {
+        compaction.compacted = false;
+    }
+    if let Some(mut profile) = world.get_resource_mut::<ProfileStats>() {
+        *profile = ProfileStats::default();
+    }
+}
+
+// Changes the grid to `width` x `height`, keeping the cells inside both the old and
+// new bounds as they are. Cells that fall outside are despawned and the new area is
+// filled with dead cells.
+pub fn resize_grid(world: &mut World, width: u32, height: u32) {
+    let (old_width, old_height) = {
+        let grid = world.resource::<Grid>();
+        (grid.width, grid.height)
+    };
+
+    let outside = world
+        .query::<(Entity, &Position)>()
+        .iter(world)
+        .filter(|(_, pos)| pos.x >= width as i32 || pos.y >= height as i32)
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+    for entity in outside {
+        world.despawn(entity);
+    }
+    for (pos, mut index) in world.query::<(&Position, &mut Index)>().iter_mut(world) {
+        index.0 = pos.y as u32 * width + pos.x as u32;
+    }
+
+    let to_spawn = (0..width * height)
+        .map(|i| (i, i % width, i / width))
+        .filter(|&(_, x, y)| x >= old_width || y >= old_height)
+        .map(|(i, x, y)| CellBundle {
+            position: Position {
+                x: x as i32,
+                y: y as i32,
+            },
+            index: Index(i),
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    world.spawn_batch(to_spawn);
+
+    world.insert_resource(Grid { width, height });
+    world.insert_resource(CellsChanged(true));
+    world.remove_resource::<ActivityRegions>();
+}
+
+// How many computed generations each existing cell spent alive.
+pub fn times_alive(world: &mut World) -> HashMap<(i32, i32), u32> {
+    world
+        .query::<(&Position, &TimesAlive)>()
+        .iter(world)
+        .map(|(pos, times_alive)| ((pos.x, pos.y), times_alive.0))
+        .collect()
+}
+
+// Randomly permutes which of the existing cells are alive, keeping the live count.
+// The same seed gives the same arrangement.
+pub fn reshuffle(world: &mut World, seed: u64) {
+    let generation = world.resource::<GenerationCounter>().0;
+    let mut query = world.query::<(&Index, &mut State, &mut LastChanged)>();
+    let mut cells = query.iter_mut(world).collect::<Vec<_>>();
+    cells.sort_by_key(|(index, _, _)| index.0);
+    let mut states = cells
+        .iter()
+        .map(|(_, state, _)| state.0)
+        .collect::<Vec<_>>();
+    states.shuffle(&mut StdRng::seed_from_u64(seed));
+    for ((_, state, last_changed), alive) in cells.iter_mut().zip(states) {
+        if state.0 != alive {
+            state.0 = alive;
+            last_changed.0 = generation;
+        }
+    }
+    world.resource_mut::<CellsChanged>().0 = true;
+    world.remove_resource::<ActivityRegions>();
+}
+
+// Sets one cell between schedule runs, as if it had been clicked. The next step
+// recomputes neighbor counts from the edited grid. Returns false for coordinates
+// outside the grid.
+pub fn perturb(world: &mut World, x: i32, y: i32, alive: bool) -> bool {
+    let width = world.resource::<Grid>().width;
+    let height = world.resource::<Grid>().height;
+    if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+        return false;
+    }
+
+    let generation = world.resource::<GenerationCounter>().0;
+    let mut query = world.query::<(&Position, &mut State, &mut LastChanged)>();
+    match query
+        .iter_mut(world)
+        .find(|(pos, _, _)| pos.x == x && pos.y == y)
+    {
+        Some((_, mut state, mut last_changed)) if state.0 != alive => {
+            state.0 = alive;
+            last_changed.0 = generation;
+        }
+        Some(_) => (),
+        // Compaction may have despawned the dead cell.
+        None if alive => {
+            world.spawn(CellBundle {
+                position: Position { x, y },
+                state: State(true),
+                last_changed: LastChanged(generation),
+                index: Index(y as u32 * width + x as u32),
+                ..Default::default()
+            });
+        }
+        None => (),
+    }
+    if let Some(mut activity) = world.get_resource_mut::<ActivityRegions>() {
+        activity.mark(x, y, generation);
+    }
+    world.resource_mut::<CellsChanged>().0 = true;
+    true
+}
+
+// Phases of a generation. The core update chain runs in `Update`; extra systems can
+// be added to the phases around it.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimulationSet {
+    BeforeUpdate,
+    Update,
+    AfterUpdate,
+}
+
+// The update systems in the order they run, each passed through `$wrap!` so tests
+// can observe the order of the real chain.
+macro_rules! update_chain {
+    ($wrap:ident) => {
+        (
+            $wrap!(plan_activity_system),
+            $wrap!(rebuild_cell_positions),
+            $wrap!(update_neighbors_brute_force_system),
+            $wrap!(update_cells_system),
+            $wrap!(spawn_births_system),
+            $wrap!(advance_background_system),
+            $wrap!(count_times_alive_system),
+            $wrap!(advance_generation_system),
+            $wrap!(script::scripted_mutation_system),
+            $wrap!(autosave_system),
+            $wrap!(compact_system),
+            $wrap!(plan_activity_system),
+            $wrap!(rebuild_cell_positions),
+            $wrap!(update_neighbors_brute_force_system),
+        )
+    };
+}
+
+macro_rules! unwrapped {
+    ($system:path) => {
+        $system
+    };
+}
+
+pub fn build_schedule() -> Schedule {
+    let mut schedule = Schedule::default();
+    schedule.configure_sets(
+        (
+            SimulationSet::BeforeUpdate,
+            SimulationSet::Update,
+            SimulationSet::AfterUpdate,
+        )
+            .chain(),
+    );
+    schedule.add_systems(
+        update_chain!(unwrapped)
+            .chain()
+            .in_set(SimulationSet::Update),
+    );
+    schedule
+}
+
+// Builds the standard schedule and runs it once. Rebuilding the schedule every call
+// is wasteful in a loop, where `build_schedule` should be kept and rerun instead.
+pub fn run_one_generation(world: &mut World) {
+    build_schedule().run(world);
+}
+
+// Grids above this many cells take a long time and a lot of memory to spawn, since
+// every cell is its own entity. `RunOptions::force` skips the check.
+pub const DENSE_CELL_LIMIT: u64 = 10_000_000;
+
+// Everything `initialize` needs to set up and run a simulation.
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    // Grid size. A side left unset takes the seed pattern's, or 600x400 without one.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub generations: u32,
+    // Rule to run. Unset means the pattern header's rule, or Conway's without one.
+    pub rule: Option<Rules>,
+    // Seed a random soup with this RNG seed instead of an all-live grid, unless a
+    // pattern or `options.empty` is given.
+    pub seed: Option<u64>,
+    // Chance of each cell starting alive in a random soup.
+    pub density: f64,
+    pub options: RunOptions,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            width: None,
+            height: None,
+            generations: 100,
+            rule: None,
+            seed: None,
+            density: 0.5,
+            options: RunOptions::default(),
+        }
+    }
+}
+
+// Rejects grids that are empty, have more cells than the `u32` cell index can
+// number or, unless `force` is set, more than `DENSE_CELL_LIMIT`.
+pub fn check_grid_size(width: u32, height: u32, force: bool) -> Result<(), GameOfLifeError> {
+    let cells = width as u64 * height as u64;
+    if cells == 0 || cells > u32::MAX as u64 {
+        return Err(GameOfLifeError::InvalidDimensions { width, height });
+    }
+    if cells > DENSE_CELL_LIMIT && !force {
+        return Err(GameOfLifeError::GridTooLarge {
+            cells,
+            limit: DENSE_CELL_LIMIT,
+        });
+    }
+    Ok(())
+}
+
+// Rejects `--compact-every` under a B0 rule, from the start or switched to by the
+// script: compaction only revives cells next to live ones, but under B0 every empty
+// region is born at once.
+fn check_compaction(rules: &Rules, options: &RunOptions) -> Result<(), GameOfLifeError> {
+    if options.compact_every.is_none() {
+        return Ok(());
+    }
+    let scripted_b0 = options.script.as_ref().is_some_and(|script| {
+        script
+            .mutations
+            .values()
+            .flatten()
+            .any(|mutation| matches!(mutation, script::Mutation::Rule(rule) if rule.born(0)))
+    });
+    if rules.born(0) || scripted_b0 {
+        return Err(GameOfLifeError::InvalidConfig(
+            "--compact-every cannot be used with a B0 rule".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+// Size of the grid a run spawns: the snapshot's when resuming, otherwise the
+// requested one with `border_margin` cells added on every side. A padded side that
+// does not fit a `u32` saturates, which `check_grid_size` then rejects.
+fn run_grid_size(width: u32, height: u32, options: &RunOptions) -> (u32, u32) {
+    let padding = options.border_margin.saturating_mul(2);
+    match &options.snapshot {
+        Some(snapshot) => (snapshot.width, snapshot.height),
+        None => (
+            width.saturating_add(padding),
+            height.saturating_add(padding),
+        ),
+    }
+}
+
+impl SimConfig {
+    // The requested grid size with unset sides filled in from the pattern, or from
+    // the 600x400 default without one.
+    pub fn grid_size(&self) -> (u32, u32) {
+        match &self.options.pattern {
+            Some(pattern) => pattern.grid_size(self.width, self.height),
+            None => (self.width.unwrap_or(600), self.height.unwrap_or(400)),
+        }
+    }
+
+    // The rule to run: the explicit one, else the pattern header's, else Conway's.
+    pub fn rules(&self) -> Result<Rules, GameOfLifeError> {
+        match &self.options.pattern {
+            Some(pattern) => pattern.rule_or(self.rule.clone()),
+            None => Ok(self.rule.clone().unwrap_or_default()),
+        }
+    }
+
+    // Rejects options the backend cannot run, grids that `check_grid_size` does not
+    // accept and grids smaller than the seed pattern. A snapshot brings its own grid,
+    // which is checked instead.
+    pub fn validate(&self) -> Result<(), GameOfLifeError> {
+        if self.options.backend == Backend::Soa {
+            let unsupported = self.options.unsupported_by_soa();
+            if !unsupported.is_empty() {
+                return Err(GameOfLifeError::InvalidConfig(format!(
+                    "--backend soa does not support {}",
+                    unsupported.join(", ")
+                )));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.density) {
+            return Err(GameOfLifeError::InvalidConfig(format!(
+                "density must be between 0 and 1, got {}",
+                self.density
+            )));
+        }
+        let (width, height) = self.grid_size();
+        let (grid_width, grid_height) = run_grid_size(width, height, &self.options);
+        check_grid_size(grid_width, grid_height, self.options.force)?;
+        check_compaction(&self.rules()?, &self.options)?;
+        if self.options.snapshot.is_some() {
+            return Ok(());
+        }
+        if let Some(pattern) = &self.options.pattern {
+            if pattern.width > width || pattern.height > height {
+                return Err(GameOfLifeError::PatternTooLarge {
+                    pattern: (pattern.width, pattern.height),
+                    grid: (width, height),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Seeds a grid from the pattern, an empty grid, a random soup or an all-live
+    // grid, in that order of preference.
+    pub fn seed_fn(&self) -> impl FnOnce(&mut World, u32, u32) {
+        let pattern = self.options.pattern.clone();
+        let empty = self.options.empty;
+        let (seed, density) = (self.seed, self.density);
+        move |world, width, height| match (pattern, seed) {
+            (Some(pattern), _) => {
+                let live = pattern.cells.into_iter().collect();
+                spawn_pattern_cells(world, width, height, &live);
+            }
+            (None, _) if empty => spawn_empty_cells(world, width, height),
+            (None, Some(seed)) => spawn_random_cells(world, width, height, seed, density),
+            (None, None) => spawn_cells(world, width, height),
+        }
+    }
+}
+
+// Runs `config`, sizing the grid and picking the rule from the pattern where the
+// config leaves them unset.
+pub fn initialize(config: SimConfig) -> Result<RunSummary, GameOfLifeError> {
+    config.validate()?;
+    let (width, height) = config.grid_size();
+    let rules = config.rules()?;
+    let seed_fn = config.seed_fn();
+    let SimConfig {
+        generations,
+        options,
+        ..
+    } = config;
+    initialize_with(width, height, generations, rules, options, seed_fn)
+}
+
+// Seeds a `width` x `height` region with `seed_fn` and places it `margin` cells in
+// from every edge of a grid that much larger, so the seed has dead space to grow into
+// before it reaches the real edges. The seeded cells are moved into place and the
+// margin filled with dead ones, so `world` should not have any cells yet.
+pub fn seed_with_margin<F>(world: &mut World, width: u32, height: u32, margin: u32, seed_fn: F)
+where
+    F: FnOnce(&mut World, u32, u32),
+{
+    seed_fn(world, width, height);
+    let offset = margin as i32;
+    let (grid_width, grid_height) = (width + 2 * margin, height + 2 * margin);
+    let mut query = world.query::<(&mut Position, &mut Index)>();
+    for (mut pos, mut index) in query.iter_mut(world) {
+        pos.x += offset;
+        pos.y += offset;
+        index.0 = pos.y as u32 * grid_width + pos.x as u32;
+    }
+
+    let inside = move |x: u32, y: u32| {
+        (margin..margin + width).contains(&x) && (margin..margin + height).contains(&y)
+    };
+    world.spawn_batch(
+        (0..grid_width * grid_height)
+            .map(move |i| (i % grid_width, i / grid_width))
+            .filter(move |&(x, y)| !inside(x, y))
+            .map(move |(x, y)| CellBundle {
+                position: Position {
+                    x: x as i32,
+                    y: y as i32,
+                },
+                index: Index(y * grid_width + x),
+                ..Default::default()
+            }),
+    );
+}
+
+// Like `initialize`, but seeds the grid with `seed_fn` (e.g. one of the `spawn_*`
+// functions). A snapshot in `options` takes precedence over the seed. The grid size
+// is checked with `check_grid_size`. `options.on_complete` is called with the
+// summary however the run ends.
+pub fn initialize_with<F>(
+    width: u32,
+    height: u32,
+    generations: u32,
+    rules: Rules,
+    options: RunOptions,
+    seed_fn: F,
+) -> Result<RunSummary, GameOfLifeError>
+where
+    F: FnOnce(&mut World, u32, u32),
+{
+    let (grid_width, grid_height) = run_grid_size(width, height, &options);
+    check_grid_size(grid_width, grid_height, options.force)?;
+    check_compaction(&rules, &options)?;
+    let on_complete = options.on_complete.clone();
+    let summary = run_seeded(width, height, generations, rules, options, seed_fn);
+    if let Some(on_complete) = on_complete {
+        (on_complete.0)(&summary);
+    }
+    Ok(summary)
+}
+
+fn run_seeded<F>(
+    width: u32,
+    height: u32,
+    generations: u32,
+    rules: Rules,
+    options: RunOptions,
+    seed_fn: F,
+) -> RunSummary
+where
+    F: FnOnce(&mut World, u32, u32),
+{
+    // Timed here rather than in the `spawn_*` functions, which also seed the worlds of
+    // surveys, batches and tests.
+    let start = Instant::now();
+    let mut world = match options.snapshot.clone() {
+        Some(snapshot) => snapshot.into_world(rules),
+        None if options.border_margin > 0 => {
+            let margin = options.border_margin;
+            let mut world = setup_world(width + 2 * margin, height + 2 * margin, rules);
+            seed_with_margin(&mut world, width, height, margin, seed_fn);
+            world
+        }
+        None => {
+            let mut world = setup_world(width, height, rules);
+            seed_fn(&mut world, width, height);
+            world
+        }
+    };
+    let grid = world.resource::<Grid>();
+    status!("Spawning {:?} cells", grid.width * grid.height);
+    status!("Spawning cells took {:?}", start.elapsed());
+    world.insert_resource(Generations(generations));
+    world.init_resource::<Control>();
+    world.insert_resource(options.interrupted.clone());
+    world.insert_resource(options.boundary);
+    if let Some(limit) = options.time_limit {
+        world.insert_resource(TimeLimit(limit));
+    }
+    if options.profile {
+        world.insert_resource(ProfileStats::default());
+    }
+    if let Some(every) = options.autosave_every {
+        let last = world.resource::<GenerationCounter>().0 + generations;
+        world.insert_resource(AutosaveConfig {
+            every,
+            dir: PathBuf::from("."),
+            last: Some(last),
+        });
+    }
+    if let Some(activity) = options.activity {
+        world.insert_resource(activity);
+    }
+    if let Some(every) = options.compact_every {
+        world.insert_resource(Compaction::every(every));
+    }
+    if let Some(script) = options.script.clone() {
+        world.insert_resource(script);
+    }
+    if let Some(hensel) = options.hensel.clone() {
+        world.insert_resource(hensel);
+        world.insert_resource(RuleSymmetry::Anisotropic);
+    }
+    if options.neighborhood != Neighborhood::Moore {
+        world.insert_resource(options.neighborhood);
+    }
+    if options.include_self {
+        world.insert_resource(IncludeSelf);
+    }
+    if !options.rule_regions.is_empty() {
+        world.insert_resource(RuleRegions(options.rule_regions.clone()));
+    }
+    if let Some(mask) = options.mask.clone() {
+        let mut query = world.query::<(&Position, &mut State)>();
+        for (pos, mut state) in query.iter_mut(&mut world) {
+            if !mask.contains(pos.x, pos.y) {
+                state.0 = false;
+            }
+        }
+        world.insert_resource(mask);
+    }
+    let mut schedule = build_schedule();
+    if options.deterministic {
+        schedule.set_executor_kind(bevy_ecs::schedule::ExecutorKind::SingleThreaded);
+        world.insert_resource(Deterministic);
+    }
+
+    if options.dry_run {
+        let population = live_cells(&mut world).len();
+        let summary = RunSummary {
+            generations: world.resource::<GenerationCounter>().0,
+            population,
+            reason: TerminationReason::finished(population),
+        };
+        let grid = world.resource::<Grid>();
+        println!(
+            "Dry run: grid {}x{} ({} cells), rule {}, boundary {}, starting generation {}, initial population {}",
+            grid.width,
+            grid.height,
+            grid.width * grid.height,
+            world.resource::<Rules>(),
+            options.boundary,
+            summary.generations,
+            summary.population
+        );
+        return summary;
+    }
+
+    if let Some(limit) = options.history_limit {
+        world.insert_resource(History::new(limit));
+    }
+    if options.interactive {
+        let summary = interactive::run(&mut world, &mut schedule);
+        status!("{}", summary);
+        return summary;
+    }
+
+    if options.backend == Backend::Soa {
+        return run_soa(&mut world, generations, &options);
+    }
+
+    let mut sinks: Vec<Box<dyn OutputSink>> = Vec::new();
+    if options.render && options.preview {
+        sinks.push(Box::new(output::PreviewSink::new(
+            Box::new(std::io::stdout()),
+            options.view_offset,
+            options.origin,
+        )));
+    } else if options.render && options.incremental {
+        sinks.push(Box::new(output::IncrementalSink::new(options.view_offset)));
+    } else if options.render {
+        sinks.push(Box::new(output::AsciiSink {
+            view_offset: options.view_offset,
+            crop: options.crop,
+            origin: options.origin,
+            max_size: options.max_render,
+            border: options.show_border,
+            follow: options.follow.then(|| Follow::new(options.view_offset)),
+        }));
+    }
+    let jsonl_to_stdout = options
+        .jsonl
+        .as_ref()
+        .is_some_and(|path| path.as_os_str() == "-");
+    match &options.jsonl {
+        Some(_) if jsonl_to_stdout => sinks.push(Box::new(
+            output::JsonlSink::new(Box::new(std::io::stdout()), options.connectivity)
+                .with_bbox(options.bbox_log)
+                .with_gliders(options.count_gliders),
+        )),
+        Some(path) => match std::fs::File::create(path) {
+            Ok(file) => sinks.push(Box::new(
+                output::JsonlSink::new(
+                    Box::new(std::io::BufWriter::new(file)),
+                    options.connectivity,
+                )
+                .with_bbox(options.bbox_log)
+                .with_gliders(options.count_gliders),
+            )),
+            Err(err) => eprintln!("Failed to create {}: {}", path.display(), err),
+        },
+        None => (),
+    }
+
+    if let Some(path) = &options.record {
+        match std::fs::File::create(path) {
+            Ok(file) => sinks.push(Box::new(RecordSink::new(Box::new(
+                std::io::BufWriter::new(file),
+            )))),
+            Err(err) => eprintln!("Failed to create {}: {}", path.display(), err),
+        }
+    }
+    if let Some(path) = &options.apng {
+        #[cfg(feature = "image")]
+        sinks.push(Box::new(png::ApngSink::new(
+            path.clone(),
+            options.apng_delay_ms,
+        )));
+        #[cfg(not(feature = "image"))]
+        eprintln!(
+            "Cannot save {}: built without the image feature",
+            path.display()
+        );
+    }
+    if let Some(range) = &options.export_range {
+        sinks = sinks
+            .into_iter()
+            .map(|sink| {
+                Box::new(output::RangeSink::new(sink, range.clone())) as Box<dyn OutputSink>
+            })
+            .collect();
+    }
+    // JSON lines on stdout already carry the bounding box, and plain `gen W H` lines
+    // between them would break the stream.
+    if options.bbox_log && !jsonl_to_stdout {
+        sinks.push(Box::new(output::BboxSink::new(Box::new(std::io::stdout()))));
+    }
+    if options.detect_attractors {
+        sinks.push(Box::<output::AttractorSink>::default());
+    }
+    if options.stats {
+        sinks.push(Box::<output::PopulationSink>::default());
+    }
+    if options.count_gliders {
+        sinks.push(Box::<output::GliderSink>::default());
+    }
+
+    let progress = progress_bar(options.progress, generations);
+    let start = Instant::now();
+    let summary = output::run_with_sinks(
+        &mut world,
+        &mut schedule,
+        generations,
+        options.show_initial,
+        &mut sinks,
+        &progress,
+    );
+    let duration = start.elapsed();
+    if options.bench_json {
+        let grid = world.resource::<Grid>();
+        let report = BenchReport::new(&summary, duration, grid.width * grid.height);
+        println!("{}", report.to_json());
+    } else {
+        status!(
+            "Running {:?} generations took {:?}",
+            summary.generations,
+            duration
+        );
+        status!("{}", summary);
+        let tally = objects::object_tally(&live_cells(&mut world), options.connectivity);
+        status!("Objects: {}", objects::format_tally(&tally));
+    }
+    if options.deterministic {
+        println!("Digest: {:016x}", state_digest(&live_cells(&mut world)));
+    }
+    if let Some(profile) = world.get_resource::<ProfileStats>() {
+        print!("{}", profile);
+    }
+    if let Some(path) = &options.save_times_alive {
+        let counts = times_alive(&mut world);
+        if let Some((&(x, y), &count)) = counts
+            .iter()
+            .max_by_key(|&(&(x, y), &count)| (count, -y, -x))
+        {
+            status!("Most often alive: ({}, {}) for {} generations", x, y, count);
+        }
+        #[cfg(feature = "image")]
+        match png::save_heatmap(&counts, world.resource::<Grid>(), path) {
+            Ok(()) => status!("Saved times-alive heatmap to {}", path.display()),
+            Err(err) => eprintln!("Failed to save heatmap: {}", err),
+        }
+        #[cfg(not(feature = "image"))]
+        eprintln!(
+            "Cannot save {}: built without the image feature",
+            path.display()
+        );
+    }
+    if let Some(path) = &options.save_pbm {
+        let live = live_cells(&mut world);
+        match save_pbm(&live, world.resource::<Grid>(), path) {
+            Ok(()) => status!("Saved bitmap to {}", path.display()),
+            Err(err) => eprintln!("Failed to save bitmap: {}", err),
+        }
+    }
+    if let Some(path) = &options.save_snapshot {
+        match save_snapshot(&mut world, path) {
+            Ok(()) => status!("Saved snapshot to {}", path.display()),
+            Err(err) => eprintln!("Failed to save snapshot: {}", err),
+        }
+    }
+    summary
+}
+
+// Runs a seeded world on the struct-of-arrays backend. Per-generation output, scripts,
+// masks and the other schedule-driven features are not available there.
+fn run_soa(world: &mut World, generations: u32, options: &RunOptions) -> RunSummary {
+    let mut soa = SoaGrid::from_world(world, options.boundary);
+    let start = Instant::now();
+    let mut reason = None;
+    let mut completed = 0;
+    for step in 1..=generations {
+        if options.interrupted.is_set() {
+            reason = Some(TerminationReason::Interrupted);
+            break;
+        }
+        if options
+            .time_limit
+            .is_some_and(|limit| start.elapsed() >= limit)
+        {
+            reason = Some(TerminationReason::TimedOut);
+            break;
+        }
+        soa.step();
+        completed = step;
+        if soa.is_extinct() {
+            break;
+        }
+    }
+    let duration = start.elapsed();
+    let summary = RunSummary {
+        generations: completed,
+        population: soa.population(),
+        reason: reason.unwrap_or_else(|| TerminationReason::finished(soa.population())),
+    };
+
+    if options.bench_json {
+        let report = BenchReport::new(&summary, duration, soa.width * soa.height);
+        println!("{}", report.to_json());
+    } else {
+        status!(
+            "Running {:?} generations took {:?}",
+            summary.generations,
+            duration
+        );
+        status!("{}", summary);
+        let tally = objects::object_tally(&soa.live_cells(), options.connectivity);
+        status!("Objects: {}", objects::format_tally(&tally));
+    }
+    if options.deterministic {
+        println!("Digest: {:016x}", state_digest(&soa.live_cells()));
+    }
+    if let Some(path) = &options.save_pbm {
+        let grid = Grid {
+            width: soa.width,
+            height: soa.height,
+        };
+        match save_pbm(&soa.live_cells(), &grid, path) {
+            Ok(()) => status!("Saved bitmap to {}", path.display()),
+            Err(err) => eprintln!("Failed to save bitmap: {}", err),
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
     use bevy_ecs::system::RunSystemOnce;
+    use std::collections::{BTreeSet, HashMap};
+    use std::sync::atomic::AtomicU32;
+
+    use super::*;
+
+    // Names of the systems in the order they ran.
+    #[derive(Resource, Default)]
+    struct ExecutionLog(Vec<&'static str>);
+
+    fn record(name: &'static str) -> impl FnMut(In<()>, ResMut<ExecutionLog>) {
+        move |_, mut log| log.0.push(name.rsplit("::").next().unwrap().trim())
+    }
+
+    macro_rules! recorded {
+        ($system:path) => {
+            $system.pipe(record(stringify!($system)))
+        };
+    }
+
+    #[test]
+    fn test_update_system_order() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        world.init_resource::<ExecutionLog>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(update_chain!(recorded).chain());
+        schedule.run(&mut world);
+
+        assert_eq!(
+            world.resource::<ExecutionLog>().0,
+            vec![
+                "plan_activity_system",
+                "rebuild_cell_positions",
+                "update_neighbors_brute_force_system",
+                "update_cells_system",
+                "spawn_births_system",
+                "advance_background_system",
+                "count_times_alive_system",
+                "advance_generation_system",
+                "scripted_mutation_system",
+                "autosave_system",
+                "compact_system",
+                "plan_activity_system",
+                "rebuild_cell_positions",
+                "update_neighbors_brute_force_system",
+            ]
+        );
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(0, 1), (1, 1), (2, 1)])
+        );
+    }
+
+    #[test]
+    fn test_position_distances() {
+        let origin = Position { x: 0, y: 0 };
+        let cases = [
+            (Position { x: 3, y: 4 }, 5, 25, 5.0),
+            (Position { x: 1, y: 1 }, 1, 2, std::f64::consts::SQRT_2),
+            (Position { x: -2, y: 3 }, 3, 13, 13f64.sqrt()),
+            (Position { x: 0, y: 0 }, 0, 0, 0.0),
+        ];
+        for (p, distance, squared, exact) in cases {
+            assert_eq!(origin.distance(p), distance);
+            assert_eq!(origin.distance_squared(p), squared);
+            assert_eq!(p.distance_squared(origin), squared);
+            assert!((origin.distance_f64(p) - exact).abs() < 1e-12);
+            assert_eq!(origin.distance(p), origin.distance_f64(p).floor() as i64);
+        }
+
+        let far = Position {
+            x: i32::MAX,
+            y: i32::MIN,
+        };
+        let near = Position { x: 0, y: -1 };
+        assert_eq!(near.distance_squared(far), 2 * (i32::MAX as i64).pow(2));
+        assert_eq!(
+            origin.distance(Position { x: i32::MAX, y: 0 }),
+            i32::MAX as i64
+        );
+        assert_eq!(
+            far.distance_squared(Position {
+                x: i32::MIN,
+                y: i32::MAX
+            }),
+            i64::MAX
+        );
+    }
+
+    #[test]
+    fn test_toroidal_distance_wraps() {
+        let left = Position { x: 0, y: 5 };
+        let right = Position { x: 9, y: 5 };
+        assert_eq!(left.distance(right), 9);
+        assert_eq!(left.toroidal_distance(right, 10, 10), 1);
+
+        let corner = Position { x: 0, y: 0 };
+        let opposite = Position { x: 9, y: 9 };
+        assert_eq!(corner.distance(opposite), 12);
+        assert_eq!(corner.toroidal_distance(opposite, 10, 10), 1);
+        assert_eq!(opposite.toroidal_distance(corner, 10, 10), 1);
+
+        let centre = Position { x: 5, y: 5 };
+        assert_eq!(
+            corner.toroidal_distance(centre, 10, 10),
+            corner.distance(centre)
+        );
+    }
+
+    #[test]
+    fn test_block() {
+        let mut world = setup_world(2, 2, Rules::default());
+        spawn_block_cells(&mut world, 2, 2);
+        run_one_generation(&mut world);
+
+        let mut query = world.query::<(&Position, &State, &Neighbors)>();
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(*cells[0].1, State(true));
+        assert_eq!(*cells[1].1, State(true));
+        assert_eq!(*cells[2].1, State(true));
+        assert_eq!(*cells[3].1, State(true));
+        assert_eq!(*cells[0].2, Neighbors(3));
+        assert_eq!(*cells[1].2, Neighbors(3));
+        assert_eq!(*cells[2].2, Neighbors(3));
+        assert_eq!(*cells[3].2, Neighbors(3));
+
+        run_one_generation(&mut world);
+
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells.len(), 4);
+        assert_eq!(*cells[0].1, State(true));
+        assert_eq!(*cells[1].1, State(true));
+        assert_eq!(*cells[2].1, State(true));
+        assert_eq!(*cells[3].1, State(true));
+        assert_eq!(*cells[0].2, Neighbors(3));
+        assert_eq!(*cells[1].2, Neighbors(3));
+        assert_eq!(*cells[2].2, Neighbors(3));
+        assert_eq!(*cells[3].2, Neighbors(3));
+
+        run_one_generation(&mut world);
+
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells.len(), 4);
+        assert_eq!(*cells[0].1, State(true));
+        assert_eq!(*cells[1].1, State(true));
+        assert_eq!(*cells[2].1, State(true));
+        assert_eq!(*cells[3].1, State(true));
+        assert_eq!(*cells[0].2, Neighbors(3));
+        assert_eq!(*cells[1].2, Neighbors(3));
+        assert_eq!(*cells[2].2, Neighbors(3));
+        assert_eq!(*cells[3].2, Neighbors(3));
+    }
+
+    #[test]
+    fn test_beehive() {
+        let mut world = setup_world(6, 3, Rules::default());
+        spawn_beehive_cells(&mut world, 6, 3);
+        run_one_generation(&mut world);
+
+        let mut query = world.query::<(&Position, &State, &Neighbors)>();
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 18);
+        assert_eq!(*cells[0].1, State(false));
+        assert_eq!(*cells[1].1, State(false));
+        assert_eq!(*cells[2].1, State(true));
+        assert_eq!(*cells[3].1, State(true));
+        assert_eq!(*cells[4].1, State(false));
+        assert_eq!(*cells[5].1, State(false));
+        assert_eq!(*cells[6].1, State(false));
+        assert_eq!(*cells[7].1, State(true));
+        assert_eq!(*cells[8].1, State(false));
+        assert_eq!(*cells[9].1, State(false));
+        assert_eq!(*cells[10].1, State(true));
+        assert_eq!(*cells[11].1, State(false));
+        assert_eq!(*cells[12].1, State(false));
+        assert_eq!(*cells[13].1, State(false));
+        assert_eq!(*cells[14].1, State(true));
+        assert_eq!(*cells[15].1, State(true));
+        assert_eq!(*cells[16].1, State(false));
+        assert_eq!(*cells[17].1, State(false));
+
+        assert_eq!(*cells[0].2, Neighbors(1));
+        assert_eq!(*cells[1].2, Neighbors(2));
+        assert_eq!(*cells[2].2, Neighbors(2));
+        assert_eq!(*cells[3].2, Neighbors(2));
+        assert_eq!(*cells[4].2, Neighbors(2));
+        assert_eq!(*cells[5].2, Neighbors(1));
+
+        assert_eq!(*cells[6].2, Neighbors(1));
+        assert_eq!(*cells[7].2, Neighbors(2));
+        assert_eq!(*cells[8].2, Neighbors(5));
+        assert_eq!(*cells[9].2, Neighbors(5));
+        assert_eq!(*cells[10].2, Neighbors(2));
+        assert_eq!(*cells[11].2, Neighbors(1));
+
+        assert_eq!(*cells[12].2, Neighbors(1));
+        assert_eq!(*cells[13].2, Neighbors(2));
+        assert_eq!(*cells[14].2, Neighbors(2));
+        assert_eq!(*cells[15].2, Neighbors(2));
+        assert_eq!(*cells[16].2, Neighbors(2));
+        assert_eq!(*cells[17].2, Neighbors(1));
+
+        run_one_generation(&mut world);
+
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 18);
+        assert_eq!(*cells[0].1, State(false));
+        assert_eq!(*cells[1].1, State(false));
+        assert_eq!(*cells[2].1, State(true));
+        assert_eq!(*cells[3].1, State(true));
+        assert_eq!(*cells[4].1, State(false));
+        assert_eq!(*cells[5].1, State(false));
+        assert_eq!(*cells[6].1, State(false));
+        assert_eq!(*cells[7].1, State(true));
+        assert_eq!(*cells[8].1, State(false));
+        assert_eq!(*cells[9].1, State(false));
+        assert_eq!(*cells[10].1, State(true));
+        assert_eq!(*cells[11].1, State(false));
+        assert_eq!(*cells[12].1, State(false));
+        assert_eq!(*cells[13].1, State(false));
+        assert_eq!(*cells[14].1, State(true));
+        assert_eq!(*cells[15].1, State(true));
+        assert_eq!(*cells[16].1, State(false));
+        assert_eq!(*cells[17].1, State(false));
+
+        assert_eq!(*cells[0].2, Neighbors(1));
+        assert_eq!(*cells[1].2, Neighbors(2));
+        assert_eq!(*cells[2].2, Neighbors(2));
+        assert_eq!(*cells[3].2, Neighbors(2));
+        assert_eq!(*cells[4].2, Neighbors(2));
+        assert_eq!(*cells[5].2, Neighbors(1));
+
+        assert_eq!(*cells[6].2, Neighbors(1));
+        assert_eq!(*cells[7].2, Neighbors(2));
+        assert_eq!(*cells[8].2, Neighbors(5));
+        assert_eq!(*cells[9].2, Neighbors(5));
+        assert_eq!(*cells[10].2, Neighbors(2));
+        assert_eq!(*cells[11].2, Neighbors(1));
+
+        assert_eq!(*cells[12].2, Neighbors(1));
+        assert_eq!(*cells[13].2, Neighbors(2));
+        assert_eq!(*cells[14].2, Neighbors(2));
+        assert_eq!(*cells[15].2, Neighbors(2));
+        assert_eq!(*cells[16].2, Neighbors(2));
+        assert_eq!(*cells[17].2, Neighbors(1));
+    }
+
+    #[test]
+    fn test_blinker() {
+        let mut world = setup_world(6, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        println!("First run");
+        run_one_generation(&mut world);
+        world.run_system_once(print_all_entities_system);
+
+        let mut query = world.query::<(&Position, &State, &Neighbors)>();
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 9);
+
+        assert_eq!(*cells[0].1, State(false));
+        assert_eq!(*cells[1].1, State(false));
+        assert_eq!(*cells[2].1, State(false));
+
+        assert_eq!(*cells[3].1, State(true));
+        assert_eq!(*cells[4].1, State(true));
+        assert_eq!(*cells[5].1, State(true));
+
+        assert_eq!(*cells[6].1, State(false));
+        assert_eq!(*cells[7].1, State(false));
+        assert_eq!(*cells[8].1, State(false));
+
+        assert_eq!(*cells[0].2, Neighbors(2));
+        assert_eq!(*cells[1].2, Neighbors(3));
+        assert_eq!(*cells[2].2, Neighbors(2));
+
+        assert_eq!(*cells[3].2, Neighbors(1));
+        assert_eq!(*cells[4].2, Neighbors(2));
+        assert_eq!(*cells[5].2, Neighbors(1));
+
+        assert_eq!(*cells[6].2, Neighbors(2));
+        assert_eq!(*cells[7].2, Neighbors(3));
+        assert_eq!(*cells[8].2, Neighbors(2));
+
+        println!("Second run");
+        run_one_generation(&mut world);
+        world.run_system_once(print_all_entities_system);
+
+        let cells = query.iter(&world).collect::<Vec<_>>();
+        assert_eq!(cells.len(), 9);
+        assert_eq!(*cells[0].1, State(false));
+        assert_eq!(*cells[1].1, State(true));
+        assert_eq!(*cells[2].1, State(false));
+
+        assert_eq!(*cells[3].1, State(false));
+        assert_eq!(*cells[4].1, State(true));
+        assert_eq!(*cells[5].1, State(false));
+
+        assert_eq!(*cells[6].1, State(false));
+        assert_eq!(*cells[7].1, State(true));
+        assert_eq!(*cells[8].1, State(false));
+
+        assert_eq!(*cells[0].2, Neighbors(2));
+        assert_eq!(*cells[1].2, Neighbors(1));
+        assert_eq!(*cells[2].2, Neighbors(2));
+
+        assert_eq!(*cells[3].2, Neighbors(3));
+        assert_eq!(*cells[4].2, Neighbors(2));
+        assert_eq!(*cells[5].2, Neighbors(3));
+
+        assert_eq!(*cells[6].2, Neighbors(2));
+        assert_eq!(*cells[7].2, Neighbors(1));
+        assert_eq!(*cells[8].2, Neighbors(2));
+    }
+
+    #[test]
+    fn test_run_one_generation_advances_once() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let vertical = live_cells(&mut world);
+        run_one_generation(&mut world);
+        assert_eq!(world.resource::<GenerationCounter>().0, 1);
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(0, 1), (1, 1), (2, 1)])
+        );
+        run_one_generation(&mut world);
+        assert_eq!(world.resource::<GenerationCounter>().0, 2);
+        assert_eq!(live_cells(&mut world), vertical);
+    }
+
+    #[test]
+    fn test_run_threaded_slow_renderer() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 3,
+            height: 3,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource(Rules::default());
+        world.insert_resource(CellPositions::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+                update_cells_system,
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+            )
+                .chain(),
+        );
+
+        let rendered = AtomicU32::new(0);
+        let stats = run_threaded(&mut world, &mut schedule, 20, |_, live| {
+            assert_eq!(live.len(), 3);
+            std::thread::sleep(Duration::from_millis(5));
+            rendered.fetch_add(1, Ordering::SeqCst);
+        });
+
+        assert_eq!(stats.simulated, 20);
+        assert_eq!(stats.sent + stats.dropped, 20);
+        assert_eq!(rendered.load(Ordering::SeqCst), stats.sent);
+
+        // Blinker has period 2, so an even number of steps ends in the start phase.
+        let live = live_cells(&mut world);
+        assert_eq!(live, HashSet::from([(1, 0), (1, 1), (1, 2)]));
+    }
+
+    #[test]
+    fn test_neighbor_count_at_beehive() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 6,
+            height: 3,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource(Rules::default());
+        world.insert_resource(CellPositions::default());
+        spawn_beehive_cells(&mut world, 6, 3);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+                update_cells_system,
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+            )
+                .chain(),
+        );
+        schedule.run(&mut world);
+
+        assert_eq!(neighbor_count_at(&mut world, 0, 0), Some(1));
+        assert_eq!(neighbor_count_at(&mut world, 1, 0), Some(2));
+        assert_eq!(neighbor_count_at(&mut world, 2, 1), Some(5));
+        assert_eq!(neighbor_count_at(&mut world, 3, 1), Some(5));
+        assert_eq!(neighbor_count_at(&mut world, 5, 2), Some(1));
+        assert_eq!(neighbor_count_at(&mut world, 6, 0), None);
+
+        assert_eq!(cell_state_at(&mut world, 2, 0), Some(true));
+        assert_eq!(cell_state_at(&mut world, 2, 1), Some(false));
+        assert_eq!(cell_state_at(&mut world, -1, 0), None);
+    }
+
+    #[test]
+    fn test_show_initial_renders_generation_zero() {
+        let mut frames = Vec::new();
+        for show_initial in [false, true] {
+            let mut world = World::new();
+            world.insert_resource(Grid {
+                width: 3,
+                height: 3,
+            });
+            world.insert_resource(CellsChanged(true));
+            world.insert_resource(Rules::default());
+            world.insert_resource(CellPositions::default());
+            spawn_blinker_cells(&mut world, 3, 3);
+            let mut schedule = Schedule::default();
+            schedule.add_systems(
+                (
+                    rebuild_cell_positions,
+                    update_neighbors_brute_force_system,
+                    update_cells_system,
+                    rebuild_cell_positions,
+                    update_neighbors_brute_force_system,
+                )
+                    .chain(),
+            );
+
+            let mut rendered = Vec::new();
+            run_generations(&mut world, &mut schedule, 4, show_initial, |gen, world| {
+                let live = live_cells(world);
+                rendered.push((gen, render(&live, world.resource::<Grid>())));
+            });
+            frames.push(rendered);
+        }
+
+        assert_eq!(frames[0].len(), 4);
+        assert_eq!(frames[1].len(), 5);
+        assert_eq!(frames[1][0], (0, ".#.\n.#.\n.#.\n".to_string()));
+        assert_eq!(frames[1][1], (1, "...\n###\n...\n".to_string()));
+        assert_eq!(frames[0][0], frames[1][1]);
+    }
+
+    #[test]
+    fn test_seeds_offspring() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 7,
+            height: 5,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource("seeds".parse::<Rules>().unwrap());
+        world.insert_resource(CellPositions::default());
+        spawn_pattern_cells(&mut world, 7, 5, &HashSet::from([(1, 2), (2, 2), (6, 0)]));
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+                update_cells_system,
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+            )
+                .chain(),
+        );
+        schedule.run(&mut world);
+
+        // Every live cell dies; the lone cell leaves nothing behind while the
+        // domino gives birth to the cells touching both of its halves.
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(1, 1), (2, 1), (1, 3), (2, 3)])
+        );
+    }
+
+    #[test]
+    fn test_life_without_death_never_kills() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 6,
+            height: 3,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource("lwd".parse::<Rules>().unwrap());
+        world.insert_resource(CellPositions::default());
+        spawn_cells(&mut world, 6, 3);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+                update_cells_system,
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+            )
+                .chain(),
+        );
+
+        let mut previous = live_cells(&mut world);
+        for _ in 0..5 {
+            schedule.run(&mut world);
+            let live = live_cells(&mut world);
+            assert!(live.is_superset(&previous));
+            previous = live;
+        }
+        assert_eq!(previous.len(), 18);
+    }
+
+    #[test]
+    fn test_interrupt_stops_run_with_summary() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 3,
+            height: 3,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource(Rules::default());
+        world.insert_resource(CellPositions::default());
+        let interrupted = Interrupted::default();
+        world.insert_resource(interrupted.clone());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+                update_cells_system,
+                rebuild_cell_positions,
+                update_neighbors_brute_force_system,
+            )
+                .chain(),
+        );
+
+        let summary = run_generations(&mut world, &mut schedule, 100, false, |gen, _| {
+            if gen == 3 {
+                interrupted.set();
+            }
+        });
+
+        assert_eq!(
+            summary,
+            RunSummary {
+                generations: 3,
+                population: 3,
+                reason: TerminationReason::Interrupted,
+            }
+        );
+    }
+
+    #[test]
+    fn test_control_stops_when_target_cell_lives() {
+        let mut world = setup_world(10, 10, Rules::default());
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        spawn_pattern_cells(&mut world, 10, 10, &glider);
+        world.init_resource::<Control>();
+        let mut schedule = build_schedule();
+        schedule.add_systems(
+            (|query: Query<(&Position, &State)>, mut control: ResMut<Control>| {
+                if query
+                    .iter()
+                    .any(|(pos, state)| state.0 && (pos.x, pos.y) == (4, 4))
+                {
+                    control.should_stop = true;
+                }
+            })
+            .in_set(SimulationSet::AfterUpdate),
+        );
+
+        let mut frames = Vec::new();
+        let summary = run_generations(&mut world, &mut schedule, 100, false, |gen, _| {
+            frames.push(gen)
+        });
+        assert_eq!(summary.generations, 8);
+        assert_eq!(frames.last(), Some(&8));
+        assert_eq!(cell_state_at(&mut world, 4, 4), Some(true));
+        assert!(world.resource::<Control>().should_stop);
+
+        // The stop is not carried into the next run.
+        let summary = run_generations(&mut world, &mut build_schedule(), 3, false, |_, _| ());
+        assert_eq!(summary.generations, 3);
+        assert_eq!(summary.reason, TerminationReason::Completed);
+    }
+
+    #[test]
+    fn test_time_limit_stops_run_early() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        world.insert_resource(TimeLimit(Duration::from_millis(20)));
+        let mut schedule = build_schedule();
+
+        let summary = run_generations(&mut world, &mut schedule, 1000, false, |_, _| {
+            thread::sleep(Duration::from_millis(5))
+        });
+        assert_eq!(summary.reason, TerminationReason::TimedOut);
+        assert!(summary.generations > 0 && summary.generations < 1000);
+        assert_eq!(world.resource::<GenerationCounter>().0, summary.generations);
+        assert!(summary.to_string().ends_with("(time limit reached)"));
+    }
+
+    #[test]
+    fn test_weighted_neighbors() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 3,
+            height: 3,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource(CellPositions::default());
+        // North counts double and east triple, north-east, south and south-west once
+        // and the rest not at all, for a total of 8.
+        world.insert_resource(NeighborWeights::new([2, 1, 3, 0, 1, 1, 0, 0]).unwrap());
+        spawn_pattern_cells(&mut world, 3, 3, &HashSet::from([(1, 0), (2, 1), (0, 2)]));
+        let mut schedule = Schedule::default();
+        schedule.add_systems((rebuild_cell_positions, update_neighbors_brute_force_system).chain());
+        schedule.run(&mut world);
+
+        assert_eq!(neighbor_count_at(&mut world, 1, 1), Some(2 + 3 + 1));
+        assert_eq!(neighbor_count_at(&mut world, 0, 0), Some(3));
+        assert_eq!(neighbor_count_at(&mut world, 0, 1), Some(1 + 1));
+        assert_eq!(neighbor_count_at(&mut world, 2, 2), Some(2));
+
+        let err = NeighborWeights::new([2, 1, 3, 1, 1, 1, 1, 1]).unwrap_err();
+        assert!(err.contains("sum to 11"), "{}", err);
+        assert_eq!(NeighborWeights::new([1; 8]), Ok(NeighborWeights::default()));
+    }
+
+    #[test]
+    fn test_diagonal_only_neighborhood() {
+        // A plus shape: the centre has only orthogonal neighbors, and (0, 1) has
+        // diagonal neighbors only at (1, 0) and (1, 2).
+        let plus = HashSet::from([(1, 0), (0, 1), (2, 1), (1, 2)]);
+        // An X shape: the centre has only diagonal neighbors.
+        let x = HashSet::from([(0, 0), (2, 0), (0, 2), (2, 2)]);
+        let counts = |live: &HashSet<(i32, i32)>, neighborhood: Neighborhood| {
+            let mut world = setup_world(3, 3, Rules::default());
+            world.insert_resource(neighborhood);
+            spawn_pattern_cells(&mut world, 3, 3, live);
+            world.run_system_once(rebuild_cell_positions);
+            world.run_system_once(update_neighbors_brute_force_system);
+            (
+                neighbor_count_at(&mut world, 1, 1),
+                neighbor_count_at(&mut world, 0, 1),
+            )
+        };
+
+        assert_eq!(
+            counts(&plus, Neighborhood::DiagonalOnly),
+            (Some(0), Some(2))
+        );
+        assert_eq!(counts(&x, Neighborhood::DiagonalOnly), (Some(4), Some(0)));
+        assert_eq!(counts(&plus, Neighborhood::VonNeumann), (Some(4), Some(0)));
+        assert_eq!(counts(&x, Neighborhood::VonNeumann), (Some(0), Some(2)));
+        assert_eq!(counts(&x, Neighborhood::Moore), (Some(4), Some(2)));
+        assert_eq!("diagonal".parse(), Ok(Neighborhood::DiagonalOnly));
+    }
+
+    #[test]
+    fn test_b0_background_inverts_every_generation() {
+        // B0/S5: an empty grid is born whole, then every cell sees eight live
+        // neighbors (the edges through the background) and nothing survives. Without
+        // the background the edges would see five and stay alive.
+        let rules: Rules = "B0/S5".parse().unwrap();
+        let mut world = setup_world(6, 5, rules);
+        spawn_empty_cells(&mut world, 6, 5);
+        let mut schedule = build_schedule();
+        for generation in 1..=6 {
+            schedule.run(&mut world);
+            let alive = generation % 2 == 1;
+            assert_eq!(world.resource::<Background>().alive, alive);
+            assert_eq!(
+                live_cells(&mut world).len(),
+                if alive { 30 } else { 0 },
+                "generation {}",
+                generation
+            );
+            assert_eq!(world.query::<&Position>().iter(&world).len(), 30);
+        }
+
+        let mut conway = setup_world(6, 5, Rules::default());
+        assert!(!conway.contains_resource::<Background>());
+        spawn_empty_cells(&mut conway, 6, 5);
+        run_one_generation(&mut conway);
+        assert!(live_cells(&mut conway).is_empty());
+    }
+
+    #[test]
+    fn test_include_self_adds_one_to_live_cells() {
+        let counts = |include_self: bool| {
+            let mut world = setup_world(6, 5, Rules::default());
+            if include_self {
+                world.insert_resource(IncludeSelf);
+            }
+            spawn_beehive_cells(&mut world, 6, 5);
+            world.run_system_once(rebuild_cell_positions);
+            world.run_system_once(update_neighbors_brute_force_system);
+            let mut query = world.query::<(&Position, &State, &Neighbors)>();
+            query
+                .iter(&world)
+                .map(|(pos, state, neighbors)| ((pos.x, pos.y), (state.0, neighbors.0)))
+                .collect::<HashMap<_, _>>()
+        };
 
-    use super::*;
+        let without = counts(false);
+        let with = counts(true);
+        for (cell, &(alive, count)) in &without {
+            assert_eq!(with[cell].1, count + alive as u8, "cell {:?}", cell);
+        }
+    }
 
     #[test]
-    fn test_block() {
+    fn test_sorted_live_orders_by_row_then_column() {
+        let scattered = HashSet::from([(4, 2), (0, 3), (1, 0), (3, 0), (0, 2), (2, 1)]);
+        let mut world = setup_world(5, 4, Rules::default());
+        spawn_pattern_cells(&mut world, 5, 4, &scattered);
+        assert_eq!(
+            sorted_live(&mut world),
+            vec![(1, 0), (3, 0), (2, 1), (0, 2), (4, 2), (0, 3)]
+        );
+        assert_eq!(sort_cells(scattered), sorted_live(&mut world));
+    }
+
+    #[test]
+    fn test_preview_matches_next_step() {
+        // Under Life without Death the blinker's ends survive instead of dying.
+        let blinker = HashSet::from([(1, 2), (2, 2), (3, 2)]);
+        let grid = Grid {
+            width: 5,
+            height: 5,
+        };
+        for regions in [vec![], vec!["0,0,5,5:lwd".parse().unwrap()]] {
+            let mut world = setup_world(5, 5, Rules::default());
+            world.insert_resource(RuleRegions(regions));
+            spawn_pattern_cells(&mut world, 5, 5, &blinker);
+            let before = live_cells(&mut world);
+            let preview = Preview::next(&mut world);
+            assert_eq!(preview.births, HashSet::from([(2, 1), (2, 3)]));
+            assert_eq!(live_cells(&mut world), before);
+
+            build_schedule().run(&mut world);
+            let after = live_cells(&mut world);
+            assert_eq!(preview, Preview::between(&before, &after));
+        }
+
+        let mut world = setup_world(5, 5, Rules::default());
+        spawn_pattern_cells(&mut world, 5, 5, &blinker);
+        let preview = Preview::next(&mut world);
+        assert_eq!(preview.deaths, HashSet::from([(1, 2), (3, 2)]));
+        assert_eq!(
+            render_preview(&blinker, &grid, &preview, Origin::TopLeft),
+            ".....\n..+..\n.x#x.\n..+..\n.....\n"
+        );
+    }
+
+    #[test]
+    fn test_border_margin_keeps_glider_whole() {
+        let glider = HashSet::from([(4, 3), (5, 4), (3, 5), (4, 5), (5, 5)]);
+        let seed = |world: &mut World, width, height| {
+            let glider = glider.clone();
+            spawn_pattern_cells(world, width, height, &glider)
+        };
+
+        let mut world = setup_world(14, 14, Rules::default());
+        seed_with_margin(&mut world, 6, 6, 4, seed);
+        assert_eq!(world.resource::<Grid>().width, 14);
+        let mut cells = world.query::<(&Position, &Index)>();
+        let positions = cells
+            .iter(&world)
+            .map(|(pos, index)| {
+                assert_eq!(index.0, pos.y as u32 * 14 + pos.x as u32);
+                (pos.x, pos.y)
+            })
+            .collect::<HashSet<_>>();
+        assert_eq!((positions.len(), cells.iter(&world).count()), (196, 196));
+        assert_eq!(
+            bounding_box(&live_cells(&mut world)),
+            Some(((7, 7), (9, 9)))
+        );
+        let mut schedule = build_schedule();
+        for _ in 0..8 {
+            schedule.run(&mut world);
+            let live = live_cells(&mut world);
+            assert_eq!(objects::classify(&live), Some("glider"));
+        }
+        assert_eq!(
+            bounding_box(&live_cells(&mut world)),
+            Some(((9, 9), (11, 11)))
+        );
+
+        let mut world = setup_world(6, 6, Rules::default());
+        seed(&mut world, 6, 6);
+        let mut schedule = build_schedule();
+        for _ in 0..8 {
+            schedule.run(&mut world);
+        }
+        assert_ne!(objects::classify(&live_cells(&mut world)), Some("glider"));
+    }
+
+    #[test]
+    fn test_downsample_block_to_one_cell() {
+        let block = (4..8)
+            .flat_map(|x| (4..8).map(move |y| (x, y)))
+            .collect::<HashSet<_>>();
+        assert_eq!(fit_block(16, 16, (4, 4)), (4, 4));
+        assert_eq!(fit_block(16, 16, (80, 24)), (1, 1));
+        let small = downsample(&block, (4, 4));
+        assert_eq!(small, HashSet::from([(1, 1)]));
+        let grid = Grid {
+            width: 4,
+            height: 4,
+        };
+        assert_eq!(render(&small, &grid), "....\n.#..\n....\n....\n");
+        assert_eq!(
+            downsample(&HashSet::from([(5, 0), (3, 7)]), (4, 4)),
+            HashSet::from([(1, 0), (0, 1)])
+        );
+    }
+
+    #[test]
+    fn test_frame_render_borders_all_sides() {
+        let live = HashSet::from([(0, 0), (2, 1)]);
+        let grid = Grid {
+            width: 3,
+            height: 2,
+        };
+        let plain = render(&live, &grid);
+        let framed = frame_render(&plain);
+        assert_eq!(framed, "+---+\n|#..|\n|..#|\n+---+\n");
+        let lines = framed.lines().collect::<Vec<_>>();
+        assert!(lines[0].chars().all(|c| c == '+' || c == '-'));
+        assert_eq!(lines[0], lines[lines.len() - 1]);
+        let interior = lines[1..lines.len() - 1]
+            .iter()
+            .map(|line| {
+                assert!(line.starts_with('|') && line.ends_with('|'));
+                format!("{}\n", &line[1..line.len() - 1])
+            })
+            .collect::<String>();
+        assert_eq!(interior, plain);
+    }
+
+    #[test]
+    fn test_render_cropped_glider() {
+        let mut world = setup_world(100, 100, Rules::default());
+        let glider = HashSet::from([(41, 40), (42, 41), (40, 42), (41, 42), (42, 42)]);
+        spawn_pattern_cells(&mut world, 100, 100, &glider);
+
+        let frame = render_cropped(&live_cells(&mut world), Origin::TopLeft);
+        assert_eq!(frame, ".#.\n..#\n###\n");
+        assert_eq!(bounding_box(&glider), Some(((40, 40), (42, 42))));
+        assert_eq!(
+            render_cropped(&HashSet::new(), Origin::TopLeft),
+            "(empty)\n"
+        );
+    }
+
+    #[test]
+    fn test_bottom_left_origin_flips_rows() {
+        let grid = Grid {
+            width: 4,
+            height: 3,
+        };
+        // An L: its foot is on row 2 in screen coordinates.
+        let live = HashSet::from([(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]);
+        let top = render_with_origin(&live, &grid, Origin::TopLeft);
+        let bottom = render_with_origin(&live, &grid, Origin::BottomLeft);
+        assert_eq!(top, render(&live, &grid));
+        assert_eq!(top, "#...\n#...\n###.\n");
+        assert_eq!(bottom, "###.\n#...\n#...\n");
+        assert_eq!(
+            top.lines().rev().collect::<Vec<_>>(),
+            bottom.lines().collect::<Vec<_>>()
+        );
+        assert_eq!(render_cropped(&live, Origin::BottomLeft), "###\n#..\n#..\n");
+        assert_eq!("bottom-left".parse::<Origin>(), Ok(Origin::BottomLeft));
+        assert!("bottom-right".parse::<Origin>().is_err());
+    }
+
+    #[test]
+    fn test_render_diff_blinker_step() {
+        let vertical = HashSet::from([(1, 0), (1, 1), (1, 2)]);
+        let horizontal = HashSet::from([(0, 1), (1, 1), (2, 1)]);
+
+        let diff = render_diff(&vertical, &horizontal);
+        assert_eq!(diff, "\x1b[1;2H.\x1b[2;1H#\x1b[2;3H#\x1b[3;2H.");
+        assert_eq!(diff.matches('\x1b').count(), 4);
+        assert!(!diff.contains("\x1b[2;2H"));
+        assert!(render_diff(&horizontal, &horizontal).is_empty());
+    }
+
+    #[test]
+    fn test_dry_run_spawns_without_stepping() {
+        let options = RunOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let summary = initialize(SimConfig {
+            width: Some(6),
+            height: Some(3),
+            generations: 10,
+            options,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(
+            summary,
+            RunSummary {
+                generations: 0,
+                population: 18,
+                reason: TerminationReason::Completed,
+            }
+        );
+
+        // Life without Death keeps the all-live grid from dying out early.
+        let summary = initialize(SimConfig {
+            width: Some(6),
+            height: Some(3),
+            generations: 10,
+            rule: Some("lwd".parse().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(summary.generations, 10);
+    }
+
+    #[test]
+    fn test_sim_config_defaults() {
+        let summary = initialize(SimConfig {
+            width: Some(8),
+            height: Some(6),
+            rule: Some("lwd".parse().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(summary.generations, 100);
+
+        let config = SimConfig {
+            width: Some(16),
+            height: Some(16),
+            generations: 3,
+            seed: Some(4),
+            ..Default::default()
+        };
+        let first = initialize(config.clone()).unwrap();
+        assert_eq!(first, initialize(config).unwrap());
+        assert_eq!(SimConfig::default().grid_size(), (600, 400));
+    }
+
+    #[test]
+    fn test_initialize_takes_grid_and_rule_from_pattern() {
+        let options = RunOptions {
+            pattern: Some(
+                parse_rle("x = 3, y = 1, rule = B36/S23\n3o!")
+                    .unwrap()
+                    .with_margin(1),
+            ),
+            ..Default::default()
+        };
+        let mut config = SimConfig {
+            generations: 2,
+            options,
+            ..Default::default()
+        };
+        assert_eq!(config.grid_size(), (5, 3));
+        assert_eq!(config.rules().unwrap(), "B36/S23".parse().unwrap());
+        // The blinker only survives with the margin to turn into.
+        assert_eq!(initialize(config.clone()).unwrap().population, 3);
+
+        config.width = Some(9);
+        config.rule = Some(Rules::default());
+        assert_eq!(config.grid_size(), (9, 3));
+        assert_eq!(config.rules().unwrap(), Rules::default());
+    }
+
+    #[test]
+    fn test_progress_toggle() {
+        assert!(progress_bar(false, 10).is_hidden());
+
+        let options = RunOptions {
+            progress: true,
+            ..Default::default()
+        };
+        let summary = initialize(SimConfig {
+            width: Some(6),
+            height: Some(3),
+            generations: 5,
+            rule: Some("lwd".parse().unwrap()),
+            options,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(summary.generations, 5);
+        assert_ne!(summary.reason, TerminationReason::Interrupted);
+    }
+
+    #[test]
+    fn test_scripted_mutation_applies_at_generation() {
         let mut world = World::new();
         world.insert_resource(Grid {
-            width: 2,
-            height: 2,
+            width: 6,
+            height: 6,
+        });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource(Rules::default());
+        world.insert_resource(GenerationCounter::default());
+        world.insert_resource(CellPositions::default());
+        world.insert_resource(script::parse_script("2 set 4 4 alive").unwrap());
+        spawn_pattern_cells(
+            &mut world,
+            6,
+            6,
+            &HashSet::from([(1, 1), (2, 1), (1, 2), (2, 2)]),
+        );
+        let mut schedule = build_schedule();
+
+        schedule.run(&mut world);
+        assert_eq!(cell_state_at(&mut world, 4, 4), Some(false));
+        schedule.run(&mut world);
+        assert_eq!(cell_state_at(&mut world, 4, 4), Some(true));
+        assert_eq!(live_cells(&mut world).len(), 5);
+        // The lone cell has no neighbors and dies on the following step.
+        schedule.run(&mut world);
+        assert_eq!(cell_state_at(&mut world, 4, 4), Some(false));
+        assert_eq!(live_cells(&mut world).len(), 4);
+    }
+
+    #[test]
+    fn test_fully_surrounded_cell_has_eight_neighbors() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 3,
+            height: 3,
         });
         world.insert_resource(CellsChanged(true));
-        world.insert_resource(CellPositions {
-            map: HashMap::new(),
+        world.insert_resource(CellPositions::default());
+        spawn_cells(&mut world, 3, 3);
+        let mut schedule = Schedule::default();
+        schedule.add_systems((rebuild_cell_positions, update_neighbors_brute_force_system).chain());
+        schedule.run(&mut world);
+
+        assert_eq!(neighbor_count_at(&mut world, 1, 1), Some(8));
+        assert_eq!(neighbor_count_at(&mut world, 0, 0), Some(3));
+        assert_eq!(neighbor_count_at(&mut world, 1, 0), Some(5));
+    }
+
+    #[test]
+    fn test_cells_outside_mask_stay_dead() {
+        let mut world = setup_world(5, 5, Rules::default());
+        world.insert_resource(mask::parse_mask("..#..\n..#..\n#####\n..#..\n..#..\n"));
+        spawn_pattern_cells(
+            &mut world,
+            5,
+            5,
+            &HashSet::from([(0, 2), (1, 2), (2, 2), (3, 2), (4, 2), (0, 0), (4, 4)]),
+        );
+        let mut schedule = build_schedule();
+        schedule.run(&mut world);
+
+        let cross = world.resource::<GridMask>().clone();
+        let live = live_cells(&mut world);
+        assert!(live.iter().all(|&(x, y)| cross.contains(x, y)));
+        // Without the mask the diagonal cells next to the row would be born too.
+        assert_eq!(
+            live,
+            HashSet::from([(1, 2), (2, 2), (3, 2), (2, 1), (2, 3)])
+        );
+        // (1, 1) is outside the mask, so three live neighbors don't bring it to life.
+        assert_eq!(neighbor_count_at(&mut world, 1, 1), Some(3));
+
+        for _ in 0..3 {
+            schedule.run(&mut world);
+            let live = live_cells(&mut world);
+            assert!(live.iter().all(|&(x, y)| cross.contains(x, y)));
+        }
+    }
+
+    #[test]
+    fn test_bench_json_report() {
+        let summary = RunSummary {
+            generations: 4,
+            population: 7,
+            reason: TerminationReason::Completed,
+        };
+        let report = BenchReport::new(&summary, Duration::from_millis(10), 18);
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+
+        assert_eq!(json["generations"].as_u64(), Some(4));
+        assert_eq!(json["grid_cells"].as_u64(), Some(18));
+        assert_eq!(json["final_population"].as_u64(), Some(7));
+        assert!(json["total_ms"].is_f64());
+        assert!((json["ms_per_generation"].as_f64().unwrap() - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_anisotropic_neighbor_mask() {
+        // A rule under which every cell keeps its state, so the masks after the step
+        // are those of the seed.
+        let mut world = setup_world(3, 3, "B/S012345678".parse().unwrap());
+        world.insert_resource(RuleSymmetry::Anisotropic);
+        spawn_pattern_cells(&mut world, 3, 3, &HashSet::from([(1, 0), (2, 1), (0, 2)]));
+        build_schedule().run(&mut world);
+
+        let mut query = world.query::<(&Position, &NeighborMask)>();
+        let masks = query
+            .iter(&world)
+            .map(|(pos, mask)| ((pos.x, pos.y), mask.0))
+            .collect::<HashMap<_, _>>();
+        // North (bit 0), east (bit 2) and south-west (bit 5).
+        assert_eq!(masks[&(1, 1)], 0b0010_0101);
+        // Only east.
+        assert_eq!(masks[&(0, 0)], 0b0000_0100);
+        // North-east (bit 1) and south (bit 4).
+        assert_eq!(masks[&(0, 1)], 0b0001_0010);
+        assert_eq!(masks[&(1, 0)], 0b0000_1000);
+    }
+
+    #[test]
+    fn test_rule_regions_run_side_by_side() {
+        let replicator = rle::parse_rle("2b3o$bo2bo$o3bo$o2bo$3o!").unwrap().cells;
+        let replicator = replicator
+            .iter()
+            .map(|&(x, y)| (x + 42, y + 12))
+            .collect::<HashSet<_>>();
+        let block = HashSet::from([(10, 10), (11, 10), (10, 11), (11, 11)]);
+        let conway = Rules::conway();
+        let highlife: Rules = "B36/S23".parse().unwrap();
+        let run = |live: &HashSet<(i32, i32)>, rules: &Rules, regions: Option<RuleRegions>| {
+            let mut world = setup_world(60, 40, rules.clone());
+            spawn_pattern_cells(&mut world, 60, 40, live);
+            if let Some(regions) = regions {
+                world.insert_resource(regions);
+            }
+            let mut schedule = build_schedule();
+            for _ in 0..12 {
+                schedule.run(&mut world);
+            }
+            live_cells(&mut world)
+        };
+
+        let regions = RuleRegions(vec![
+            "0,0,30,40:B3/S23".parse().unwrap(),
+            "30,0,30,40:B36/S23".parse().unwrap(),
+        ]);
+        let both = run(
+            &replicator.union(&block).copied().collect(),
+            &conway,
+            Some(regions),
+        );
+        let (left, right): (HashSet<_>, HashSet<_>) = both.into_iter().partition(|c| c.0 < 30);
+
+        assert_eq!(left, block);
+        assert_eq!(right, run(&replicator, &highlife, None));
+        assert_ne!(right, run(&replicator, &conway, None));
+    }
+
+    #[test]
+    fn test_hensel_birth_depends_on_arrangement() {
+        let mut births = Vec::new();
+        // Two corners on the same side (2c), then the same shape rotated by 45
+        // degrees, which puts both neighbors on edges instead (2e).
+        for seed in [[(0, 0), (2, 0)], [(1, 0), (2, 1)]] {
+            let mut world = setup_world(3, 3, Rules::default());
+            world.insert_resource("B2c/S".parse::<HenselRules>().unwrap());
+            world.insert_resource(RuleSymmetry::Anisotropic);
+            spawn_pattern_cells(&mut world, 3, 3, &HashSet::from(seed));
+            build_schedule().run(&mut world);
+            births.push(cell_state_at(&mut world, 1, 1));
+        }
+
+        assert_eq!(births, vec![Some(true), Some(false)]);
+    }
+
+    #[test]
+    fn test_cell_positions_flat_index() {
+        let mut world = World::new();
+        world.insert_resource(Grid {
+            width: 6,
+            height: 3,
         });
+        world.insert_resource(CellsChanged(true));
+        world.insert_resource(CellPositions::default());
+        spawn_beehive_cells(&mut world, 6, 3);
+        world.run_system_once(rebuild_cell_positions);
+
+        let cell_positions = world.resource::<CellPositions>();
+        assert_eq!(cell_positions.cells.len(), 18);
+        // Row-major: (4, 1) is at 1 * 6 + 4.
+        assert!(cell_positions.cells[10]);
+        assert!(cell_positions.is_alive(4, 1));
+        assert!(cell_positions.is_alive(2, 0));
+        assert!(!cell_positions.is_alive(0, 0));
+        assert!(!cell_positions.is_alive(-1, 1));
+        assert!(!cell_positions.is_alive(6, 1));
+        assert!(!cell_positions.is_alive(2, 3));
+    }
+
+    #[test]
+    fn test_on_complete_reports_extinction() {
+        let seen = Arc::new(std::sync::Mutex::new(None));
+        let sink = Arc::clone(&seen);
+        let options = RunOptions {
+            on_complete: Some(OnComplete::new(move |summary| {
+                *sink.lock().unwrap() = Some(summary.clone())
+            })),
+            ..Default::default()
+        };
+        let lone_cell = |world: &mut World, width, height| {
+            spawn_pattern_cells(world, width, height, &HashSet::from([(1, 1)]))
+        };
+        let summary = initialize_with(3, 3, 4, Rules::default(), options, lone_cell).unwrap();
+
+        assert_eq!(summary.reason, TerminationReason::Extinct);
+        assert_eq!(seen.lock().unwrap().as_ref(), Some(&summary));
+        // The run stops at the first empty generation instead of running out the cap.
+        assert_eq!(
+            summary.to_string(),
+            "Completed 1 generations with a final population of 0 (extinct)"
+        );
+
+        let soa = RunOptions {
+            backend: Backend::Soa,
+            ..Default::default()
+        };
+        let summary = initialize_with(3, 3, 4, Rules::default(), soa, lone_cell).unwrap();
+        assert_eq!(
+            (summary.generations, summary.reason),
+            (1, TerminationReason::Extinct)
+        );
+
+        // Under B0 an empty grid is born again, so the run goes on.
+        let b0 = "B0/S".parse().unwrap();
+        let summary = initialize_with(3, 3, 4, b0, RunOptions::default(), lone_cell).unwrap();
+        assert_eq!(summary.generations, 4);
+    }
+
+    #[test]
+    fn test_initialize_with_blinker() {
+        let summary = initialize_with(
+            3,
+            3,
+            5,
+            Rules::default(),
+            RunOptions::default(),
+            spawn_blinker_cells,
+        )
+        .unwrap();
+        assert_eq!(
+            summary,
+            RunSummary {
+                generations: 5,
+                population: 3,
+                reason: TerminationReason::Completed,
+            }
+        );
+
+        let summary = initialize_with(
+            6,
+            3,
+            5,
+            Rules::default(),
+            RunOptions::default(),
+            spawn_beehive_cells,
+        )
+        .unwrap();
+        assert_eq!(summary.population, 6);
+    }
+
+    #[test]
+    fn test_profile_records_each_system() {
+        let mut world = setup_world(3, 3, Rules::default());
+        world.insert_resource(ProfileStats::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = build_schedule();
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        let profile = world.resource::<ProfileStats>();
+        for system in ["rebuild_cell_positions", "update_neighbors", "update_cells"] {
+            let timing = profile.timings[system];
+            assert!(timing.total > Duration::ZERO, "{} took no time", system);
+            assert!(timing.calls > 0);
+        }
+        assert_eq!(profile.timings["update_neighbors"].calls, 4);
+        assert_eq!(profile.timings["update_cells"].calls, 2);
+        assert!(profile.to_string().contains("update_cells"));
+    }
+
+    #[test]
+    fn test_compute_neighbors_matches_ecs() {
+        let mut world = setup_world(6, 3, Rules::default());
+        spawn_beehive_cells(&mut world, 6, 3);
+        world.run_system_once(rebuild_cell_positions);
+        world.run_system_once(update_neighbors_brute_force_system);
+
+        let live = live_cells(&mut world);
+        let grid = Grid {
+            width: 6,
+            height: 3,
+        };
+        let counts = compute_neighbors(&live, &grid, BoundaryMode::Dead);
+        assert_eq!(counts.len(), 18);
+        let mut query = world.query::<(&Position, &Neighbors)>();
+        for (pos, neighbors) in query.iter(&world) {
+            assert_eq!(counts[&(pos.x, pos.y)], neighbors.0, "at {:?}", pos);
+        }
+
+        // Wrapping makes the bottom row of the beehive adjacent to the top row.
+        let wrapped = compute_neighbors(&live, &grid, BoundaryMode::Toroidal);
+        assert_eq!(counts[&(2, 0)], 2);
+        assert_eq!(wrapped[&(2, 0)], 4);
+        world.insert_resource(BoundaryMode::Toroidal);
+        world.run_system_once(update_neighbors_brute_force_system);
+        let mut query = world.query::<(&Position, &Neighbors)>();
+        for (pos, neighbors) in query.iter(&world) {
+            assert_eq!(wrapped[&(pos.x, pos.y)], neighbors.0, "at {:?}", pos);
+        }
+    }
+
+    #[test]
+    fn test_empty_grid_has_no_live_cells() {
+        let options = RunOptions {
+            dry_run: true,
+            empty: true,
+            ..Default::default()
+        };
+        let summary = initialize(SimConfig {
+            width: Some(5),
+            height: Some(4),
+            generations: 3,
+            options,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(summary.population, 0);
+
+        let mut world = setup_world(5, 4, Rules::default());
+        spawn_empty_cells(&mut world, 5, 4);
+        assert!(live_cells(&mut world).is_empty());
+        assert_eq!(cell_state_at(&mut world, 4, 3), Some(false));
+    }
+
+    #[test]
+    fn test_grid_snapshot_display() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let snapshot = GridSnapshot::from(&mut world);
+        assert_eq!(snapshot.to_string(), ".#.\n.#.\n.#.\n");
+
+        build_schedule().run(&mut world);
+        assert_eq!(
+            GridSnapshot::from(&mut world).to_string(),
+            "...\n###\n...\n"
+        );
+    }
+
+    #[test]
+    fn test_autosave_every() {
+        let dir =
+            std::env::temp_dir().join(format!("ecs_without_bevy_autosave_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = AutosaveConfig {
+            every: 5,
+            dir: dir.clone(),
+            last: Some(10),
+        };
+
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        world.insert_resource(config.clone());
+        let mut schedule = build_schedule();
+        for _ in 0..10 {
+            schedule.run(&mut world);
+        }
+
+        let mut saved = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        saved.sort();
+        assert_eq!(saved, vec!["autosave_00005.json", "autosave_00010.json"]);
+
+        let fifth = Snapshot::read(&config.path(5)).unwrap();
+        assert_eq!(fifth.generation, 5);
+        assert_eq!(fifth.live, vec![(0, 1), (1, 1), (2, 1)]);
+        let tenth = Snapshot::read(&config.path(10)).unwrap();
+        assert_eq!(tenth.generation, 10);
+        assert_eq!(tenth.live, vec![(1, 0), (1, 1), (1, 2)]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_oversized_dense_grid_needs_force() {
+        let err = initialize(SimConfig {
+            width: Some(5000),
+            height: Some(5000),
+            generations: 1,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(err, GameOfLifeError::GridTooLarge { .. }));
+        assert!(err.to_string().contains("dense spawning"), "{}", err);
+        assert!(err.to_string().contains("--force"), "{}", err);
+
+        let err = initialize_with(
+            5000,
+            5000,
+            1,
+            Rules::default(),
+            RunOptions::default(),
+            spawn_empty_cells,
+        )
+        .unwrap_err();
+        assert!(matches!(err, GameOfLifeError::GridTooLarge { .. }));
+        // Forcing does not lift the limit of what a u32 cell index can number.
+        let err = initialize(SimConfig {
+            width: Some(70_000),
+            height: Some(70_000),
+            options: RunOptions {
+                force: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(matches!(err, GameOfLifeError::InvalidDimensions { .. }));
+
+        // The limit applies to the grid with its border margin.
+        let margin = |border_margin| SimConfig {
+            width: Some(3000),
+            height: Some(3000),
+            options: RunOptions {
+                border_margin,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(margin(0).validate().is_ok());
+        let err = margin(200).validate().unwrap_err();
+        assert!(
+            matches!(err, GameOfLifeError::GridTooLarge { cells, .. } if cells == 3400 * 3400),
+            "{}",
+            err
+        );
+        assert!(matches!(
+            margin(u32::MAX).validate(),
+            Err(GameOfLifeError::InvalidDimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_clear_world() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        world.insert_resource(Generations(5));
+        world.insert_resource(TimeLimit(Duration::from_secs(1)));
+        world.insert_resource(Control { should_stop: true });
+        world.insert_resource(Compaction::every(1));
+        world.insert_resource(History::new(4));
+        let mut schedule = build_schedule();
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        let live = live_cells(&mut world);
+        world.resource_mut::<History>().push(2, live, None);
+        step_with_diff(&mut world, &mut schedule);
+
+        clear_world(&mut world);
+        assert_eq!(world.query::<&Position>().iter(&world).count(), 0);
+        assert!(live_cells(&mut world).is_empty());
+        assert_eq!(world.resource::<GenerationCounter>().0, 0);
+        assert!(world.resource::<CellsChanged>().0);
+        assert!(world.resource::<CellPositions>().cells.is_empty());
+        assert!(!world.contains_resource::<Generations>());
+        assert!(!world.contains_resource::<TimeLimit>());
+        assert!(!world.contains_resource::<output::PreviousLive>());
+        assert_eq!(*world.resource::<Control>(), Control::default());
+        assert!(!world.resource::<Compaction>().compacted);
+        assert!(world.resource::<History>().is_empty());
+
         spawn_block_cells(&mut world, 2, 2);
+        schedule.run(&mut world);
+        assert_eq!(live_cells(&mut world).len(), 4);
+        assert_eq!(world.resource::<GenerationCounter>().0, 1);
+    }
 
-        let mut schedule = Schedule::default();
-        schedule.add_systems(
-            (
-                rebuild_cell_positions,
-                update_neighbors_brute_force_system,
-                update_cells_system,
-                rebuild_cell_positions,
-                update_neighbors_brute_force_system,
-            )
-                .chain(),
+    #[test]
+    fn test_resize_grid_keeps_interior_cells() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let before = live_cells(&mut world);
+
+        resize_grid(&mut world, 5, 4);
+        assert_eq!(world.query::<&Position>().iter(&world).count(), 20);
+        assert_eq!(live_cells(&mut world), before);
+        assert_eq!(cell_state_at(&mut world, 4, 3), Some(false));
+        assert_eq!(cell_state_at(&mut world, 3, 1), Some(false));
+        let indices = cells_by_index(&mut world)
+            .into_iter()
+            .map(|(pos, _)| (pos.x, pos.y))
+            .collect::<Vec<_>>();
+        assert_eq!(indices[5..8], [(0, 1), (1, 1), (2, 1)]);
+
+        build_schedule().run(&mut world);
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(0, 1), (1, 1), (2, 1)])
         );
+
+        resize_grid(&mut world, 2, 2);
+        assert_eq!(world.query::<&Position>().iter(&world).count(), 4);
+        assert_eq!(live_cells(&mut world), HashSet::from([(0, 1), (1, 1)]));
+    }
+
+    #[test]
+    fn test_perturb_breaks_block() {
+        let block = HashSet::from([(1, 1), (2, 1), (1, 2), (2, 2)]);
+        let mut world = setup_world(6, 6, Rules::default());
+        spawn_pattern_cells(&mut world, 6, 6, &block);
+        let mut schedule = build_schedule();
         schedule.run(&mut world);
+        assert_eq!(live_cells(&mut world), block);
 
-        let mut query = world.query::<(&Position, &State, &Neighbors)>();
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 4);
-        assert_eq!(*cells[0].1, State(true));
-        assert_eq!(*cells[1].1, State(true));
-        assert_eq!(*cells[2].1, State(true));
-        assert_eq!(*cells[3].1, State(true));
-        assert_eq!(*cells[0].2, Neighbors(3));
-        assert_eq!(*cells[1].2, Neighbors(3));
-        assert_eq!(*cells[2].2, Neighbors(3));
-        assert_eq!(*cells[3].2, Neighbors(3));
+        assert!(perturb(&mut world, 3, 1, true));
+        assert!(!perturb(&mut world, 6, 0, true));
+        assert_eq!(cell_state_at(&mut world, 3, 1), Some(true));
+        assert_eq!(last_changed_at(&mut world, 3, 1), Some(1));
 
         schedule.run(&mut world);
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(1, 1), (3, 1), (1, 2), (2, 0), (3, 2)])
+        );
+    }
 
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 4);
-        assert_eq!(cells.len(), 4);
-        assert_eq!(*cells[0].1, State(true));
-        assert_eq!(*cells[1].1, State(true));
-        assert_eq!(*cells[2].1, State(true));
-        assert_eq!(*cells[3].1, State(true));
-        assert_eq!(*cells[0].2, Neighbors(3));
-        assert_eq!(*cells[1].2, Neighbors(3));
-        assert_eq!(*cells[2].2, Neighbors(3));
-        assert_eq!(*cells[3].2, Neighbors(3));
+    // Uses only the core dependencies, so `cargo test --no-default-features` runs it.
+    #[test]
+    fn test_headless_blinker() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let summary = output::run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            3,
+            false,
+            &mut [],
+            &progress_bar(true, 3),
+        );
+        assert_eq!(summary.generations, 3);
+        assert_eq!(summary.reason, TerminationReason::Completed);
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(0, 1), (1, 1), (2, 1)])
+        );
+    }
 
-        schedule.run(&mut world);
+    #[test]
+    fn test_times_alive_peaks_at_blinker_center() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = build_schedule();
+        for _ in 0..5 {
+            schedule.run(&mut world);
+        }
+        let counts = times_alive(&mut world);
+        assert_eq!(counts[&(1, 1)], 5);
+        assert_eq!(counts[&(0, 1)], 3);
+        assert_eq!(counts[&(1, 0)], 2);
+        assert!(counts
+            .iter()
+            .all(|(&cell, &count)| cell == (1, 1) || count < counts[&(1, 1)]));
+    }
 
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 4);
-        assert_eq!(cells.len(), 4);
-        assert_eq!(*cells[0].1, State(true));
-        assert_eq!(*cells[1].1, State(true));
-        assert_eq!(*cells[2].1, State(true));
-        assert_eq!(*cells[3].1, State(true));
-        assert_eq!(*cells[0].2, Neighbors(3));
-        assert_eq!(*cells[1].2, Neighbors(3));
-        assert_eq!(*cells[2].2, Neighbors(3));
-        assert_eq!(*cells[3].2, Neighbors(3));
+    #[test]
+    fn test_reshuffle_keeps_live_count() {
+        let shuffled = |seed| {
+            let mut world = setup_world(16, 16, Rules::default());
+            spawn_random_cells(&mut world, 16, 16, 7, 0.3);
+            let before = live_cells(&mut world);
+            reshuffle(&mut world, seed);
+            let after = live_cells(&mut world);
+            assert_eq!(after.len(), before.len());
+            assert_ne!(after, before);
+            after
+        };
+        assert_eq!(shuffled(1), shuffled(1));
+        assert_ne!(shuffled(1), shuffled(2));
     }
 
     #[test]
-    fn test_beehive() {
-        let mut world = World::new();
-        world.insert_resource(Grid {
+    fn test_view_offset_reassembles_wrapped_block() {
+        let grid = Grid {
             width: 6,
-            height: 3,
-        });
-        world.insert_resource(CellsChanged(true));
-        world.insert_resource(CellPositions {
-            map: HashMap::new(),
-        });
-        spawn_beehive_cells(&mut world, 6, 3);
-        let mut schedule = Schedule::default();
-        schedule.add_systems(
-            (
-                rebuild_cell_positions,
-                update_neighbors_brute_force_system,
-                update_cells_system,
-                rebuild_cell_positions,
-                update_neighbors_brute_force_system,
-            )
-                .chain(),
+            height: 4,
+        };
+        let live = HashSet::from([(5, 1), (0, 1), (5, 2), (0, 2)]);
+        assert_eq!(render(&live, &grid), "......\n#....#\n#....#\n......\n");
+        assert_eq!(
+            render(&view_cells(&live, &grid, (3, 1)), &grid),
+            "..##..\n..##..\n......\n......\n"
+        );
+    }
+
+    #[test]
+    fn test_follow_keeps_glider_in_view() {
+        let mut world = setup_world(16, 12, Rules::default());
+        world.insert_resource(BoundaryMode::Toroidal);
+        spawn_pattern_cells(
+            &mut world,
+            16,
+            12,
+            &HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]),
         );
+        let grid = Grid {
+            width: 16,
+            height: 12,
+        };
+        let mut follow = Follow::default();
+        let mut schedule = build_schedule();
+        for generation in 0..200 {
+            let viewed = follow.update(&live_cells(&mut world), &grid);
+            assert_eq!(viewed.len(), 5, "generation {}", generation);
+            assert!(
+                viewed
+                    .iter()
+                    .all(|&(x, y)| (6..=10).contains(&x) && (4..=8).contains(&y)),
+                "generation {}: {:?}",
+                generation,
+                viewed
+            );
+            schedule.run(&mut world);
+        }
+    }
+
+    #[test]
+    fn test_last_changed_stops_after_stabilizing() {
+        // An L tromino fills in its corner and becomes a block in generation 1.
+        let mut world = setup_world(4, 4, Rules::default());
+        spawn_pattern_cells(&mut world, 4, 4, &HashSet::from([(0, 0), (1, 0), (0, 1)]));
+        let mut schedule = build_schedule();
         schedule.run(&mut world);
+        assert_eq!(last_changed_at(&mut world, 1, 1), Some(1));
+        assert_eq!(last_changed_at(&mut world, 0, 0), Some(0));
 
-        let mut query = world.query::<(&Position, &State, &Neighbors)>();
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 18);
-        assert_eq!(*cells[0].1, State(false));
-        assert_eq!(*cells[1].1, State(false));
-        assert_eq!(*cells[2].1, State(true));
-        assert_eq!(*cells[3].1, State(true));
-        assert_eq!(*cells[4].1, State(false));
-        assert_eq!(*cells[5].1, State(false));
-        assert_eq!(*cells[6].1, State(false));
-        assert_eq!(*cells[7].1, State(true));
-        assert_eq!(*cells[8].1, State(false));
-        assert_eq!(*cells[9].1, State(false));
-        assert_eq!(*cells[10].1, State(true));
-        assert_eq!(*cells[11].1, State(false));
-        assert_eq!(*cells[12].1, State(false));
-        assert_eq!(*cells[13].1, State(false));
-        assert_eq!(*cells[14].1, State(true));
-        assert_eq!(*cells[15].1, State(true));
-        assert_eq!(*cells[16].1, State(false));
-        assert_eq!(*cells[17].1, State(false));
+        for _ in 0..5 {
+            schedule.run(&mut world);
+        }
+        let mut query = world.query::<&LastChanged>();
+        assert!(query.iter(&world).all(|last_changed| last_changed.0 <= 1));
+        assert_eq!(last_changed_at(&mut world, 1, 1), Some(1));
+        assert_eq!(last_changed_at(&mut world, 9, 9), None);
+    }
 
-        assert_eq!(*cells[0].2, Neighbors(1));
-        assert_eq!(*cells[1].2, Neighbors(2));
-        assert_eq!(*cells[2].2, Neighbors(2));
-        assert_eq!(*cells[3].2, Neighbors(2));
-        assert_eq!(*cells[4].2, Neighbors(2));
-        assert_eq!(*cells[5].2, Neighbors(1));
+    #[test]
+    fn test_compaction_rejects_b0_rules() {
+        let config = |rule: &str, script: Option<&str>| SimConfig {
+            width: Some(8),
+            height: Some(8),
+            rule: Some(rule.parse().unwrap()),
+            options: RunOptions {
+                compact_every: Some(2),
+                script: script.map(|text| script::parse_script(text).unwrap()),
+                ..RunOptions::default()
+            },
+            ..SimConfig::default()
+        };
+        assert!(config("B3/S23", None).validate().is_ok());
+        assert!(config("B3/S23", Some("5 rule B36/S23")).validate().is_ok());
+        for config in [
+            config("B0/S5", None),
+            config("B3/S23", Some("5 rule B0/S8")),
+        ] {
+            assert!(matches!(
+                config.validate(),
+                Err(GameOfLifeError::InvalidConfig(_))
+            ));
+            assert!(matches!(
+                initialize(config),
+                Err(GameOfLifeError::InvalidConfig(_))
+            ));
+        }
+    }
 
-        assert_eq!(*cells[6].2, Neighbors(1));
-        assert_eq!(*cells[7].2, Neighbors(2));
-        assert_eq!(*cells[8].2, Neighbors(5));
-        assert_eq!(*cells[9].2, Neighbors(5));
-        assert_eq!(*cells[10].2, Neighbors(2));
-        assert_eq!(*cells[11].2, Neighbors(1));
+    #[test]
+    fn test_compaction_drops_dead_entities() {
+        let mut dense = setup_world(12, 12, Rules::default());
+        spawn_cells(&mut dense, 12, 12);
+        let mut compacted = setup_world(12, 12, Rules::default());
+        spawn_cells(&mut compacted, 12, 12);
+        compacted.insert_resource(Compaction::every(2));
 
-        assert_eq!(*cells[12].2, Neighbors(1));
-        assert_eq!(*cells[13].2, Neighbors(2));
-        assert_eq!(*cells[14].2, Neighbors(2));
-        assert_eq!(*cells[15].2, Neighbors(2));
-        assert_eq!(*cells[16].2, Neighbors(2));
-        assert_eq!(*cells[17].2, Neighbors(1));
+        let mut dense_schedule = build_schedule();
+        let mut compacted_schedule = build_schedule();
+        for generation in 1..=12 {
+            dense_schedule.run(&mut dense);
+            compacted_schedule.run(&mut compacted);
+            assert_eq!(
+                live_cells(&mut dense),
+                live_cells(&mut compacted),
+                "generation {}",
+                generation
+            );
+        }
 
-        schedule.run(&mut world);
+        let entities = compacted.query::<&Position>().iter(&compacted).count();
+        assert!(entities < 144, "{} entities left", entities);
+        assert_eq!(dense.query::<&Position>().iter(&dense).count(), 144);
+    }
 
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 18);
-        assert_eq!(*cells[0].1, State(false));
-        assert_eq!(*cells[1].1, State(false));
-        assert_eq!(*cells[2].1, State(true));
-        assert_eq!(*cells[3].1, State(true));
-        assert_eq!(*cells[4].1, State(false));
-        assert_eq!(*cells[5].1, State(false));
-        assert_eq!(*cells[6].1, State(false));
-        assert_eq!(*cells[7].1, State(true));
-        assert_eq!(*cells[8].1, State(false));
-        assert_eq!(*cells[9].1, State(false));
-        assert_eq!(*cells[10].1, State(true));
-        assert_eq!(*cells[11].1, State(false));
-        assert_eq!(*cells[12].1, State(false));
-        assert_eq!(*cells[13].1, State(false));
-        assert_eq!(*cells[14].1, State(true));
-        assert_eq!(*cells[15].1, State(true));
-        assert_eq!(*cells[16].1, State(false));
-        assert_eq!(*cells[17].1, State(false));
+    #[test]
+    fn test_beehive_neighbor_range() {
+        let mut world = setup_world(6, 3, Rules::default());
+        spawn_beehive_cells(&mut world, 6, 3);
+        assert_eq!(neighbor_range(&mut world), None);
 
-        assert_eq!(*cells[0].2, Neighbors(1));
-        assert_eq!(*cells[1].2, Neighbors(2));
-        assert_eq!(*cells[2].2, Neighbors(2));
-        assert_eq!(*cells[3].2, Neighbors(2));
-        assert_eq!(*cells[4].2, Neighbors(2));
-        assert_eq!(*cells[5].2, Neighbors(1));
+        build_schedule().run(&mut world);
+        assert_eq!(live_cells(&mut world).len(), 6);
+        assert_eq!(neighbor_range(&mut world), Some((2, 2)));
 
-        assert_eq!(*cells[6].2, Neighbors(1));
-        assert_eq!(*cells[7].2, Neighbors(2));
-        assert_eq!(*cells[8].2, Neighbors(5));
-        assert_eq!(*cells[9].2, Neighbors(5));
-        assert_eq!(*cells[10].2, Neighbors(2));
-        assert_eq!(*cells[11].2, Neighbors(1));
+        perturb(&mut world, 0, 0, true);
+        assert_eq!(neighbor_range(&mut world), None);
 
-        assert_eq!(*cells[12].2, Neighbors(1));
-        assert_eq!(*cells[13].2, Neighbors(2));
-        assert_eq!(*cells[14].2, Neighbors(2));
-        assert_eq!(*cells[15].2, Neighbors(2));
-        assert_eq!(*cells[16].2, Neighbors(2));
-        assert_eq!(*cells[17].2, Neighbors(1));
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        build_schedule().run(&mut world);
+        assert_eq!(neighbor_range(&mut world), Some((1, 2)));
     }
 
     #[test]
-    fn test_blinker() {
-        let mut world = World::new();
-        world.insert_resource(Grid {
-            width: 6,
-            height: 3,
+    fn test_activity_keep_active() {
+        let run = |activity: ActivityConfig| {
+            let mut world = setup_world(3, 3, Rules::default());
+            spawn_blinker_cells(&mut world, 3, 3);
+            world.insert_resource(activity);
+            build_schedule().run(&mut world);
+            live_cells(&mut world)
+        };
+
+        // Pruning regions the moment they stop changing misses the blinker's births.
+        let aggressive = run(ActivityConfig {
+            region_size: 2,
+            keep_active: 0,
         });
-        world.insert_resource(CellsChanged(true));
-        world.insert_resource(CellPositions {
-            map: HashMap::new(),
+        assert_eq!(aggressive, HashSet::from([(1, 0), (1, 1), (1, 2)]));
+
+        let safe = run(ActivityConfig {
+            region_size: 2,
+            ..Default::default()
         });
-        spawn_blinker_cells(&mut world, 3, 3);
-        let mut schedule = Schedule::default();
-        schedule.add_systems(
-            (
-                rebuild_cell_positions,
-                update_neighbors_brute_force_system,
-                update_cells_system,
-                rebuild_cell_positions,
-                update_neighbors_brute_force_system,
-            )
-                .chain(),
-        );
-        println!("First run");
-        schedule.run(&mut world);
-        world.run_system_once(print_all_entities_system);
+        assert_eq!(safe, HashSet::from([(0, 1), (1, 1), (2, 1)]));
+    }
 
-        let mut query = world.query::<(&Position, &State, &Neighbors)>();
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 9);
+    #[test]
+    fn test_activity_matches_full_evaluation() {
+        let r_pentomino = HashSet::from([(11, 10), (12, 10), (10, 11), (11, 11), (11, 12)]);
+        let mut full = setup_world(24, 24, Rules::default());
+        spawn_pattern_cells(&mut full, 24, 24, &r_pentomino);
+        let mut active = setup_world(24, 24, Rules::default());
+        spawn_pattern_cells(&mut active, 24, 24, &r_pentomino);
+        active.insert_resource(ActivityConfig {
+            region_size: 4,
+            keep_active: 1,
+        });
 
-        assert_eq!(*cells[0].1, State(false));
-        assert_eq!(*cells[1].1, State(false));
-        assert_eq!(*cells[2].1, State(false));
+        let mut full_schedule = build_schedule();
+        let mut active_schedule = build_schedule();
+        for generation in 1..=40 {
+            full_schedule.run(&mut full);
+            active_schedule.run(&mut active);
+            assert_eq!(
+                live_cells(&mut full),
+                live_cells(&mut active),
+                "generation {}",
+                generation
+            );
+        }
+    }
 
-        assert_eq!(*cells[3].1, State(true));
-        assert_eq!(*cells[4].1, State(true));
-        assert_eq!(*cells[5].1, State(true));
+    #[test]
+    fn test_activity_matches_full_evaluation_across_wrapped_edges() {
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        // 10 is not a multiple of the region size, so the regions on the far edges are
+        // narrower than the rest.
+        for boundary in [
+            BoundaryMode::Toroidal,
+            BoundaryMode::KleinBottle,
+            BoundaryMode::ProjectivePlane,
+        ] {
+            let mut full = setup_world(10, 10, Rules::default());
+            spawn_pattern_cells(&mut full, 10, 10, &glider);
+            full.insert_resource(boundary);
+            let mut active = setup_world(10, 10, Rules::default());
+            spawn_pattern_cells(&mut active, 10, 10, &glider);
+            active.insert_resource(boundary);
+            active.insert_resource(ActivityConfig {
+                region_size: 4,
+                keep_active: 1,
+            });
 
-        assert_eq!(*cells[6].1, State(false));
-        assert_eq!(*cells[7].1, State(false));
-        assert_eq!(*cells[8].1, State(false));
+            let mut full_schedule = build_schedule();
+            let mut active_schedule = build_schedule();
+            for generation in 1..=60 {
+                full_schedule.run(&mut full);
+                active_schedule.run(&mut active);
+                assert_eq!(
+                    live_cells(&mut full),
+                    live_cells(&mut active),
+                    "{:?} generation {}",
+                    boundary,
+                    generation
+                );
+            }
+        }
+    }
 
-        assert_eq!(*cells[0].2, Neighbors(2));
-        assert_eq!(*cells[1].2, Neighbors(3));
-        assert_eq!(*cells[2].2, Neighbors(2));
+    #[test]
+    fn test_spawn_checkerboard() {
+        let mut world = setup_world(4, 3, Rules::default());
+        spawn_checkerboard_cells(&mut world, 4, 3);
+        let live = live_cells(&mut world);
+        assert_eq!(live.len(), 6);
+        for y in 0..3 {
+            for x in 0..4 {
+                assert_eq!(
+                    live.contains(&(x, y)),
+                    (x + y) % 2 == 0,
+                    "at ({}, {})",
+                    x,
+                    y
+                );
+            }
+        }
+    }
 
-        assert_eq!(*cells[3].2, Neighbors(1));
-        assert_eq!(*cells[4].2, Neighbors(2));
-        assert_eq!(*cells[5].2, Neighbors(1));
+    #[test]
+    fn test_spawn_stripes() {
+        let mut world = setup_world(5, 4, Rules::default());
+        spawn_stripes_cells(&mut world, 5, 4, StripeOrientation::Vertical);
+        let live = live_cells(&mut world);
+        let columns = live.iter().map(|&(x, _)| x).collect::<BTreeSet<_>>();
+        assert_eq!(columns, BTreeSet::from([0, 2, 4]));
+        assert_eq!(live.len(), 12);
 
-        assert_eq!(*cells[6].2, Neighbors(2));
-        assert_eq!(*cells[7].2, Neighbors(3));
-        assert_eq!(*cells[8].2, Neighbors(2));
+        let mut world = setup_world(5, 4, Rules::default());
+        spawn_stripes_cells(&mut world, 5, 4, StripeOrientation::Horizontal);
+        let live = live_cells(&mut world);
+        let rows = live.iter().map(|&(_, y)| y).collect::<BTreeSet<_>>();
+        assert_eq!(rows, BTreeSet::from([0, 2]));
+        assert_eq!(live.len(), 10);
+    }
 
-        println!("Second run");
-        schedule.run(&mut world);
-        world.run_system_once(print_all_entities_system);
+    #[test]
+    fn test_spawn_random_is_seeded() {
+        let soup = |seed| {
+            let mut world = setup_world(16, 16, Rules::default());
+            spawn_random_cells(&mut world, 16, 16, seed, 0.3);
+            live_cells(&mut world)
+        };
+        assert_eq!(soup(1), soup(1));
+        assert_ne!(soup(1), soup(2));
+        assert!((30..120).contains(&soup(1).len()));
+    }
 
-        let cells = query.iter(&world).collect::<Vec<_>>();
-        assert_eq!(cells.len(), 9);
-        assert_eq!(*cells[0].1, State(false));
-        assert_eq!(*cells[1].1, State(true));
-        assert_eq!(*cells[2].1, State(false));
+    #[test]
+    fn test_cells_by_index_keeps_grid_order() {
+        let mut world = setup_world(12, 12, Rules::default());
+        spawn_cells(&mut world, 12, 12);
+        world.insert_resource(Compaction::every(3));
+        let mut schedule = build_schedule();
+        for _ in 0..20 {
+            schedule.run(&mut world);
+        }
 
-        assert_eq!(*cells[3].1, State(false));
-        assert_eq!(*cells[4].1, State(true));
-        assert_eq!(*cells[5].1, State(false));
+        let cells = cells_by_index(&mut world);
+        let positions = cells
+            .iter()
+            .map(|(pos, _)| (pos.y, pos.x))
+            .collect::<Vec<_>>();
+        let mut expected = positions.clone();
+        expected.sort();
+        assert_eq!(positions, expected);
 
-        assert_eq!(*cells[6].1, State(false));
-        assert_eq!(*cells[7].1, State(true));
-        assert_eq!(*cells[8].1, State(false));
+        let live = cells
+            .iter()
+            .filter(|(_, alive)| *alive)
+            .map(|(pos, _)| (pos.x, pos.y))
+            .collect::<HashSet<_>>();
+        assert_eq!(live, live_cells(&mut world));
 
-        assert_eq!(*cells[0].2, Neighbors(2));
-        assert_eq!(*cells[1].2, Neighbors(1));
-        assert_eq!(*cells[2].2, Neighbors(2));
+        let mut query = world.query::<(&Index, &Position)>();
+        for (index, pos) in query.iter(&world) {
+            assert_eq!(index.0, pos.y as u32 * 12 + pos.x as u32);
+        }
+    }
 
-        assert_eq!(*cells[3].2, Neighbors(3));
-        assert_eq!(*cells[4].2, Neighbors(2));
-        assert_eq!(*cells[5].2, Neighbors(3));
+    #[test]
+    fn test_live_neighbors_boundaries() {
+        let grid = Grid {
+            width: 4,
+            height: 4,
+        };
+        // Left column and top row alive.
+        let live = (0..4)
+            .flat_map(|i| [(0, i), (i, 0)])
+            .collect::<HashSet<_>>();
 
-        assert_eq!(*cells[6].2, Neighbors(2));
-        assert_eq!(*cells[7].2, Neighbors(1));
-        assert_eq!(*cells[8].2, Neighbors(2));
+        let dead = BoundaryMode::Dead;
+        assert_eq!(live_neighbors(&live, 1, 1, &grid, dead), 5);
+        assert_eq!(live_neighbors(&live, 3, 2, &grid, dead), 0);
+        assert_eq!(live_neighbors(&live, 0, 0, &grid, dead), 2);
+        assert_eq!(live_neighbors(&live, 3, 3, &grid, dead), 0);
+
+        let torus = BoundaryMode::Toroidal;
+        assert_eq!(live_neighbors(&live, 1, 1, &grid, torus), 5);
+        assert_eq!(live_neighbors(&live, 3, 2, &grid, torus), 3);
+        assert_eq!(live_neighbors(&live, 0, 0, &grid, torus), 4);
+        assert_eq!(live_neighbors(&live, 3, 3, &grid, torus), 5);
+    }
+
+    #[test]
+    fn test_deterministic_digest_is_stable() {
+        let run = |deterministic: bool| {
+            let mut world = setup_world(32, 32, Rules::default());
+            spawn_random_cells(&mut world, 32, 32, 42, 0.35);
+            let mut schedule = build_schedule();
+            if deterministic {
+                schedule.set_executor_kind(bevy_ecs::schedule::ExecutorKind::SingleThreaded);
+                world.insert_resource(Deterministic);
+            }
+            for _ in 0..30 {
+                schedule.run(&mut world);
+            }
+            state_digest(&live_cells(&mut world))
+        };
+
+        let digest = run(true);
+        assert_eq!(run(true), digest);
+        assert_eq!(run(false), digest);
+        assert_ne!(
+            state_digest(&HashSet::from([(0, 1)])),
+            state_digest(&HashSet::from([(1, 0)]))
+        );
     }
 }
@@ -0,0 +1,457 @@
+use std::collections::{BTreeMap, HashSet};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use super::rle::parse_rle;
+use super::NEIGHBOR_OFFSETS;
+
+type Shape = Vec<(i32, i32)>;
+
+// Which cells count as touching when grouping live cells into objects.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    // Orthogonal neighbors only.
+    Four,
+    // Orthogonal and diagonal neighbors.
+    #[default]
+    Eight,
+}
+
+impl Connectivity {
+    fn offsets(self) -> impl Iterator<Item = (i32, i32)> {
+        NEIGHBOR_OFFSETS
+            .into_iter()
+            .filter(move |(dx, dy)| self == Connectivity::Eight || dx * dy == 0)
+    }
+}
+
+impl FromStr for Connectivity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4" => Ok(Connectivity::Four),
+            "8" => Ok(Connectivity::Eight),
+            _ => Err(format!("connectivity must be 4 or 8, got {:?}", s)),
+        }
+    }
+}
+
+// Splits the live cells into groups of cells that touch under `connectivity`.
+pub fn connected_components(
+    live: &HashSet<(i32, i32)>,
+    connectivity: Connectivity,
+) -> Vec<HashSet<(i32, i32)>> {
+    let mut unvisited = live.clone();
+    let mut components = Vec::new();
+    while let Some(&start) = unvisited.iter().next() {
+        unvisited.remove(&start);
+        let mut component = HashSet::from([start]);
+        let mut stack = vec![start];
+        while let Some((x, y)) = stack.pop() {
+            for (dx, dy) in connectivity.offsets() {
+                let neighbor = (x + dx, y + dy);
+                if unvisited.remove(&neighbor) {
+                    component.insert(neighbor);
+                    stack.push(neighbor);
+                }
+            }
+        }
+        components.push(component);
+    }
+    components
+}
+
+// Translates cells so the bounding box starts at the origin, sorted for comparison.
+fn normalize(cells: impl IntoIterator<Item = (i32, i32)>) -> Shape {
+    let cells = cells.into_iter().collect::<Vec<_>>();
+    let min_x = cells.iter().map(|c| c.0).min().unwrap_or(0);
+    let min_y = cells.iter().map(|c| c.1).min().unwrap_or(0);
+    let mut normalized = cells
+        .into_iter()
+        .map(|(x, y)| (x - min_x, y - min_y))
+        .collect::<Vec<_>>();
+    normalized.sort();
+    normalized
+}
+
+// All rotations and reflections of a shape, normalized.
+fn orientations(cells: &[(i32, i32)]) -> Vec<Shape> {
+    type Transform = fn((i32, i32)) -> (i32, i32);
+    let transforms: [Transform; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-y, x),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, -x),
+        |(x, y)| (-x, y),
+        |(x, y)| (y, x),
+        |(x, y)| (x, -y),
+        |(x, y)| (-y, -x),
+    ];
+    transforms
+        .iter()
+        .map(|transform| normalize(cells.iter().map(|&c| transform(c))))
+        .collect()
+}
+
+// Common still lifes, oscillators (every phase) and the glider (every phase).
+const TEMPLATES: [(&str, &str); 16] = [
+    ("block", "2o$2o!"),
+    ("beehive", "b2o$o2bo$b2o!"),
+    ("loaf", "b2o$o2bo$bobo$2bo!"),
+    ("boat", "2o$obo$bo!"),
+    ("ship", "2o$obo$b2o!"),
+    ("tub", "bo$obo$bo!"),
+    ("pond", "b2o$o2bo$o2bo$b2o!"),
+    ("blinker", "3o!"),
+    ("toad", "b3o$3o!"),
+    ("toad", "2bo$o2bo$o2bo$bo!"),
+    ("beacon", "2o$2o$2b2o$2b2o!"),
+    ("beacon", "2o$o$3bo$2b2o!"),
+    ("glider", "bo$2bo$3o!"),
+    ("glider", "obo$b2o$bo!"),
+    ("glider", "2bo$obo$b2o!"),
+    ("glider", "o$b2o$2o!"),
+];
+
+fn library() -> &'static Vec<(Shape, &'static str)> {
+    static LIBRARY: OnceLock<Vec<(Shape, &'static str)>> = OnceLock::new();
+    LIBRARY.get_or_init(|| {
+        let mut library = Vec::new();
+        for (name, rle) in TEMPLATES {
+            let cells = parse_rle(rle).unwrap().cells;
+            for shape in orientations(&cells) {
+                library.push((shape, name));
+            }
+        }
+        library
+    })
+}
+
+// Normalized shapes of still lifes and oscillators (every phase, every orientation)
+// that a run can settle into.
+#[derive(Debug, Clone)]
+pub struct AttractorLibrary {
+    templates: Vec<(Shape, &'static str)>,
+}
+
+impl AttractorLibrary {
+    // Every template from the object classifier except the spaceships.
+    pub fn standard() -> Self {
+        AttractorLibrary {
+            templates: library()
+                .iter()
+                .filter(|(_, name)| *name != "glider")
+                .cloned()
+                .collect(),
+        }
+    }
+
+    // Adds a shape in all its orientations.
+    pub fn add(&mut self, name: &'static str, cells: &[(i32, i32)]) {
+        for shape in orientations(cells) {
+            self.templates.push((shape, name));
+        }
+    }
+
+    // Names the attractor when the whole live set is one object matching a template.
+    pub fn matches(&self, live: &HashSet<(i32, i32)>) -> Option<&'static str> {
+        let shape = normalize(live.iter().copied());
+        self.templates
+            .iter()
+            .find(|(template, _)| *template == shape)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl Default for AttractorLibrary {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+// Names a single object if it matches a known pattern in any orientation.
+pub fn classify(component: &HashSet<(i32, i32)>) -> Option<&'static str> {
+    let shape = normalize(component.iter().copied());
+    library()
+        .iter()
+        .find(|(template, _)| *template == shape)
+        .map(|(_, name)| *name)
+}
+
+// Counts the objects in the live set by name; unrecognized ones count as "unknown".
+pub fn object_tally(
+    live: &HashSet<(i32, i32)>,
+    connectivity: Connectivity,
+) -> BTreeMap<&'static str, usize> {
+    let mut tally = BTreeMap::new();
+    for component in connected_components(live, connectivity) {
+        *tally
+            .entry(classify(&component).unwrap_or("unknown"))
+            .or_insert(0) += 1;
+    }
+    tally
+}
+
+// Formats a tally like "2 blocks, 1 blinker, 1 unknown".
+pub fn format_tally(tally: &BTreeMap<&'static str, usize>) -> String {
+    let known = tally.iter().filter(|(name, _)| **name != "unknown");
+    let unknown = tally.get_key_value("unknown");
+    let parts = known
+        .chain(unknown)
+        .map(|(name, count)| match (*name, count) {
+            ("unknown", _) | (_, 1) => format!("{} {}", count, name),
+            _ => format!("{} {}s", count, name),
+        })
+        .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "no objects".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+// Names an object independently of where it sits and which way it faces: the
+// classifier's name when it knows the shape (which covers every phase of its
+// oscillators), otherwise the smallest of its normalized orientations written out as
+// `x.y` pairs.
+fn ash_key(component: &HashSet<(i32, i32)>) -> String {
+    if let Some(name) = classify(component) {
+        return name.to_string();
+    }
+    let cells = component.iter().copied().collect::<Vec<_>>();
+    let shape = orientations(&cells).into_iter().min().unwrap_or_default();
+    shape
+        .iter()
+        .map(|(x, y)| format!("{}.{}", x, y))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// The ash of a settled grid, given every phase of its period (just one for still
+// lifes): one key per object, sorted, from whichever phase sorts first. The same
+// debris gives the same list wherever its objects lie and whichever phase the run
+// stopped on.
+pub fn ash(phases: &[HashSet<(i32, i32)>]) -> Vec<String> {
+    phases
+        .iter()
+        .map(|live| {
+            let mut keys = connected_components(live, Connectivity::Eight)
+                .iter()
+                .map(ash_key)
+                .collect::<Vec<_>>();
+            keys.sort();
+            keys
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+// 32-bit FNV-1a hash of the ash, short enough to catalogue soups by.
+pub fn ash_signature(phases: &[HashSet<(i32, i32)>]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in ash(phases).join(" ").bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    hash
+}
+
+// Follows glider-shaped objects from frame to frame. A glider moves at most one cell
+// per generation, so one whose corner sits next to a glider from the previous frame
+// is the same glider; any other is newly launched.
+#[derive(Debug, Default, Clone)]
+pub struct GliderTracker {
+    corners: Vec<(i32, i32)>,
+    pub launched: usize,
+}
+
+impl GliderTracker {
+    // Records the gliders in `live` and returns how many are in flight.
+    pub fn observe(&mut self, live: &HashSet<(i32, i32)>) -> usize {
+        let corners = connected_components(live, Connectivity::Eight)
+            .into_iter()
+            .filter(|component| classify(component) == Some("glider"))
+            .map(|component| {
+                let x = component.iter().map(|c| c.0).min().unwrap_or(0);
+                let y = component.iter().map(|c| c.1).min().unwrap_or(0);
+                (x, y)
+            })
+            .collect::<Vec<_>>();
+        self.launched += corners
+            .iter()
+            .filter(|(x, y)| {
+                !self
+                    .corners
+                    .iter()
+                    .any(|(px, py)| (x - px).abs() <= 1 && (y - py).abs() <= 1)
+            })
+            .count();
+        self.corners = corners;
+        self.corners.len()
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.corners.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{spawn_pattern_cells, Rules, Simulation};
+    use super::*;
+
+    fn shifted(rle: &str, dx: i32, dy: i32) -> Vec<(i32, i32)> {
+        parse_rle(rle)
+            .unwrap()
+            .cells
+            .into_iter()
+            .map(|(x, y)| (x + dx, y + dy))
+            .collect()
+    }
+
+    #[test]
+    fn test_classify_orientations() {
+        let vertical_blinker = HashSet::from([(5, 5), (5, 6), (5, 7)]);
+        assert_eq!(classify(&vertical_blinker), Some("blinker"));
+
+        let rotated_boat = shifted("bo$obo$b2o!", 10, 10).into_iter().collect();
+        assert_eq!(classify(&rotated_boat), Some("boat"));
+
+        let r_pentomino = shifted("b2o$2o$bo!", 0, 0).into_iter().collect();
+        assert_eq!(classify(&r_pentomino), None);
+    }
+
+    #[test]
+    fn test_object_tally() {
+        let mut live = HashSet::new();
+        live.extend(shifted("2o$2o!", 0, 0));
+        live.extend(shifted("2o$2o!", 10, 0));
+        live.extend(shifted("3o!", 0, 10));
+        live.extend(shifted("b2o$2o$bo!", 10, 10));
+
+        let tally = object_tally(&live, Connectivity::Eight);
+        assert_eq!(tally["block"], 2);
+        assert_eq!(tally["blinker"], 1);
+        assert_eq!(format_tally(&tally), "1 blinker, 2 blocks, 1 unknown");
+        assert_eq!(
+            format_tally(&object_tally(&HashSet::new(), Connectivity::Eight)),
+            "no objects"
+        );
+    }
+
+    #[test]
+    fn test_ash_of_unknown_oscillator_ignores_phase() {
+        // The pulsar is a period-3 oscillator the classifier does not know. Its pieces
+        // split and merge from phase to phase; with a block beside it the grid has 52,
+        // 60 and 76 cells.
+        let pulsar = shifted(
+            "2b3o3b3o2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2$2b3o3b3o$o4bobo4bo$\
+             o4bobo4bo$o4bobo4bo2$2b3o3b3o!",
+            4,
+            4,
+        )
+        .into_iter()
+        .chain(shifted("2o$2o!", 22, 22))
+        .collect::<HashSet<_>>();
+        let mut sim = Simulation::new(26, 26, Rules::default(), |world, width, height| {
+            spawn_pattern_cells(world, width, height, &pulsar)
+        });
+        let phases = (0..4)
+            .map(|_| {
+                let live = sim.live_cells();
+                sim.step();
+                live
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            phases.iter().map(HashSet::len).collect::<Vec<_>>(),
+            vec![52, 60, 76, 52]
+        );
+        assert_eq!(phases[3], phases[0]);
+
+        assert_ne!(ash(&phases[0..1]), ash(&phases[1..2]));
+        let first = ash(&phases[0..3]);
+        assert_eq!(ash(&phases[1..4]), first);
+        assert_eq!(
+            ash(&[phases[2].clone(), phases[0].clone(), phases[1].clone()]),
+            first
+        );
+        assert!(first.contains(&"block".to_string()));
+    }
+
+    #[test]
+    fn test_connectivity_diagonal_blocks() {
+        let mut live = HashSet::new();
+        live.extend(shifted("2o$2o!", 0, 0));
+        live.extend(shifted("2o$2o!", 2, 2));
+
+        assert_eq!(connected_components(&live, Connectivity::Eight).len(), 1);
+        assert_eq!(connected_components(&live, Connectivity::Four).len(), 2);
+        assert_eq!(
+            format_tally(&object_tally(&live, Connectivity::Four)),
+            "2 blocks"
+        );
+        assert_eq!("4".parse::<Connectivity>(), Ok(Connectivity::Four));
+        assert!("6".parse::<Connectivity>().is_err());
+    }
+
+    #[test]
+    fn test_attractor_library() {
+        let attractors = AttractorLibrary::standard();
+        assert_eq!(
+            attractors.matches(&shifted("3o!", 4, 4).into_iter().collect()),
+            Some("blinker")
+        );
+        assert_eq!(
+            attractors.matches(&shifted("bo$2bo$3o!", 0, 0).into_iter().collect()),
+            None
+        );
+
+        let mut two_blocks = HashSet::new();
+        two_blocks.extend(shifted("2o$2o!", 0, 0));
+        two_blocks.extend(shifted("2o$2o!", 5, 0));
+        assert_eq!(attractors.matches(&two_blocks), None);
+
+        let mut custom = AttractorLibrary::standard();
+        custom.add(
+            "bi-block",
+            &shifted("2o$2o!", 0, 0)
+                .into_iter()
+                .chain(shifted("2o$2o!", 3, 0))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            custom.matches(
+                &shifted("2o$2o!", 0, 0)
+                    .into_iter()
+                    .chain(shifted("2o$2o!", 0, 3))
+                    .collect()
+            ),
+            Some("bi-block")
+        );
+    }
+
+    #[test]
+    fn test_glider_tracker_gosper_gun() {
+        use super::super::{spawn_pattern_cells, Rules, Simulation};
+
+        let gun = parse_rle(
+            "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4bobo$\
+             10bo5bo7bo$11bo3bo$12b2o!",
+        )
+        .unwrap()
+        .with_margin(1);
+        let live = gun.cells.iter().copied().collect::<HashSet<_>>();
+        let mut sim = Simulation::new(60, 60, Rules::default(), move |world, w, h| {
+            spawn_pattern_cells(world, w, h, &live)
+        });
+
+        let mut tracker = GliderTracker::default();
+        for live in sim.iter().take(60) {
+            tracker.observe(&live);
+        }
+        assert!(tracker.in_flight() >= 1);
+        assert_eq!(tracker.launched, tracker.in_flight());
+    }
+}
@@ -0,0 +1,915 @@
+use bevy_ecs::prelude::*;
+use std::collections::{HashSet, VecDeque};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::time::Instant;
+
+use super::objects::{connected_components, AttractorLibrary, Connectivity, GliderTracker};
+use super::stability::{grows_unbounded, sparkline};
+use super::{
+    bbox_size, downsample, fit_block, frame_render, live_cells, neighbor_range, render,
+    render_cropped, render_diff, render_preview, render_with_origin, run_generations, sort_cells,
+    view_cells, Follow, GenerationStats, Grid, GridSnapshot, Origin, Preview, ProgressBar,
+    RunSummary,
+};
+
+// What changed in the step that produced a frame. Empty for the initial frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StepResult {
+    pub births: usize,
+    pub deaths: usize,
+    // Smallest and largest neighbor count among the frame's live cells, when known.
+    pub neighbor_range: Option<(u8, u8)>,
+}
+
+impl StepResult {
+    pub fn between(previous: &HashSet<(i32, i32)>, current: &HashSet<(i32, i32)>) -> Self {
+        StepResult {
+            births: current.difference(previous).count(),
+            deaths: previous.difference(current).count(),
+            neighbor_range: None,
+        }
+    }
+}
+
+// Coordinates that flipped in one step, each sorted by row then column.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub births: Vec<(i32, i32)>,
+    pub deaths: Vec<(i32, i32)>,
+}
+
+impl Diff {
+    pub fn between(previous: &HashSet<(i32, i32)>, current: &HashSet<(i32, i32)>) -> Self {
+        Diff {
+            births: sort_cells(current.difference(previous).copied()),
+            deaths: sort_cells(previous.difference(current).copied()),
+        }
+    }
+}
+
+// Live cells after the last `step_with_diff`, compared against after the next one.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct PreviousLive(pub HashSet<(i32, i32)>);
+
+// Runs one step and returns what changed, for embedders driving their own
+// incremental renderers. Edits made to the world between calls are counted as part
+// of the next step's diff.
+pub fn step_with_diff(world: &mut World, schedule: &mut Schedule) -> (StepResult, Diff) {
+    let previous = match world.remove_resource::<PreviousLive>() {
+        Some(previous) => previous.0,
+        None => live_cells(world),
+    };
+    schedule.run(world);
+    let current = live_cells(world);
+    let step = StepResult {
+        neighbor_range: neighbor_range(world),
+        ..StepResult::between(&previous, &current)
+    };
+    let diff = Diff::between(&previous, &current);
+    world.insert_resource(PreviousLive(current));
+    (step, diff)
+}
+
+// Receives every frame of a run. Several sinks can be active at once.
+pub trait OutputSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, step: &StepResult);
+
+    // Sinks that return true get `on_lookahead` with the frame the run's next step
+    // would produce, before `finish`.
+    fn wants_lookahead(&self) -> bool {
+        false
+    }
+
+    fn on_lookahead(&mut self, _next: &GridSnapshot) {}
+
+    fn finish(&mut self) {}
+}
+
+// Prints each generation as a full ASCII frame, or just the live cells' bounding box
+// with `crop`.
+pub struct AsciiSink {
+    pub view_offset: (i32, i32),
+    pub crop: bool,
+    pub origin: Origin,
+    // Columns and rows a frame may take up before it is downsampled to fit.
+    pub max_size: Option<(u32, u32)>,
+    // Frame the grid with `frame_render`. Not applied with `crop`.
+    pub border: bool,
+    // Recentre the view on the live cells every generation, replacing `view_offset`.
+    pub follow: Option<Follow>,
+}
+
+impl OutputSink for AsciiSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        let size = Grid {
+            width: grid.width,
+            height: grid.height,
+        };
+        let mut live = match &mut self.follow {
+            Some(follow) => follow.update(&grid.live, &size),
+            None => view_cells(&grid.live, &size, self.view_offset),
+        };
+        let frame = if self.crop && !live.is_empty() {
+            bbox_size(&live)
+        } else {
+            (size.width, size.height)
+        };
+        let border = if self.border { 2 } else { 0 };
+        let block = self.max_size.map_or((1, 1), |(columns, rows)| {
+            fit_block(
+                frame.0,
+                frame.1,
+                (columns.saturating_sub(border), rows.saturating_sub(border)),
+            )
+        });
+        if block != (1, 1) {
+            live = downsample(&live, block);
+        }
+        println!("Generation {:?}:", generation);
+        if self.crop {
+            println!("{}", render_cropped(&live, self.origin));
+        } else {
+            let size = Grid {
+                width: size.width.div_ceil(block.0),
+                height: size.height.div_ceil(block.1),
+            };
+            let rendered = render_with_origin(&live, &size, self.origin);
+            if self.border {
+                println!("{}", frame_render(&rendered));
+            } else {
+                println!("{}", rendered);
+            }
+        }
+    }
+}
+
+// Prints each generation with the cells about to be born or die marked. A frame is
+// held back until the next one arrives and marked with what actually flipped between
+// them, so the marks follow Hensel rules, rule regions, script edits and B0
+// backgrounds alike. The last frame is marked against the lookahead frame.
+pub struct PreviewSink {
+    out: Box<dyn Write>,
+    view_offset: (i32, i32),
+    origin: Origin,
+    pending: Option<(u32, GridSnapshot)>,
+}
+
+impl PreviewSink {
+    pub fn new(out: Box<dyn Write>, view_offset: (i32, i32), origin: Origin) -> Self {
+        PreviewSink {
+            out,
+            view_offset,
+            origin,
+            pending: None,
+        }
+    }
+
+    fn print(&mut self, generation: u32, grid: &GridSnapshot, preview: &Preview) {
+        let size = Grid {
+            width: grid.width,
+            height: grid.height,
+        };
+        let view = |cells: &HashSet<(i32, i32)>| view_cells(cells, &size, self.view_offset);
+        let preview = Preview {
+            births: view(&preview.births),
+            deaths: view(&preview.deaths),
+        };
+        let frame = render_preview(&view(&grid.live), &size, &preview, self.origin);
+        if let Err(err) = write!(self.out, "Generation {:?}:\n{}\n", generation, frame) {
+            eprintln!("Failed to write preview: {}", err);
+        }
+    }
+}
+
+impl OutputSink for PreviewSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        if let Some((previous, frame)) = self.pending.take() {
+            let preview = Preview::between(&frame.live, &grid.live);
+            self.print(previous, &frame, &preview);
+        }
+        self.pending = Some((generation, grid.clone()));
+    }
+
+    fn wants_lookahead(&self) -> bool {
+        true
+    }
+
+    fn on_lookahead(&mut self, next: &GridSnapshot) {
+        if let Some((generation, frame)) = self.pending.take() {
+            self.print(
+                generation,
+                &frame,
+                &Preview::between(&frame.live, &next.live),
+            );
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some((generation, frame)) = self.pending.take() {
+            self.print(generation, &frame, &Preview::default());
+        }
+        let _ = self.out.flush();
+    }
+}
+
+// Tracks gliders through the run and reports how many are in flight at the end.
+#[derive(Default)]
+pub struct GliderSink {
+    tracker: GliderTracker,
+}
+
+impl OutputSink for GliderSink {
+    fn on_generation(&mut self, _generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        self.tracker.observe(&grid.live);
+    }
+
+    fn finish(&mut self) {
+        println!(
+            "{} gliders in flight ({} launched)",
+            self.tracker.in_flight(),
+            self.tracker.launched
+        );
+    }
+}
+
+// Most characters `PopulationSink` prints; longer runs are averaged down to fit.
+pub const SPARKLINE_WIDTH: usize = 80;
+
+// Generations per window, and how many windows in a row must set a new population
+// high, before `PopulationSink` warns about unbounded growth. 30 is the period of the
+// Gosper gun.
+pub const GROWTH_WINDOW: usize = 30;
+pub const GROWTH_WINDOWS: usize = 4;
+
+// Prints a sparkline of the population over the run once it finishes, and warns if
+// the population was still climbing at the end, e.g. because a gun's eater is missing
+// or misplaced.
+#[derive(Default)]
+pub struct PopulationSink {
+    pub populations: Vec<usize>,
+}
+
+impl PopulationSink {
+    pub fn unbounded_growth(&self) -> bool {
+        grows_unbounded(&self.populations, GROWTH_WINDOW, GROWTH_WINDOWS)
+    }
+}
+
+impl OutputSink for PopulationSink {
+    fn on_generation(&mut self, _generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        self.populations.push(grid.live.len());
+    }
+
+    fn finish(&mut self) {
+        let (Some(min), Some(max)) = (self.populations.iter().min(), self.populations.iter().max())
+        else {
+            return;
+        };
+        let chunk = self.populations.len().div_ceil(SPARKLINE_WIDTH);
+        let averaged = self
+            .populations
+            .chunks(chunk)
+            .map(|chunk| chunk.iter().sum::<usize>() / chunk.len())
+            .collect::<Vec<_>>();
+        println!(
+            "Population {} (min {}, max {})",
+            sparkline(&averaged),
+            min,
+            max
+        );
+        if self.unbounded_growth() {
+            println!(
+                "Warning: the population set a new high in each of the last {} generations; \
+                 is an eater missing or misplaced?",
+                GROWTH_WINDOW * GROWTH_WINDOWS
+            );
+        }
+    }
+}
+
+// Passes on only the generations inside `range`, so a long run can export a
+// short window without writing every frame.
+pub struct RangeSink {
+    inner: Box<dyn OutputSink>,
+    range: RangeInclusive<u32>,
+    // Set once the frame after the range has gone to the inner sink as its lookahead.
+    looked_ahead: bool,
+}
+
+impl RangeSink {
+    pub fn new(inner: Box<dyn OutputSink>, range: RangeInclusive<u32>) -> Self {
+        RangeSink {
+            inner,
+            range,
+            looked_ahead: false,
+        }
+    }
+}
+
+impl OutputSink for RangeSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, step: &StepResult) {
+        if self.range.contains(&generation) {
+            self.inner.on_generation(generation, grid, step);
+        } else if generation.checked_sub(1) == Some(*self.range.end()) {
+            self.inner.on_lookahead(grid);
+            self.looked_ahead = true;
+        }
+    }
+
+    fn wants_lookahead(&self) -> bool {
+        self.inner.wants_lookahead()
+    }
+
+    fn on_lookahead(&mut self, next: &GridSnapshot) {
+        if !self.looked_ahead {
+            self.inner.on_lookahead(next);
+        }
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+}
+
+// Reports the first generation whose live cells form a known still life or
+// oscillator.
+#[derive(Default)]
+pub struct AttractorSink {
+    pub library: AttractorLibrary,
+    pub reached: Option<(u32, &'static str)>,
+}
+
+impl OutputSink for AttractorSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        if self.reached.is_some() {
+            return;
+        }
+        if let Some(name) = self.library.matches(&grid.live) {
+            println!(
+                "Generation {}: reached a known attractor: {}",
+                generation, name
+            );
+            self.reached = Some((generation, name));
+        }
+    }
+}
+
+// Draws the first frame in full, then redraws only the changed cells in place.
+pub struct IncrementalSink {
+    pub view_offset: (i32, i32),
+    previous: Option<HashSet<(i32, i32)>>,
+}
+
+impl IncrementalSink {
+    pub fn new(view_offset: (i32, i32)) -> Self {
+        IncrementalSink {
+            view_offset,
+            previous: None,
+        }
+    }
+}
+
+impl OutputSink for IncrementalSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        let size = Grid {
+            width: grid.width,
+            height: grid.height,
+        };
+        let live = view_cells(&grid.live, &size, self.view_offset);
+        match &self.previous {
+            None => print!("\x1b[2J\x1b[H{}", render(&live, &size)),
+            Some(previous) => print!("{}", render_diff(previous, &live)),
+        }
+        print!(
+            "\x1b[{};1HGeneration {:?}\x1b[K",
+            grid.height + 1,
+            generation
+        );
+        let _ = std::io::stdout().flush();
+        self.previous = Some(live);
+    }
+
+    fn finish(&mut self) {
+        if self.previous.is_some() {
+            println!();
+        }
+    }
+}
+
+// Writes one `GenerationStats` JSON object per line, counting objects with
+// `connectivity`.
+pub struct JsonlSink {
+    out: Box<dyn Write>,
+    connectivity: Connectivity,
+    bbox: bool,
+    gliders: Option<GliderTracker>,
+}
+
+impl JsonlSink {
+    pub fn new(out: Box<dyn Write>, connectivity: Connectivity) -> Self {
+        JsonlSink {
+            out,
+            connectivity,
+            bbox: false,
+            gliders: None,
+        }
+    }
+
+    // Also write the bounding box width and height.
+    pub fn with_bbox(mut self, bbox: bool) -> Self {
+        self.bbox = bbox;
+        self
+    }
+
+    // Also write how many gliders are in flight.
+    pub fn with_gliders(mut self, gliders: bool) -> Self {
+        self.gliders = gliders.then(GliderTracker::default);
+        self
+    }
+}
+
+impl OutputSink for JsonlSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, step: &StepResult) {
+        let stats = GenerationStats {
+            generation,
+            population: grid.live.len(),
+            births: step.births,
+            deaths: step.deaths,
+            components: Some(connected_components(&grid.live, self.connectivity).len()),
+            min_neighbors: step.neighbor_range.map(|range| range.0),
+            max_neighbors: step.neighbor_range.map(|range| range.1),
+            bbox_width: self.bbox.then(|| bbox_size(&grid.live).0),
+            bbox_height: self.bbox.then(|| bbox_size(&grid.live).1),
+            gliders: self
+                .gliders
+                .as_mut()
+                .map(|tracker| tracker.observe(&grid.live)),
+        };
+        if let Err(err) = stats.write_jsonl(&mut self.out) {
+            eprintln!("Failed to write JSON lines: {}", err);
+        }
+    }
+
+    fn finish(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+// Writes `gen W H`, the size of the live cells' bounding box, for every generation.
+pub struct BboxSink {
+    out: Box<dyn Write>,
+}
+
+impl BboxSink {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        BboxSink { out }
+    }
+}
+
+impl OutputSink for BboxSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        let (width, height) = bbox_size(&grid.live);
+        if let Err(err) = writeln!(self.out, "{} {} {}", generation, width, height) {
+            eprintln!("Failed to write bounding box: {}", err);
+        }
+    }
+
+    fn finish(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+// How many recent frame times `GpsMeter` averages over.
+pub const GPS_SAMPLES: usize = 32;
+
+// Rolling generations-per-second rate over the last `GPS_SAMPLES` frames.
+#[derive(Debug, Clone, Default)]
+pub struct GpsMeter {
+    samples: VecDeque<Instant>,
+}
+
+impl GpsMeter {
+    pub fn record(&mut self, at: Instant) {
+        if self.samples.len() == GPS_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(at);
+    }
+
+    // None until two frames have been recorded at different times.
+    pub fn rate(&self) -> Option<f64> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let elapsed = last.duration_since(*first).as_secs_f64();
+        (elapsed > 0.0).then(|| (self.samples.len() - 1) as f64 / elapsed)
+    }
+}
+
+// `run_generations` feeding every frame to `sinks`, with their output kept clear of
+// the progress bar. Sinks are finished once the run ends.
+pub fn run_with_sinks(
+    world: &mut World,
+    schedule: &mut Schedule,
+    generations: u32,
+    show_initial: bool,
+    sinks: &mut [Box<dyn OutputSink>],
+    progress: &ProgressBar,
+) -> RunSummary {
+    let mut previous = live_cells(world);
+    let mut gps = GpsMeter::default();
+    let summary = run_generations(
+        world,
+        schedule,
+        generations,
+        show_initial,
+        |generation, world| {
+            if sinks.is_empty() && progress.is_hidden() {
+                return;
+            }
+            let snapshot = GridSnapshot::from(&mut *world);
+            let step = StepResult {
+                neighbor_range: neighbor_range(world),
+                ..StepResult::between(&previous, &snapshot.live)
+            };
+            progress.suspend(|| {
+                for sink in sinks.iter_mut() {
+                    sink.on_generation(generation, &snapshot, &step);
+                }
+            });
+            progress.set_position(generation as u64);
+            if !progress.is_hidden() {
+                gps.record(Instant::now());
+                progress.set_message(match gps.rate() {
+                    Some(rate) => format!("population {}, {:.1} gen/s", snapshot.live.len(), rate),
+                    None => format!("population {}", snapshot.live.len()),
+                });
+            }
+            previous = snapshot.live;
+        },
+    );
+    progress.finish_and_clear();
+    if sinks.iter().any(|sink| sink.wants_lookahead()) {
+        let flips = Preview::next(world);
+        let mut next = GridSnapshot::from(&mut *world);
+        next.live.retain(|cell| !flips.deaths.contains(cell));
+        next.live.extend(flips.births);
+        for sink in sinks.iter_mut() {
+            sink.on_lookahead(&next);
+        }
+    }
+    for sink in sinks.iter_mut() {
+        sink.finish();
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        build_schedule, setup_world, spawn_blinker_cells, spawn_pattern_cells, RuleRegions, Rules,
+    };
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    struct CountingSink {
+        calls: Rc<RefCell<Vec<(u32, usize, StepResult)>>>,
+        finished: Rc<RefCell<bool>>,
+    }
+
+    impl OutputSink for CountingSink {
+        fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, step: &StepResult) {
+            self.calls
+                .borrow_mut()
+                .push((generation, grid.live.len(), *step));
+        }
+
+        fn finish(&mut self) {
+            *self.finished.borrow_mut() = true;
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_jsonl_blinker() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let buffer = SharedBuffer::default();
+        let mut sinks: Vec<Box<dyn OutputSink>> = vec![Box::new(JsonlSink::new(
+            Box::new(buffer.clone()),
+            Connectivity::Eight,
+        ))];
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            4,
+            false,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4);
+        assert!(
+            lines[0].starts_with(r#"{"gen":1,"population":3,"births":2,"deaths":2,"#),
+            "{}",
+            lines[0]
+        );
+        for (i, line) in lines.iter().enumerate() {
+            let value: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(value["gen"], i as u64 + 1);
+            assert_eq!(value["population"], 3);
+            assert_eq!(
+                (value["births"].clone(), value["deaths"].clone()),
+                (2.into(), 2.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_preview_marks_what_the_step_flipped() {
+        // Under Life without Death the blinker's ends survive, which the starting
+        // Conway rule would have marked as deaths. The last frame is marked with the
+        // step the run stops short of.
+        let mut world = setup_world(5, 5, Rules::default());
+        world.insert_resource(RuleRegions(vec!["0,0,5,5:lwd".parse().unwrap()]));
+        spawn_pattern_cells(&mut world, 5, 5, &HashSet::from([(1, 2), (2, 2), (3, 2)]));
+        let buffer = SharedBuffer::default();
+        let mut sinks: Vec<Box<dyn OutputSink>> = vec![Box::new(PreviewSink::new(
+            Box::new(buffer.clone()),
+            (0, 1),
+            Origin::TopLeft,
+        ))];
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            1,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(
+            text,
+            "Generation 0:\n..+..\n.###.\n..+..\n.....\n.....\n\n\
+             Generation 1:\n.+#+.\n.###.\n.+#+.\n.....\n.....\n\n"
+        );
+    }
+
+    #[test]
+    fn test_jsonl_counts_gliders_per_generation() {
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut world = setup_world(12, 12, Rules::default());
+        spawn_pattern_cells(&mut world, 12, 12, &glider);
+        let buffer = SharedBuffer::default();
+        let mut sinks: Vec<Box<dyn OutputSink>> = vec![Box::new(
+            JsonlSink::new(Box::new(buffer.clone()), Connectivity::Eight).with_gliders(true),
+        )];
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            4,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let gliders = text
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["gliders"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(gliders, vec![1; 5]);
+    }
+
+    #[test]
+    fn test_jsonl_component_count_merges() {
+        // Two vertical blinkers three columns apart turn horizontal and touch.
+        let live = HashSet::from([(3, 3), (3, 4), (3, 5), (6, 3), (6, 4), (6, 5)]);
+        let mut world = setup_world(10, 10, Rules::default());
+        spawn_pattern_cells(&mut world, 10, 10, &live);
+        let buffer = SharedBuffer::default();
+        let mut sinks: Vec<Box<dyn OutputSink>> = vec![Box::new(JsonlSink::new(
+            Box::new(buffer.clone()),
+            Connectivity::Eight,
+        ))];
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            1,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let components = text
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["components"].clone()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(components, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_bbox_log_glider_stays_three_by_three() {
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut world = setup_world(20, 20, Rules::default());
+        spawn_pattern_cells(&mut world, 20, 20, &glider);
+        let buffer = SharedBuffer::default();
+        let mut sinks: Vec<Box<dyn OutputSink>> =
+            vec![Box::new(BboxSink::new(Box::new(buffer.clone())))];
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            8,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        let expected = (0..=8)
+            .map(|generation| format!("{} 3 3\n", generation))
+            .collect::<String>();
+        assert_eq!(text, expected);
+        assert_eq!(bbox_size(&HashSet::new()), (0, 0));
+    }
+
+    #[test]
+    fn test_attractor_sink_reports_blinker() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut sink = AttractorSink::default();
+        let mut previous = live_cells(&mut world);
+        run_generations(
+            &mut world,
+            &mut build_schedule(),
+            3,
+            true,
+            |generation, world| {
+                let snapshot = GridSnapshot::from(world);
+                sink.on_generation(
+                    generation,
+                    &snapshot,
+                    &StepResult::between(&previous, &snapshot.live),
+                );
+                previous = snapshot.live;
+            },
+        );
+        assert_eq!(sink.reached, Some((0, "blinker")));
+    }
+
+    #[test]
+    fn test_step_with_diff_blinker() {
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = build_schedule();
+
+        let (step, diff) = step_with_diff(&mut world, &mut schedule);
+        assert_eq!(
+            diff,
+            Diff {
+                births: vec![(0, 1), (2, 1)],
+                deaths: vec![(1, 0), (1, 2)],
+            }
+        );
+        assert_eq!((step.births, step.deaths), (2, 2));
+
+        let (_, diff) = step_with_diff(&mut world, &mut schedule);
+        assert_eq!(diff.births, vec![(1, 0), (1, 2)]);
+        assert_eq!(diff.deaths, vec![(0, 1), (2, 1)]);
+    }
+
+    #[test]
+    fn test_gps_meter_rolling_rate() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut gps = GpsMeter::default();
+        assert_eq!(gps.rate(), None);
+        gps.record(at(0));
+        assert_eq!(gps.rate(), None);
+        for i in 1..=4 {
+            gps.record(at(i * 100));
+        }
+        assert!((gps.rate().unwrap() - 10.0).abs() < 1e-9);
+
+        // Once full, only the latest samples count: 20 ms frames give 50 gen/s.
+        for i in 1..=GPS_SAMPLES as u64 {
+            gps.record(at(400 + i * 20));
+        }
+        assert!((gps.rate().unwrap() - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_range_sink_exports_window() {
+        let calls = Rc::default();
+        let finished = Rc::default();
+        let counting = CountingSink {
+            calls: Rc::clone(&calls),
+            finished: Rc::clone(&finished),
+        };
+        let mut sinks: Vec<Box<dyn OutputSink>> =
+            vec![Box::new(RangeSink::new(Box::new(counting), 3..=5))];
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            10,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let generations = calls
+            .borrow()
+            .iter()
+            .map(|call: &(u32, usize, StepResult)| call.0)
+            .collect::<Vec<_>>();
+        assert_eq!(generations, vec![3, 4, 5]);
+        assert!(*finished.borrow());
+    }
+
+    #[test]
+    fn test_range_sink_marks_last_frame_with_the_next() {
+        let buffer = SharedBuffer::default();
+        let preview = PreviewSink::new(Box::new(buffer.clone()), (0, 0), Origin::TopLeft);
+        let mut sinks: Vec<Box<dyn OutputSink>> =
+            vec![Box::new(RangeSink::new(Box::new(preview), 1..=1))];
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            4,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(text, "Generation 1:\n.+.\nx#x\n.+.\n\n");
+    }
+
+    #[test]
+    fn test_every_sink_sees_every_generation() {
+        let calls = [Rc::default(), Rc::default()];
+        let finished = [Rc::default(), Rc::default()];
+        let mut sinks = (0..2)
+            .map(|i| {
+                Box::new(CountingSink {
+                    calls: Rc::clone(&calls[i]),
+                    finished: Rc::clone(&finished[i]),
+                }) as Box<dyn OutputSink>
+            })
+            .collect::<Vec<_>>();
+
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = build_schedule();
+        run_with_sinks(
+            &mut world,
+            &mut schedule,
+            5,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        for (calls, finished) in calls.iter().zip(&finished) {
+            let calls: &Vec<(u32, usize, StepResult)> = &calls.borrow();
+            assert_eq!(calls.len(), 6);
+            assert_eq!(calls[0], (0, 3, StepResult::default()));
+            assert_eq!(
+                calls[5],
+                (
+                    5,
+                    3,
+                    StepResult {
+                        births: 2,
+                        deaths: 2,
+                        neighbor_range: Some((1, 2)),
+                    }
+                )
+            );
+            assert!(*finished.borrow());
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use super::{GameOfLifeError, Grid};
+
+// Pixels per line of `to_pbm` output, which at two characters each keeps lines within
+// the 70 characters the format allows.
+const PIXELS_PER_LINE: usize = 35;
+
+// Plain (ASCII, `P1`) portable bitmap of the grid: 1 for live cells, 0 for dead. Each
+// grid row starts a new line and rows wider than `PIXELS_PER_LINE` continue on the
+// lines after it.
+pub fn to_pbm(live: &HashSet<(i32, i32)>, grid: &Grid) -> String {
+    let mut output = format!("P1\n{} {}\n", grid.width, grid.height);
+    for y in 0..grid.height as i32 {
+        let row = (0..grid.width as i32)
+            .map(|x| if live.contains(&(x, y)) { "1" } else { "0" })
+            .collect::<Vec<_>>();
+        for line in row.chunks(PIXELS_PER_LINE) {
+            output.push_str(&line.join(" "));
+            output.push('\n');
+        }
+    }
+    output
+}
+
+pub fn save_pbm(
+    live: &HashSet<(i32, i32)>,
+    grid: &Grid,
+    path: &Path,
+) -> Result<(), GameOfLifeError> {
+    fs::write(path, to_pbm(live, grid)).map_err(|err| GameOfLifeError::io(path, err))
+}
+
+// Reads a `P1` bitmap back into its size and set pixels. `#` comments are skipped.
+pub fn parse_pbm(text: &str) -> Result<(Grid, HashSet<(i32, i32)>), GameOfLifeError> {
+    parse_pixels(text).map_err(GameOfLifeError::ParseError)
+}
+
+fn parse_pixels(text: &str) -> Result<(Grid, HashSet<(i32, i32)>), String> {
+    let mut tokens = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(str::split_whitespace);
+    if tokens.next() != Some("P1") {
+        return Err("not a plain PBM file, expected P1".to_string());
+    }
+
+    let mut dimension = |name: &str| {
+        tokens
+            .next()
+            .and_then(|token| token.parse::<u32>().ok())
+            .ok_or_else(|| format!("invalid PBM {}", name))
+    };
+    let width = dimension("width")?;
+    let height = dimension("height")?;
+
+    // Pixels may also be written without separating whitespace.
+    let pixels = tokens.flat_map(str::chars).collect::<Vec<_>>();
+    let expected = width as u64 * height as u64;
+    if pixels.len() as u64 != expected {
+        return Err(format!(
+            "expected {} PBM pixels, found {}",
+            expected,
+            pixels.len()
+        ));
+    }
+
+    let mut live = HashSet::new();
+    let columns = width as usize;
+    for (i, pixel) in pixels.into_iter().enumerate() {
+        match pixel {
+            '0' => (),
+            '1' => {
+                live.insert(((i % columns) as i32, (i / columns) as i32));
+            }
+            _ => return Err(format!("invalid PBM pixel {:?}", pixel)),
+        }
+    }
+    Ok((Grid { width, height }, live))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_round_trip() {
+        let grid = Grid {
+            width: 4,
+            height: 3,
+        };
+        let block = HashSet::from([(1, 1), (2, 1), (1, 2), (2, 2)]);
+        let path =
+            std::env::temp_dir().join(format!("ecs_without_bevy_block_{}.pbm", std::process::id()));
+        save_pbm(&block, &grid, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(text, "P1\n4 3\n0 0 0 0\n0 1 1 0\n0 1 1 0\n");
+        let (read_grid, live) = parse_pbm(&text).unwrap();
+        assert_eq!((read_grid.width, read_grid.height), (4, 3));
+        assert_eq!(live, block);
+        assert!(parse_pbm("P1\n2 2\n0 1 0").is_err());
+        let err = parse_pbm("P1\n4294967295 4294967295\n0").unwrap_err();
+        assert!(err.to_string().contains("18446744065119617025"), "{}", err);
+    }
+
+    #[test]
+    fn test_long_rows_wrap_within_70_characters() {
+        let grid = Grid {
+            width: 80,
+            height: 2,
+        };
+        let live = HashSet::from([(0, 0), (79, 0), (40, 1)]);
+        let text = to_pbm(&live, &grid);
+        assert!(text.lines().all(|line| line.len() <= 70), "{}", text);
+        // Each 80-pixel row takes three lines: 35, 35 and 10 pixels.
+        assert_eq!(text.lines().count(), 2 + 2 * 3);
+        let (read_grid, read) = parse_pbm(&text).unwrap();
+        assert_eq!((read_grid.width, read_grid.height), (80, 2));
+        assert_eq!(read, live);
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+
+use super::output::{OutputSink, StepResult};
+use super::{GameOfLifeError, Grid, GridSnapshot, Pattern};
+
+// Pixel brightness a cell must exceed to start alive when no threshold is given.
+pub const DEFAULT_THRESHOLD: u8 = 127;
+
+// Reads an image as a pattern the size of the image: every pixel whose grayscale
+// brightness is above `threshold` becomes a live cell.
+pub fn parse_png(bytes: &[u8], threshold: u8) -> Result<Pattern, GameOfLifeError> {
+    let image = image::load_from_memory_with_format(bytes, image::ImageFormat::Png)
+        .map_err(|err| GameOfLifeError::ParseError(format!("invalid PNG: {}", err)))?
+        .into_luma8();
+    let cells = image
+        .enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[0] > threshold)
+        .map(|(x, y, _)| (x as i32, y as i32))
+        .collect();
+    Ok(Pattern {
+        width: image.width(),
+        height: image.height(),
+        cells,
+        rule: None,
+    })
+}
+
+pub fn load_png(path: &Path, threshold: u8) -> Result<Pattern, GameOfLifeError> {
+    let bytes = std::fs::read(path).map_err(|err| GameOfLifeError::io(path, err))?;
+    parse_png(&bytes, threshold)
+}
+
+// Draws per-cell counts as a grayscale image the size of the grid, the highest
+// count white and zero black.
+pub fn heatmap(counts: &HashMap<(i32, i32), u32>, grid: &Grid) -> image::GrayImage {
+    let max = counts.values().copied().max().unwrap_or(0).max(1);
+    image::GrayImage::from_fn(grid.width, grid.height, |x, y| {
+        let count = counts.get(&(x as i32, y as i32)).copied().unwrap_or(0);
+        image::Luma([(count as u64 * 255 / max as u64) as u8])
+    })
+}
+
+pub fn save_heatmap(
+    counts: &HashMap<(i32, i32), u32>,
+    grid: &Grid,
+    path: &Path,
+) -> Result<(), GameOfLifeError> {
+    let mut bytes = Vec::new();
+    heatmap(counts, grid)
+        .write_to(&mut Cursor::new(&mut bytes), image::ImageOutputFormat::Png)
+        .map_err(|err| GameOfLifeError::EncodeError(format!("cannot encode PNG: {}", err)))?;
+    std::fs::write(path, bytes).map_err(|err| GameOfLifeError::io(path, err))
+}
+
+fn encode_error(err: ::png::EncodingError) -> GameOfLifeError {
+    GameOfLifeError::EncodeError(format!("cannot encode APNG: {}", err))
+}
+
+// Starts an animated PNG that loops forever, one pixel per cell, showing each of its
+// `frames` for `delay_ms`.
+fn apng_writer<W: Write>(
+    out: W,
+    width: u32,
+    height: u32,
+    frames: u32,
+    delay_ms: u64,
+) -> Result<::png::Writer<W>, GameOfLifeError> {
+    let mut encoder = ::png::Encoder::new(out, width, height);
+    encoder.set_color(::png::ColorType::Grayscale);
+    encoder.set_depth(::png::BitDepth::Eight);
+    encoder.set_animated(frames, 0).map_err(encode_error)?;
+    encoder
+        .set_frame_delay(delay_ms.min(u16::MAX as u64) as u16, 1000)
+        .map_err(encode_error)?;
+    encoder.write_header().map_err(encode_error)
+}
+
+// Live cells white, the rest black. The frame must be the size of the first.
+fn write_frame<W: Write>(
+    writer: &mut ::png::Writer<W>,
+    frame: &GridSnapshot,
+) -> Result<(), GameOfLifeError> {
+    let mut pixels = vec![0u8; frame.width as usize * frame.height as usize];
+    for &(x, y) in &frame.live {
+        pixels[y as usize * frame.width as usize + x as usize] = 255;
+    }
+    writer.write_image_data(&pixels).map_err(encode_error)
+}
+
+// Encodes the frames as an animated PNG that loops forever, one pixel per cell, live
+// cells white. Every frame is shown for `delay_ms` and must be the size of the first.
+pub fn encode_apng(frames: &[GridSnapshot], delay_ms: u64) -> Result<Vec<u8>, GameOfLifeError> {
+    let Some(first) = frames.first() else {
+        return Err(GameOfLifeError::EncodeError(
+            "cannot encode APNG: no frames".to_string(),
+        ));
+    };
+    let mut bytes = Vec::new();
+    let mut writer = apng_writer(
+        &mut bytes,
+        first.width,
+        first.height,
+        frames.len() as u32,
+        delay_ms,
+    )?;
+    for frame in frames {
+        write_frame(&mut writer, frame)?;
+    }
+    writer.finish().map_err(encode_error)?;
+    Ok(bytes)
+}
+
+pub fn export_apng(
+    frames: &[GridSnapshot],
+    path: &Path,
+    delay_ms: u64,
+) -> Result<(), GameOfLifeError> {
+    let bytes = encode_apng(frames, delay_ms)?;
+    std::fs::write(path, bytes).map_err(|err| GameOfLifeError::io(path, err))
+}
+
+// Collects every generation and writes them as an APNG when the run ends. The frame
+// count goes in the header before any frame, and a run can stop early, so nothing is
+// written until the count is known.
+pub struct ApngSink {
+    path: PathBuf,
+    delay_ms: u64,
+    frames: Vec<GridSnapshot>,
+}
+
+impl ApngSink {
+    pub fn new(path: PathBuf, delay_ms: u64) -> Self {
+        ApngSink {
+            path,
+            delay_ms,
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for ApngSink {
+    fn on_generation(&mut self, _generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        self.frames.push(grid.clone());
+    }
+
+    fn finish(&mut self) {
+        match export_apng(&self.frames, &self.path, self.delay_ms) {
+            Ok(()) => crate::status!(
+                "Saved {} frames to {}",
+                self.frames.len(),
+                self.path.display()
+            ),
+            Err(err) => eprintln!("Failed to save APNG: {}", err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::output::run_with_sinks;
+    use super::super::{build_schedule, setup_world, spawn_blinker_cells, ProgressBar, Rules};
+    use super::*;
+    use image::{GrayImage, ImageOutputFormat, Luma};
+
+    #[test]
+    fn test_bright_pixels_become_live_cells() {
+        let mut image = GrayImage::new(4, 3);
+        image.put_pixel(1, 0, Luma([255]));
+        image.put_pixel(2, 1, Luma([200]));
+        image.put_pixel(0, 2, Luma([100]));
+        image.put_pixel(3, 2, Luma([128]));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageOutputFormat::Png)
+            .unwrap();
+
+        let pattern = parse_png(&bytes, DEFAULT_THRESHOLD).unwrap();
+        assert_eq!((pattern.width, pattern.height), (4, 3));
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (3, 2)]);
+        let pattern = parse_png(&bytes, 150).unwrap();
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1)]);
+        assert!(parse_png(b"not a png", DEFAULT_THRESHOLD).is_err());
+    }
+
+    #[test]
+    fn test_heatmap_scales_to_highest_count() {
+        let counts = HashMap::from([((0, 0), 4), ((1, 0), 2), ((1, 1), 0)]);
+        let image = heatmap(
+            &counts,
+            &Grid {
+                width: 2,
+                height: 2,
+            },
+        );
+        let pixels = image.pixels().map(|p| p.0[0]).collect::<Vec<_>>();
+        assert_eq!(pixels, vec![255, 127, 0, 0]);
+    }
+
+    #[test]
+    fn test_apng_has_one_frame_per_generation() {
+        let path =
+            std::env::temp_dir().join(format!("ecs_without_bevy_apng_{}.png", std::process::id()));
+        let mut world = setup_world(5, 5, Rules::default());
+        spawn_blinker_cells(&mut world, 5, 5);
+        let mut sinks: Vec<Box<dyn OutputSink>> = vec![Box::new(ApngSink::new(path.clone(), 80))];
+        run_with_sinks(
+            &mut world,
+            &mut build_schedule(),
+            6,
+            false,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut reader = ::png::Decoder::new(Cursor::new(bytes)).read_info().unwrap();
+        let control = reader.info().animation_control().unwrap();
+        assert_eq!((control.num_frames, control.num_plays), (6, 0));
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let mut frames = Vec::new();
+        while reader.next_frame(&mut buffer).is_ok() {
+            let control = reader.info().frame_control().unwrap();
+            assert_eq!((control.delay_num, control.delay_den), (80, 1000));
+            frames.push(buffer.iter().filter(|&&pixel| pixel == 255).count());
+        }
+        assert_eq!(frames, vec![3; 6]);
+        assert!(matches!(
+            encode_apng(&[], 80),
+            Err(GameOfLifeError::EncodeError(_))
+        ));
+    }
+}
@@ -0,0 +1,29 @@
+#[cfg(feature = "term")]
+pub use indicatif::ProgressBar;
+
+// Stand-in for indicatif's progress bar in builds without the `term` feature. It is
+// always hidden and only has the methods the runner calls.
+#[cfg(not(feature = "term"))]
+#[derive(Debug, Clone, Default)]
+pub struct ProgressBar;
+
+#[cfg(not(feature = "term"))]
+impl ProgressBar {
+    pub fn hidden() -> Self {
+        ProgressBar
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        true
+    }
+
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        f()
+    }
+
+    pub fn set_position(&self, _position: u64) {}
+
+    pub fn set_message(&self, _message: impl Into<std::borrow::Cow<'static, str>>) {}
+
+    pub fn finish_and_clear(&self) {}
+}
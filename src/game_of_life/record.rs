@@ -0,0 +1,205 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+
+use super::{sort_cells, GameOfLifeError, GridSnapshot, OutputSink, StepResult};
+
+// First line of a recording.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+struct Header {
+    width: u32,
+    height: u32,
+}
+
+// One line per generation, live cells sorted by row then column.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Frame {
+    #[serde(rename = "gen")]
+    generation: u32,
+    live: Vec<(i32, i32)>,
+}
+
+// Writes every generation's live cells as JSON lines after a grid size header, so a
+// run can be replayed later without simulating it again.
+pub struct RecordSink {
+    out: Box<dyn Write>,
+    header_written: bool,
+}
+
+impl RecordSink {
+    pub fn new(out: Box<dyn Write>) -> Self {
+        RecordSink {
+            out,
+            header_written: false,
+        }
+    }
+
+    fn write_frame(&mut self, generation: u32, grid: &GridSnapshot) -> std::io::Result<()> {
+        if !self.header_written {
+            let header = Header {
+                width: grid.width,
+                height: grid.height,
+            };
+            writeln!(self.out, "{}", serde_json::to_string(&header)?)?;
+            self.header_written = true;
+        }
+        let live = sort_cells(grid.live.iter().copied());
+        let frame = Frame { generation, live };
+        writeln!(self.out, "{}", serde_json::to_string(&frame)?)
+    }
+}
+
+impl OutputSink for RecordSink {
+    fn on_generation(&mut self, generation: u32, grid: &GridSnapshot, _step: &StepResult) {
+        if let Err(err) = self.write_frame(generation, grid) {
+            eprintln!("Failed to write recording: {}", err);
+        }
+    }
+
+    fn finish(&mut self) {
+        let _ = self.out.flush();
+    }
+}
+
+// A recorded run: the grid size and each generation's live cells in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording {
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<(u32, HashSet<(i32, i32)>)>,
+}
+
+impl Recording {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|line| !line.trim().is_empty());
+        let header = lines.next().ok_or("empty recording")?;
+        let header: Header =
+            serde_json::from_str(header).map_err(|err| format!("invalid header: {}", err))?;
+        let frames = lines
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str::<Frame>(line)
+                    .map(|frame| (frame.generation, frame.live.into_iter().collect()))
+                    .map_err(|err| format!("line {}: {}", i + 2, err))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Recording {
+            width: header.width,
+            height: header.height,
+            frames,
+        })
+    }
+
+    pub fn read(path: &Path) -> Result<Self, GameOfLifeError> {
+        let text = fs::read_to_string(path).map_err(|err| GameOfLifeError::io(path, err))?;
+        Recording::parse(&text).map_err(|err| {
+            GameOfLifeError::ParseError(format!("invalid recording {}: {}", path.display(), err))
+        })
+    }
+
+    pub fn snapshots(&self) -> impl Iterator<Item = (u32, GridSnapshot)> + '_ {
+        self.frames.iter().map(|(generation, live)| {
+            (
+                *generation,
+                GridSnapshot {
+                    width: self.width,
+                    height: self.height,
+                    live: live.clone(),
+                },
+            )
+        })
+    }
+}
+
+// Replay speed multipliers `frame_delay` accepts; speeds outside it are clamped.
+pub const SPEED_RANGE: RangeInclusive<f64> = 0.01..=100.0;
+
+// Pause between replayed frames: `base` at speed 1, shorter when faster and longer
+// when slower. A delay too long for a `Duration` saturates instead of panicking.
+pub fn frame_delay(base: Duration, speed: f64) -> Duration {
+    let speed = speed.clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
+    Duration::try_from_secs_f64(base.as_secs_f64() / speed).unwrap_or(Duration::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        build_schedule, live_cells, output, setup_world, spawn_pattern_cells, ProgressBar, Rules,
+    };
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // Shares the written bytes with the test after the sink is dropped.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_replay_matches_recorded_run() {
+        let buffer = Buffer::default();
+        let mut world = setup_world(8, 8, Rules::default());
+        let glider = HashSet::from([(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        spawn_pattern_cells(&mut world, 8, 8, &glider);
+
+        let mut sinks: Vec<Box<dyn OutputSink>> =
+            vec![Box::new(RecordSink::new(Box::new(buffer.clone())))];
+        let mut schedule = build_schedule();
+        output::run_with_sinks(
+            &mut world,
+            &mut schedule,
+            6,
+            true,
+            &mut sinks,
+            &ProgressBar::hidden(),
+        );
+        drop(sinks);
+
+        let mut world = setup_world(8, 8, Rules::default());
+        spawn_pattern_cells(&mut world, 8, 8, &glider);
+        let mut schedule = build_schedule();
+        let mut expected = vec![live_cells(&mut world)];
+        for _ in 0..6 {
+            schedule.run(&mut world);
+            expected.push(live_cells(&mut world));
+        }
+
+        let text = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let recording = Recording::parse(&text).unwrap();
+        assert_eq!((recording.width, recording.height), (8, 8));
+        assert_eq!(
+            recording.frames.iter().map(|f| f.0).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 5, 6]
+        );
+        let replayed = recording
+            .snapshots()
+            .map(|(_, snapshot)| snapshot.live)
+            .collect::<Vec<_>>();
+        assert_eq!(replayed, expected);
+        assert!(Recording::parse("").is_err());
+    }
+
+    #[test]
+    fn test_frame_delay_scales_inversely_with_speed() {
+        let base = Duration::from_millis(100);
+        assert_eq!(frame_delay(base, 1.0), base);
+        assert_eq!(frame_delay(base, 2.0), Duration::from_millis(50));
+        assert_eq!(frame_delay(base, 4.0), Duration::from_millis(25));
+        assert_eq!(frame_delay(base, 0.25), Duration::from_millis(400));
+        assert_eq!(frame_delay(base, 1e-300), Duration::from_secs(10));
+        assert_eq!(frame_delay(base, 1e300), Duration::from_millis(1));
+        assert_eq!(frame_delay(Duration::MAX, 0.01), Duration::MAX);
+    }
+}
@@ -0,0 +1,309 @@
+use std::fs;
+use std::path::Path;
+
+use super::{GameOfLifeError, Rules};
+
+// A pattern read from a file or string, with live cells relative to its top-left corner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: u32,
+    pub height: u32,
+    pub cells: Vec<(i32, i32)>,
+    pub rule: Option<String>,
+}
+
+impl Pattern {
+    // Pads the pattern with `margin` dead cells on every side, moving its cells so
+    // they stay centred within the new bounds.
+    pub fn with_margin(mut self, margin: u32) -> Self {
+        let offset = margin as i32;
+        for (x, y) in &mut self.cells {
+            *x += offset;
+            *y += offset;
+        }
+        self.width += 2 * margin;
+        self.height += 2 * margin;
+        self
+    }
+
+    // Grid size for this pattern where the command line did not give one; explicit
+    // dimensions win.
+    pub fn grid_size(&self, width: Option<u32>, height: Option<u32>) -> (u32, u32) {
+        (width.unwrap_or(self.width), height.unwrap_or(self.height))
+    }
+
+    // The rule named in the header, if there was one.
+    pub fn rules(&self) -> Result<Option<Rules>, GameOfLifeError> {
+        self.rule
+            .as_deref()
+            .map(|rule| {
+                rule.parse().map_err(|err| {
+                    GameOfLifeError::ParseError(format!("invalid rule in RLE header: {}", err))
+                })
+            })
+            .transpose()
+    }
+
+    // The rule to run the pattern under: `explicit` when one was given, otherwise the
+    // header's, otherwise Conway's.
+    pub fn rule_or(&self, explicit: Option<Rules>) -> Result<Rules, GameOfLifeError> {
+        match explicit {
+            Some(rules) => Ok(rules),
+            None => Ok(self.rules()?.unwrap_or_default()),
+        }
+    }
+}
+
+fn parse_header(line: &str, pattern: &mut Pattern) -> Result<(), String> {
+    for field in line.split(',') {
+        let (key, value) = field
+            .split_once('=')
+            .ok_or_else(|| format!("invalid RLE header field {:?}", field.trim()))?;
+        let value = value.trim();
+        match key.trim() {
+            "x" => {
+                pattern.width = value
+                    .parse()
+                    .map_err(|_| format!("invalid RLE width {:?}", value))?
+            }
+            "y" => {
+                pattern.height = value
+                    .parse()
+                    .map_err(|_| format!("invalid RLE height {:?}", value))?
+            }
+            "rule" => pattern.rule = Some(value.to_string()),
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+// `#P x y` or `#R x y`: where the pattern's top-left corner belongs.
+fn parse_offset(line: &str) -> Result<Option<(i32, i32)>, String> {
+    let mut parts = line.split_whitespace();
+    if !matches!(parts.next(), Some("#P" | "#R")) {
+        return Ok(None);
+    }
+    let mut coordinate = || {
+        parts
+            .next()
+            .and_then(|n| n.parse::<i32>().ok())
+            .ok_or_else(|| format!("invalid position comment {:?}", line))
+    };
+    let (x, y) = (coordinate()?, coordinate()?);
+    if x < 0 || y < 0 {
+        return Err(format!(
+            "position comment {:?} places the pattern at negative coordinates",
+            line
+        ));
+    }
+    Ok(Some((x, y)))
+}
+
+// Parses Run Length Encoded patterns. `#P`/`#R` position comments move the pattern to
+// the given top-left corner, growing its bounds to match; other `#` comment lines
+// are skipped. The `x = .., y = ..` header is optional; without it the size is taken
+// from the body.
+pub fn parse_rle(text: &str) -> Result<Pattern, GameOfLifeError> {
+    parse_body(text).map_err(GameOfLifeError::ParseError)
+}
+
+fn parse_body(text: &str) -> Result<Pattern, String> {
+    let mut pattern = Pattern {
+        width: 0,
+        height: 0,
+        cells: Vec::new(),
+        rule: None,
+    };
+    let mut body = String::new();
+    let mut offset = (0, 0);
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('#') {
+            if let Some(position) = parse_offset(line)? {
+                offset = position;
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('x') && body.is_empty() {
+            parse_header(line, &mut pattern)?;
+        } else {
+            body.push_str(line);
+        }
+    }
+
+    let (mut x, mut y) = (0i32, 0i32);
+    let (mut max_x, mut max_y) = (0i32, 0i32);
+    let mut run = String::new();
+    for c in body.chars() {
+        if c.is_ascii_digit() {
+            run.push(c);
+            continue;
+        }
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let count = if run.is_empty() {
+            1
+        } else {
+            run.parse::<i32>()
+                .map_err(|_| format!("invalid run length {:?}", run))?
+        };
+        run.clear();
+        let advance = |position: i32| {
+            position
+                .checked_add(count)
+                .ok_or_else(|| format!("RLE body reaches past coordinate {}", i32::MAX))
+        };
+        match c {
+            'b' | '.' => x = advance(x)?,
+            'o' => {
+                let end = advance(x)?;
+                pattern.cells.extend((x..end).map(|cx| (cx, y)));
+                x = end;
+                max_x = max_x.max(x);
+                max_y = max_y.max(y.saturating_add(1));
+            }
+            '$' => {
+                y = advance(y)?;
+                x = 0;
+            }
+            '!' => break,
+            _ => return Err(format!("unexpected character {:?} in RLE body", c)),
+        }
+    }
+
+    let past_edge = || {
+        format!(
+            "position ({}, {}) moves the pattern past coordinate {}",
+            offset.0,
+            offset.1,
+            i32::MAX
+        )
+    };
+    for (x, y) in &mut pattern.cells {
+        *x = x.checked_add(offset.0).ok_or_else(past_edge)?;
+        *y = y.checked_add(offset.1).ok_or_else(past_edge)?;
+    }
+    pattern.width = pattern
+        .width
+        .max(max_x as u32)
+        .checked_add(offset.0 as u32)
+        .ok_or_else(past_edge)?;
+    pattern.height = pattern
+        .height
+        .max(max_y as u32)
+        .checked_add(offset.1 as u32)
+        .ok_or_else(past_edge)?;
+    Ok(pattern)
+}
+
+pub fn load_rle(path: &Path) -> Result<Pattern, GameOfLifeError> {
+    let text = fs::read_to_string(path).map_err(|err| GameOfLifeError::io(path, err))?;
+    parse_rle(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inline_glider() {
+        let pattern = parse_rle("bo$2bo$3o!").unwrap();
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 3);
+        assert_eq!(pattern.rule, None);
+        assert_eq!(pattern.cells, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+    }
+
+    #[test]
+    fn test_parse_with_header() {
+        let text = "#N Blinker\nx = 3, y = 1, rule = B3/S23\n3o!\n";
+        let pattern = parse_rle(text).unwrap();
+        assert_eq!(pattern.width, 3);
+        assert_eq!(pattern.height, 1);
+        assert_eq!(pattern.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(pattern.cells, vec![(0, 0), (1, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn test_parse_multiline_body_and_blank_rows() {
+        let pattern = parse_rle("2o$\n2$2o!").unwrap();
+        assert_eq!(pattern.cells, vec![(0, 0), (1, 0), (0, 3), (1, 3)]);
+        assert_eq!(pattern.height, 4);
+        assert!(parse_rle("2q!").is_err());
+    }
+
+    #[test]
+    fn test_header_rule() {
+        let path = std::env::temp_dir().join(format!(
+            "ecs_without_bevy_highlife_{}.rle",
+            std::process::id()
+        ));
+        fs::write(&path, "x = 3, y = 3, rule = B36/S23\nbo$2bo$3o!\n").unwrap();
+        let pattern = load_rle(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(pattern.rules().unwrap(), Some("B36/S23".parse().unwrap()));
+        assert_eq!(parse_rle("3o!").unwrap().rules().unwrap(), None);
+        assert!(parse_rle("x = 3, y = 1, rule = B9/S\n3o!")
+            .unwrap()
+            .rules()
+            .is_err());
+
+        let conway = Rules::default();
+        assert_eq!(pattern.rule_or(None).unwrap(), "B36/S23".parse().unwrap());
+        assert_eq!(pattern.rule_or(Some(conway.clone())).unwrap(), conway);
+        assert_eq!(parse_rle("3o!").unwrap().rule_or(None).unwrap(), conway);
+        // An explicit rule wins even over a header that does not parse.
+        let invalid = parse_rle("x = 3, y = 1, rule = B9/S\n3o!").unwrap();
+        assert_eq!(invalid.rule_or(Some(conway.clone())).unwrap(), conway);
+        assert!(invalid.rule_or(None).is_err());
+    }
+
+    #[test]
+    fn test_position_comment_offsets_pattern() {
+        let path = std::env::temp_dir().join(format!(
+            "ecs_without_bevy_offset_{}.rle",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "#N Glider\n#P 10 5\n#O someone\nx = 3, y = 3\nbo$2bo$3o!\n",
+        )
+        .unwrap();
+        let pattern = load_rle(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            pattern.cells,
+            vec![(11, 5), (12, 6), (10, 7), (11, 7), (12, 7)]
+        );
+        assert_eq!((pattern.width, pattern.height), (13, 8));
+        assert_eq!(parse_rle("#R 2 0\n3o!").unwrap().cells[0], (2, 0));
+        assert!(parse_rle("#P 1\n3o!").is_err());
+        assert!(parse_rle("#P -1 0\n3o!").is_err());
+        assert!(parse_rle("#P 2147483647 0\n2o!").is_err());
+        assert!(parse_rle("#P 0 2147483647\n$o!").is_err());
+        assert!(parse_rle("2147483647b2o!").is_err());
+        assert!(parse_rle("x = 4294967295, y = 1\n#P 1 0\no!").is_err());
+        assert_eq!(parse_rle("#Pxyz\n3o!").unwrap().cells[0], (0, 0));
+    }
+
+    #[test]
+    fn test_grid_size_with_margin() {
+        let glider = parse_rle("x = 3, y = 3\nbo$2bo$3o!")
+            .unwrap()
+            .with_margin(5);
+        assert_eq!(glider.grid_size(None, None), (13, 13));
+        assert_eq!(glider.grid_size(Some(40), None), (40, 13));
+        assert_eq!(glider.cells[0], (6, 5));
+        assert!(glider
+            .cells
+            .iter()
+            .all(|&(x, y)| (5..8).contains(&x) && (5..8).contains(&y)));
+    }
+}
@@ -0,0 +1,311 @@
+use bevy_ecs::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+// Outer-totalistic birth/survival rule indexed by live neighbor count.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct Rules {
+    pub birth: [bool; 9],
+    pub survival: [bool; 9],
+}
+
+// Whether a cell is alive next generation, given whether it is alive now and its
+// live neighbor count.
+pub fn next_cell_state(alive: bool, neighbors: u8, rules: &Rules) -> bool {
+    if alive {
+        rules.survives(neighbors)
+    } else {
+        rules.born(neighbors)
+    }
+}
+
+impl Rules {
+    pub fn conway() -> Self {
+        "B3/S23".parse().unwrap()
+    }
+
+    pub fn born(&self, neighbors: u8) -> bool {
+        self.birth.get(neighbors as usize).copied().unwrap_or(false)
+    }
+
+    pub fn survives(&self, neighbors: u8) -> bool {
+        self.survival
+            .get(neighbors as usize)
+            .copied()
+            .unwrap_or(false)
+    }
+
+    // Each birth and survival count is included with even odds; the same seed gives the
+    // same rule. B0 is never chosen, since it would light up the whole empty background.
+    pub fn random(seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut birth = [false; 9];
+        let mut survival = [false; 9];
+        for born in &mut birth[1..] {
+            *born = rng.gen_bool(0.5);
+        }
+        for survives in &mut survival {
+            *survives = rng.gen_bool(0.5);
+        }
+        Rules { birth, survival }
+    }
+
+    fn preset(name: &str) -> Option<&'static str> {
+        match name {
+            "conway" | "life" => Some("B3/S23"),
+            "seeds" => Some("B2/S"),
+            "lwd" => Some("B3/S012345678"),
+            // Symmetric under swapping live and dead cells.
+            "daynight" => Some("B3678/S34678"),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self::conway()
+    }
+}
+
+// Isotropic rules only look at how many neighbors are alive. Anisotropic rules also
+// care about which directions they are in, so the neighbor pass records a
+// `NeighborMask` per cell.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RuleSymmetry {
+    #[default]
+    Isotropic,
+    Anisotropic,
+}
+
+fn parse_counts(digits: &str, rule: &str) -> Result<[bool; 9], String> {
+    let mut counts = [false; 9];
+    for c in digits.chars() {
+        match c.to_digit(10) {
+            Some(n) if n <= 8 => counts[n as usize] = true,
+            _ => return Err(format!("invalid neighbor count {:?} in rule {:?}", c, rule)),
+        }
+    }
+    Ok(counts)
+}
+
+impl FromStr for Rules {
+    type Err = String;
+
+    // Accepts a preset name (`conway`, `seeds`, `lwd`, `daynight`) or a rulestring like `B36/S23`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = s.trim().to_ascii_lowercase();
+        if let Some(preset) = Self::preset(&rule) {
+            return preset.parse();
+        }
+
+        let mut birth = None;
+        let mut survival = None;
+        for part in rule.split('/') {
+            if let Some(digits) = part.strip_prefix('b') {
+                birth = Some(parse_counts(digits, s)?);
+            } else if let Some(digits) = part.strip_prefix('s') {
+                survival = Some(parse_counts(digits, s)?);
+            } else {
+                return Err(format!("invalid rulestring {:?}, expected e.g. B3/S23", s));
+            }
+        }
+
+        match (birth, survival) {
+            (Some(birth), Some(survival)) => Ok(Rules { birth, survival }),
+            _ => Err(format!("rulestring {:?} needs both B and S parts", s)),
+        }
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        for (n, _) in self.birth.iter().enumerate().filter(|(_, b)| **b) {
+            write!(f, "{}", n)?;
+        }
+        write!(f, "/S")?;
+        for (n, _) in self.survival.iter().enumerate().filter(|(_, s)| **s) {
+            write!(f, "{}", n)?;
+        }
+        Ok(())
+    }
+}
+
+// A rectangle of the grid that runs its own rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleRegion {
+    pub x: Range<i32>,
+    pub y: Range<i32>,
+    pub rules: Rules,
+}
+
+impl RuleRegion {
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.x.contains(&x) && self.y.contains(&y)
+    }
+}
+
+impl FromStr for RuleRegion {
+    type Err = String;
+
+    // `X,Y,W,H:RULE`, e.g. `30,0,30,40:B36/S23` for a 30x40 HighLife block at (30, 0).
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rect, rule) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected X,Y,W,H:RULE, got {:?}", s))?;
+        let numbers = rect
+            .split(',')
+            .map(|n| n.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid rectangle {:?}", rect))?;
+        let [x, y, width, height] = numbers[..] else {
+            return Err(format!("expected X,Y,W,H, got {:?}", rect));
+        };
+        let (Some(right), Some(bottom)) = (x.checked_add(width), y.checked_add(height)) else {
+            return Err(format!(
+                "rectangle {:?} reaches outside the i32 coordinate range",
+                rect
+            ));
+        };
+        Ok(RuleRegion {
+            x: x..right,
+            y: y..bottom,
+            rules: rule.parse()?,
+        })
+    }
+}
+
+// Rectangles that override the grid's `Rules` for the cells inside them. Each cell
+// follows the rule of the region it is in, the first listed when they overlap.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct RuleRegions(pub Vec<RuleRegion>);
+
+impl RuleRegions {
+    pub fn rules_at(&self, x: i32, y: i32) -> Option<&Rules> {
+        self.0
+            .iter()
+            .find(|region| region.contains(x, y))
+            .map(|region| &region.rules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_cell_state_conway_truth_table() {
+        let rules = Rules::conway();
+        for neighbors in 0..=8 {
+            assert_eq!(
+                next_cell_state(false, neighbors, &rules),
+                neighbors == 3,
+                "dead cell with {} neighbors",
+                neighbors
+            );
+            assert_eq!(
+                next_cell_state(true, neighbors, &rules),
+                neighbors == 2 || neighbors == 3,
+                "live cell with {} neighbors",
+                neighbors
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_conway() {
+        let rules: Rules = "B3/S23".parse().unwrap();
+        assert_eq!(rules, Rules::default());
+        assert!(rules.born(3));
+        assert!(!rules.born(2));
+        assert!(rules.survives(2));
+        assert!(rules.survives(3));
+        assert!(!rules.survives(4));
+        assert!(!rules.born(9));
+    }
+
+    #[test]
+    fn test_parse_presets() {
+        assert_eq!("seeds".parse::<Rules>().unwrap().to_string(), "B2/S");
+        assert_eq!("lwd".parse::<Rules>().unwrap().to_string(), "B3/S012345678");
+        assert_eq!("Conway".parse::<Rules>().unwrap().to_string(), "B3/S23");
+        assert_eq!("s23/b36".parse::<Rules>().unwrap().to_string(), "B36/S23");
+        assert_eq!(
+            "daynight".parse::<Rules>().unwrap().to_string(),
+            "B3678/S34678"
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!("B9/S23".parse::<Rules>().is_err());
+        assert!("B3".parse::<Rules>().is_err());
+        assert!("highlander".parse::<Rules>().is_err());
+    }
+
+    #[test]
+    fn test_parse_rule_region() {
+        let region: RuleRegion = "30,0,30,40:B36/S23".parse().unwrap();
+        assert_eq!((region.x, region.y), (30..60, 0..40));
+        assert_eq!(region.rules.to_string(), "B36/S23");
+        assert!("30,0,30:B3/S23".parse::<RuleRegion>().is_err());
+        assert!("30,0,30,40".parse::<RuleRegion>().is_err());
+        assert!("2147483647,0,1,1:B3/S23".parse::<RuleRegion>().is_err());
+        assert!("0,-2147483648,1,-1:B3/S23".parse::<RuleRegion>().is_err());
+    }
+
+    #[test]
+    fn test_random_rule_is_reproducible() {
+        let rule = Rules::random(42).to_string();
+        assert_eq!(rule, Rules::random(42).to_string());
+        assert_eq!(rule.parse::<Rules>().unwrap(), Rules::random(42));
+        let rules = (0..20).map(Rules::random).collect::<Vec<_>>();
+        assert!(rules.iter().all(|rules| !rules.born(0)));
+        assert!(rules.iter().any(|rules| *rules != Rules::random(42)));
+    }
+
+    #[test]
+    fn test_daynight_inversion_symmetry() {
+        use super::super::{compute_neighbors, BoundaryMode, Grid};
+        use std::collections::HashSet;
+
+        let rules: Rules = "daynight".parse().unwrap();
+        let grid = Grid {
+            width: 8,
+            height: 8,
+        };
+        let step = |live: &HashSet<(i32, i32)>| {
+            compute_neighbors(live, &grid, BoundaryMode::Toroidal)
+                .into_iter()
+                .filter(|&(cell, count)| next_cell_state(live.contains(&cell), count, &rules))
+                .map(|(cell, _)| cell)
+                .collect::<HashSet<_>>()
+        };
+        let invert = |live: &HashSet<(i32, i32)>| {
+            (0..8)
+                .flat_map(|y| (0..8).map(move |x| (x, y)))
+                .filter(|cell| !live.contains(cell))
+                .collect::<HashSet<_>>()
+        };
+
+        let pattern = HashSet::from([
+            (1, 1),
+            (2, 1),
+            (3, 2),
+            (1, 3),
+            (2, 3),
+            (5, 5),
+            (6, 5),
+            (5, 6),
+        ]);
+        let mut live = pattern;
+        for _ in 0..4 {
+            assert_eq!(step(&invert(&live)), invert(&step(&live)));
+            live = step(&live);
+        }
+    }
+}
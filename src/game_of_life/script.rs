@@ -0,0 +1,261 @@
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::{
+    ActivityRegions, Background, CellBundle, CellsChanged, GameOfLifeError, GenerationCounter,
+    Grid, Index, LastChanged, Position, Rules, State,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mutation {
+    Set { x: i32, y: i32, alive: bool },
+    Rule(Rules),
+}
+
+// Timed mutations keyed by the generation they are applied to. A mutation for
+// generation N is applied right after N is computed, so generation N already shows it.
+#[derive(Resource, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Script {
+    pub mutations: HashMap<u32, Vec<Mutation>>,
+}
+
+fn parse_line(line: &str) -> Result<(u32, Mutation), String> {
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+    let generation = parts[0]
+        .parse::<u32>()
+        .map_err(|_| format!("invalid generation {:?}", parts[0]))?;
+    if generation == 0 {
+        return Err("generation 0 is the seed, mutations start at generation 1".to_string());
+    }
+
+    match parts[1..] {
+        ["set", x, y, state] => {
+            let x = x.parse().map_err(|_| format!("invalid x {:?}", x))?;
+            let y = y.parse().map_err(|_| format!("invalid y {:?}", y))?;
+            let alive = match state {
+                "alive" => true,
+                "dead" => false,
+                _ => return Err(format!("expected alive or dead, got {:?}", state)),
+            };
+            Ok((generation, Mutation::Set { x, y, alive }))
+        }
+        ["rule", rule] => Ok((generation, Mutation::Rule(rule.parse()?))),
+        _ => Err(format!("unknown command {:?}", line)),
+    }
+}
+
+// One command per line, e.g. `10 set 5 5 alive` or `50 rule B36/S23`. Blank lines and `#` comments are skipped.
+pub fn parse_script(text: &str) -> Result<Script, GameOfLifeError> {
+    let mut script = Script::default();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (generation, mutation) = parse_line(line)
+            .map_err(|err| GameOfLifeError::ParseError(format!("line {}: {}", number + 1, err)))?;
+        script
+            .mutations
+            .entry(generation)
+            .or_default()
+            .push(mutation);
+    }
+    Ok(script)
+}
+
+pub fn load_script(path: &Path) -> Result<Script, GameOfLifeError> {
+    let text = fs::read_to_string(path).map_err(|err| GameOfLifeError::io(path, err))?;
+    parse_script(&text)
+}
+
+// Exclusive, like `compact_system`, so the cells it spawns and the background it
+// starts or drops are in place for the neighbor pass that closes the generation.
+pub(super) fn scripted_mutation_system(world: &mut World) {
+    let generation = world.resource::<GenerationCounter>().0;
+    let Some(mutations) = world
+        .get_resource::<Script>()
+        .and_then(|s| s.mutations.get(&generation))
+        .cloned()
+    else {
+        return;
+    };
+
+    for mutation in mutations {
+        match mutation {
+            Mutation::Set { x, y, alive } => {
+                let existing = world
+                    .query::<(Entity, &Position)>()
+                    .iter(world)
+                    .find(|(_, pos)| pos.x == x && pos.y == y)
+                    .map(|(entity, _)| entity);
+                match existing {
+                    Some(entity) => {
+                        world.entity_mut(entity).insert(State(alive));
+                    }
+                    // Compaction has despawned this cell, so a live one needs a new
+                    // entity and a dead one is already taken care of.
+                    None if alive && world.resource::<Grid>().contains(x, y) => {
+                        let width = world.resource::<Grid>().width;
+                        world.spawn(CellBundle {
+                            position: Position { x, y },
+                            state: State(true),
+                            last_changed: LastChanged(generation),
+                            index: Index(y as u32 * width + x as u32),
+                            ..Default::default()
+                        });
+                    }
+                    None => continue,
+                };
+                world.resource_mut::<CellsChanged>().0 = true;
+                if let Some(mut activity) = world.get_resource_mut::<ActivityRegions>() {
+                    activity.mark(x, y, generation);
+                }
+            }
+            Mutation::Rule(rule) => {
+                // Switching to a B0 rule starts tracking the (still dead) background.
+                // Switching away drops it once it is dead, since nothing can revive it.
+                match (
+                    Background::for_rules(&rule),
+                    world.get_resource::<Background>(),
+                ) {
+                    (Some(fresh), None) => world.insert_resource(fresh),
+                    (None, Some(current)) if !current.alive => {
+                        world.remove_resource::<Background>();
+                    }
+                    _ => (),
+                }
+                world.insert_resource(rule);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_script() {
+        let script =
+            parse_script("# perturb\n10 set 5 5 alive\n\n10 set 1 2 dead\n3 set 0 0 alive\n")
+                .unwrap();
+        assert_eq!(
+            script.mutations[&10],
+            vec![
+                Mutation::Set {
+                    x: 5,
+                    y: 5,
+                    alive: true
+                },
+                Mutation::Set {
+                    x: 1,
+                    y: 2,
+                    alive: false
+                },
+            ]
+        );
+        assert_eq!(script.mutations[&3].len(), 1);
+    }
+
+    #[test]
+    fn test_parse_script_errors() {
+        assert!(parse_script("0 set 1 1 alive").is_err());
+        assert!(parse_script("2 set 1 alive").is_err());
+        assert!(parse_script("2 set 1 1 maybe").is_err());
+        assert!(parse_script("2 flip 1 1").is_err());
+        assert!(parse_script("2 rule B9/S").is_err());
+        assert!(parse_script("two set 1 1 alive")
+            .unwrap_err()
+            .to_string()
+            .starts_with("line 1"));
+    }
+
+    #[test]
+    fn test_scripted_rule_change() {
+        use super::super::{build_schedule, live_cells, setup_world, spawn_pattern_cells};
+        use std::collections::HashSet;
+
+        let block = HashSet::from([(1, 1), (2, 1), (1, 2), (2, 2)]);
+        let mut world = setup_world(4, 4, Rules::default());
+        spawn_pattern_cells(&mut world, 4, 4, &block);
+        world.insert_resource(parse_script("2 rule B2/S").unwrap());
+        let mut schedule = build_schedule();
+
+        // Still a block under Conway for the first two generations.
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        assert_eq!(live_cells(&mut world), block);
+        assert_eq!(world.resource::<Rules>().to_string(), "B2/S");
+
+        // Under Seeds nothing survives and the cells around the block are born.
+        schedule.run(&mut world);
+        let live = live_cells(&mut world);
+        assert!(live.is_disjoint(&block));
+        assert!(live.contains(&(1, 0)));
+    }
+
+    #[test]
+    fn test_scripted_rule_change_tracks_background() {
+        use super::super::{build_schedule, live_cells, setup_world, spawn_empty_cells};
+
+        let run = |script: &str, generations: u32| {
+            let mut world = setup_world(6, 5, Rules::default());
+            spawn_empty_cells(&mut world, 6, 5);
+            world.insert_resource(parse_script(script).unwrap());
+            let mut schedule = build_schedule();
+            for _ in 0..generations {
+                schedule.run(&mut world);
+            }
+            world
+        };
+
+        // Switching to B0/S5 starts a dead background, which the next generation
+        // brings to life along with every cell.
+        let world = run("1 rule B0/S5", 1);
+        assert_eq!(
+            world.get_resource::<Background>(),
+            Some(&Background::default())
+        );
+        let mut world = run("1 rule B0/S5", 2);
+        assert!(world.resource::<Background>().alive);
+        assert_eq!(live_cells(&mut world).len(), 30);
+
+        // Leaving B0 while the background is alive keeps it, so the edges still see
+        // eight neighbors and under Conway even the corners die.
+        let mut world = run("1 rule B0/S5\n2 rule B3/S23", 3);
+        assert!(live_cells(&mut world).is_empty());
+        assert!(!world.resource::<Background>().alive);
+
+        // Leaving B0 while it is dead drops it.
+        let world = run("1 rule B0/S5\n3 rule B3/S23", 3);
+        assert!(!world.contains_resource::<Background>());
+    }
+
+    #[test]
+    fn test_scripted_cells_survive_compaction() {
+        use super::super::{
+            build_schedule, live_cells, setup_world, spawn_pattern_cells, Compaction,
+        };
+        use std::collections::HashSet;
+
+        let block = HashSet::from([(0, 0), (1, 0), (0, 1), (1, 1)]);
+        let mut world = setup_world(8, 8, Rules::default());
+        spawn_pattern_cells(&mut world, 8, 8, &block);
+        world.insert_resource(Compaction::every(1));
+        world.insert_resource(
+            parse_script("2 set 5 5 alive\n2 set 6 5 alive\n2 set 5 6 alive\n2 set 6 6 alive")
+                .unwrap(),
+        );
+        let mut schedule = build_schedule();
+        for _ in 0..3 {
+            schedule.run(&mut world);
+        }
+        let mut expected = block.clone();
+        expected.extend([(5, 5), (6, 5), (5, 6), (6, 6)]);
+        assert_eq!(live_cells(&mut world), expected);
+        assert_eq!(world.query::<&Position>().iter(&world).count(), 8);
+    }
+}
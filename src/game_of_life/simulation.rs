@@ -0,0 +1,191 @@
+use bevy_ecs::prelude::*;
+use std::collections::HashSet;
+
+use super::{build_schedule, live_cells, setup_world, Rules, SimulationSet};
+
+// A world and its standard schedule, stepped one generation at a time.
+pub struct Simulation {
+    pub world: World,
+    schedule: Schedule,
+}
+
+impl Simulation {
+    pub fn new<F>(width: u32, height: u32, rules: Rules, seed_fn: F) -> Self
+    where
+        F: FnOnce(&mut World, u32, u32),
+    {
+        let mut world = setup_world(width, height, rules);
+        seed_fn(&mut world, width, height);
+        Simulation {
+            world,
+            schedule: build_schedule(),
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.schedule.run(&mut self.world);
+    }
+
+    pub fn live_cells(&mut self) -> HashSet<(i32, i32)> {
+        live_cells(&mut self.world)
+    }
+
+    pub fn iter(&mut self) -> GenerationsIter<'_> {
+        let previous = self.live_cells();
+        GenerationsIter {
+            simulation: self,
+            previous,
+            stop_when_settled: false,
+            finished: false,
+        }
+    }
+}
+
+type SeedFn = Box<dyn FnOnce(&mut World, u32, u32)>;
+
+// Builds a `Simulation` whose schedule also runs user systems, either before the
+// core update chain or after it (once the generation counter has advanced).
+pub struct SimulationBuilder {
+    width: u32,
+    height: u32,
+    rules: Rules,
+    seed_fn: Option<SeedFn>,
+    schedule: Schedule,
+}
+
+impl SimulationBuilder {
+    pub fn new(width: u32, height: u32) -> Self {
+        SimulationBuilder {
+            width,
+            height,
+            rules: Rules::default(),
+            seed_fn: None,
+            schedule: build_schedule(),
+        }
+    }
+
+    pub fn rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    pub fn seed(mut self, seed_fn: impl FnOnce(&mut World, u32, u32) + 'static) -> Self {
+        self.seed_fn = Some(Box::new(seed_fn));
+        self
+    }
+
+    // Runs `system` once per generation, after the core update chain.
+    pub fn add_user_system<M>(mut self, system: impl IntoSystemConfigs<M>) -> Self {
+        self.schedule
+            .add_systems(system.in_set(SimulationSet::AfterUpdate));
+        self
+    }
+
+    // Runs `system` once per generation, before the core update chain.
+    pub fn add_user_system_before<M>(mut self, system: impl IntoSystemConfigs<M>) -> Self {
+        self.schedule
+            .add_systems(system.in_set(SimulationSet::BeforeUpdate));
+        self
+    }
+
+    pub fn build(self) -> Simulation {
+        let mut world = setup_world(self.width, self.height, self.rules);
+        if let Some(seed_fn) = self.seed_fn {
+            seed_fn(&mut world, self.width, self.height);
+        }
+        Simulation {
+            world,
+            schedule: self.schedule,
+        }
+    }
+}
+
+// Lazily steps the simulation and yields each new generation's live cells.
+pub struct GenerationsIter<'a> {
+    simulation: &'a mut Simulation,
+    previous: HashSet<(i32, i32)>,
+    stop_when_settled: bool,
+    finished: bool,
+}
+
+impl GenerationsIter<'_> {
+    // Ends the iteration after yielding a generation that is empty or identical to
+    // the one before it.
+    pub fn until_settled(mut self) -> Self {
+        self.stop_when_settled = true;
+        self
+    }
+}
+
+impl Iterator for GenerationsIter<'_> {
+    type Item = HashSet<(i32, i32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        self.simulation.step();
+        let live = self.simulation.live_cells();
+        if self.stop_when_settled && (live.is_empty() || live == self.previous) {
+            self.finished = true;
+        }
+        self.previous = live.clone();
+        Some(live)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{spawn_blinker_cells, spawn_block_cells};
+    use super::*;
+
+    #[test]
+    fn test_blinker_generations() {
+        let mut sim = Simulation::new(3, 3, Rules::default(), spawn_blinker_cells);
+        let generations = sim.iter().take(3).collect::<Vec<_>>();
+
+        let horizontal = HashSet::from([(0, 1), (1, 1), (2, 1)]);
+        let vertical = HashSet::from([(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(generations, vec![horizontal.clone(), vertical, horizontal]);
+    }
+
+    #[test]
+    fn test_until_settled() {
+        let mut sim = Simulation::new(2, 2, Rules::default(), spawn_block_cells);
+        assert_eq!(sim.iter().until_settled().count(), 1);
+
+        let mut sim = Simulation::new(3, 3, Rules::default(), spawn_blinker_cells);
+        assert_eq!(sim.iter().until_settled().take(10).count(), 10);
+    }
+
+    #[test]
+    fn test_user_systems_run_once_per_generation() {
+        use super::super::GenerationCounter;
+
+        #[derive(Resource, Default)]
+        struct Seen {
+            before: Vec<u32>,
+            after: Vec<u32>,
+        }
+
+        let mut sim = SimulationBuilder::new(3, 3)
+            .seed(spawn_blinker_cells)
+            .add_user_system_before(|counter: Res<GenerationCounter>, mut seen: ResMut<Seen>| {
+                seen.before.push(counter.0)
+            })
+            .add_user_system(|counter: Res<GenerationCounter>, mut seen: ResMut<Seen>| {
+                seen.after.push(counter.0)
+            })
+            .build();
+        sim.world.init_resource::<Seen>();
+        for _ in 0..3 {
+            sim.step();
+        }
+
+        let seen = sim.world.resource::<Seen>();
+        assert_eq!(seen.before, vec![0, 1, 2]);
+        assert_eq!(seen.after, vec![1, 2, 3]);
+        assert_eq!(sim.live_cells(), HashSet::from([(0, 1), (1, 1), (2, 1)]));
+    }
+}
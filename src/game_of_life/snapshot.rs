@@ -0,0 +1,151 @@
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::{
+    setup_world, sorted_live, spawn_pattern_cells, Background, GameOfLifeError, GenerationCounter,
+    Grid, Rules,
+};
+
+// Serializable state of a run: the grid, the generation it was taken at, its live
+// cells sorted by row then column and, under B0 rules, whether the background beyond
+// the edges is alive. The field is left out while the background is dead.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub width: u32,
+    pub height: u32,
+    pub generation: u32,
+    pub live: Vec<(i32, i32)>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub background: bool,
+}
+
+impl Snapshot {
+    pub fn capture(world: &mut World) -> Self {
+        let live = sorted_live(world);
+        let grid = world.resource::<Grid>();
+        Snapshot {
+            width: grid.width,
+            height: grid.height,
+            generation: world
+                .get_resource::<GenerationCounter>()
+                .map_or(0, |counter| counter.0),
+            live,
+            background: world
+                .get_resource::<Background>()
+                .is_some_and(|background| background.alive),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, GameOfLifeError> {
+        let text = fs::read_to_string(path).map_err(|err| GameOfLifeError::io(path, err))?;
+        serde_json::from_str(&text).map_err(|err| {
+            GameOfLifeError::ParseError(format!("invalid snapshot {}: {}", path.display(), err))
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), GameOfLifeError> {
+        let text = serde_json::to_string(self).map_err(|err| {
+            GameOfLifeError::EncodeError(format!("cannot encode snapshot: {}", err))
+        })?;
+        fs::write(path, text).map_err(|err| GameOfLifeError::io(path, err))
+    }
+
+    // Builds a world seeded with the snapshot's cells and background whose generation
+    // counter continues from where the snapshot was taken.
+    pub fn into_world(self, rules: Rules) -> World {
+        let mut world = setup_world(self.width, self.height, rules);
+        spawn_pattern_cells(
+            &mut world,
+            self.width,
+            self.height,
+            &self.live.into_iter().collect(),
+        );
+        world.insert_resource(GenerationCounter(self.generation));
+        if self.background {
+            world.insert_resource(Background { alive: true });
+        }
+        world
+    }
+}
+
+pub fn save_snapshot(world: &mut World, path: &Path) -> Result<(), GameOfLifeError> {
+    Snapshot::capture(world).write(path)
+}
+
+pub fn load_snapshot(path: &Path, rules: Rules) -> Result<World, GameOfLifeError> {
+    Ok(Snapshot::read(path)?.into_world(rules))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{build_schedule, live_cells, spawn_blinker_cells};
+    use super::*;
+    use std::collections::HashSet;
+    use std::env;
+
+    #[test]
+    fn test_resume_continues_generation_count() {
+        let path = env::temp_dir().join(format!(
+            "ecs_without_bevy_resume_{}.json",
+            std::process::id()
+        ));
+        let mut world = setup_world(3, 3, Rules::default());
+        spawn_blinker_cells(&mut world, 3, 3);
+        let mut schedule = build_schedule();
+        for _ in 0..3 {
+            schedule.run(&mut world);
+        }
+        save_snapshot(&mut world, &path).unwrap();
+
+        let mut world = load_snapshot(&path, Rules::default()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(world.resource::<GenerationCounter>().0, 3);
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(0, 1), (1, 1), (2, 1)])
+        );
+
+        let mut schedule = build_schedule();
+        for _ in 0..2 {
+            schedule.run(&mut world);
+        }
+        assert_eq!(world.resource::<GenerationCounter>().0, 5);
+        assert_eq!(
+            live_cells(&mut world),
+            HashSet::from([(0, 1), (1, 1), (2, 1)])
+        );
+    }
+
+    #[test]
+    fn test_snapshot_keeps_b0_background() {
+        let path = env::temp_dir().join(format!(
+            "ecs_without_bevy_background_{}.json",
+            std::process::id()
+        ));
+        let rules: Rules = "B0/S5".parse().unwrap();
+        let mut world = setup_world(6, 5, rules.clone());
+        spawn_pattern_cells(&mut world, 6, 5, &HashSet::new());
+        let mut schedule = build_schedule();
+        schedule.run(&mut world);
+        assert!(world.resource::<Background>().alive);
+        save_snapshot(&mut world, &path).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"background\":true"));
+
+        let mut resumed = load_snapshot(&path, rules).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(resumed.resource::<Background>().alive);
+        build_schedule().run(&mut resumed);
+        schedule.run(&mut world);
+        assert_eq!(live_cells(&mut resumed), live_cells(&mut world));
+        assert!(live_cells(&mut resumed).is_empty());
+
+        let blinker = Snapshot::capture(&mut setup_world(3, 3, Rules::default()));
+        let text = serde_json::to_string(&blinker).unwrap();
+        assert!(!text.contains("background"));
+        assert_eq!(serde_json::from_str::<Snapshot>(&text).unwrap(), blinker);
+    }
+}
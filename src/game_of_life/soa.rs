@@ -0,0 +1,215 @@
+use bevy_ecs::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{
+    live_cells, next_cell_state, Background, BoundaryMode, GenerationCounter, Grid, Rules,
+};
+
+// Storage the simulation runs on.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    // One entity per cell, stepped by the schedule.
+    #[default]
+    Ecs,
+    // Parallel state and neighbor count vectors; see `SoaGrid`.
+    Soa,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ecs" => Ok(Backend::Ecs),
+            "soa" => Ok(Backend::Soa),
+            _ => Err(format!("backend must be ecs or soa, got {:?}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Ecs => write!(f, "ecs"),
+            Backend::Soa => write!(f, "soa"),
+        }
+    }
+}
+
+// Dense bounded grid kept as struct-of-arrays indexed by cell id (`y * width + x`),
+// without entities or a schedule. Only supports outer-totalistic rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoaGrid {
+    pub width: u32,
+    pub height: u32,
+    pub generation: u32,
+    states: Vec<bool>,
+    neighbors: Vec<u8>,
+    rules: Rules,
+    boundary: BoundaryMode,
+    // Beyond a dead boundary, for rules with B0.
+    background: Option<Background>,
+}
+
+impl SoaGrid {
+    pub fn new(width: u32, height: u32, rules: Rules, boundary: BoundaryMode) -> Self {
+        let cells = (width * height) as usize;
+        SoaGrid {
+            width,
+            height,
+            generation: 0,
+            states: vec![false; cells],
+            neighbors: vec![0; cells],
+            background: Background::for_rules(&rules),
+            rules,
+            boundary,
+        }
+    }
+
+    // Copies the grid size, rule, generation and live cells out of a seeded world.
+    pub fn from_world(world: &mut World, boundary: BoundaryMode) -> Self {
+        let live = live_cells(world);
+        let grid = world.resource::<Grid>();
+        let mut soa = SoaGrid::new(
+            grid.width,
+            grid.height,
+            world.resource::<Rules>().clone(),
+            boundary,
+        );
+        soa.generation = world
+            .get_resource::<GenerationCounter>()
+            .map_or(0, |counter| counter.0);
+        if let Some(background) = world.get_resource::<Background>() {
+            soa.background = Some(*background);
+        }
+        for (x, y) in live {
+            soa.set(x, y, true);
+        }
+        soa
+    }
+
+    pub fn set(&mut self, x: i32, y: i32, alive: bool) {
+        if x >= 0 && y >= 0 && x < self.width as i32 && y < self.height as i32 {
+            self.states[(y as u32 * self.width + x as u32) as usize] = alive;
+        }
+    }
+
+    pub fn step(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let outside = self.background.is_some_and(|background| background.alive);
+        for id in 0..self.states.len() {
+            let (x, y) = ((id as u32 % width) as i32, (id as u32 / width) as i32);
+            self.neighbors[id] = self
+                .boundary
+                .neighbors(x, y, width, height)
+                .filter(|(_, cell)| match cell {
+                    Some((nx, ny)) => self.states[(*ny as u32 * width + *nx as u32) as usize],
+                    None => outside,
+                })
+                .count() as u8;
+        }
+        for (state, &count) in self.states.iter_mut().zip(&self.neighbors) {
+            *state = next_cell_state(*state, count, &self.rules);
+        }
+        self.background = self
+            .background
+            .map(|background| background.next(&self.rules, 8));
+        self.generation += 1;
+    }
+
+    pub fn population(&self) -> usize {
+        self.states.iter().filter(|alive| **alive).count()
+    }
+
+    // No live cells and no B0 background to bring any back.
+    pub fn is_extinct(&self) -> bool {
+        self.background.is_none() && !self.states.contains(&true)
+    }
+
+    pub fn live_cells(&self) -> HashSet<(i32, i32)> {
+        self.states
+            .iter()
+            .enumerate()
+            .filter(|(_, alive)| **alive)
+            .map(|(id, _)| {
+                let id = id as u32;
+                ((id % self.width) as i32, (id / self.width) as i32)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        build_schedule, setup_world, spawn_beehive_cells, spawn_blinker_cells, spawn_block_cells,
+        GameOfLifeError, RunOptions, SimConfig,
+    };
+    use super::*;
+
+    type SeedFn = fn(&mut World, u32, u32);
+
+    #[test]
+    fn test_soa_matches_ecs() {
+        let seeds: [(u32, u32, SeedFn); 3] = [
+            (3, 3, spawn_blinker_cells),
+            (6, 3, spawn_beehive_cells),
+            (2, 2, spawn_block_cells),
+        ];
+        for (width, height, seed_fn) in seeds {
+            for boundary in [BoundaryMode::Dead, BoundaryMode::Toroidal] {
+                let mut world = setup_world(width, height, Rules::default());
+                seed_fn(&mut world, width, height);
+                world.insert_resource(boundary);
+                let mut soa = SoaGrid::from_world(&mut world, boundary);
+                let mut schedule = build_schedule();
+                for _ in 0..4 {
+                    schedule.run(&mut world);
+                    soa.step();
+                    assert_eq!(soa.live_cells(), live_cells(&mut world));
+                }
+                assert_eq!(soa.generation, 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!("soa".parse::<Backend>(), Ok(Backend::Soa));
+        assert_eq!(Backend::Ecs.to_string(), "ecs");
+        assert!("bitgrid".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn test_soa_rejects_unsupported_options() {
+        let config = |backend: Backend, options: RunOptions| SimConfig {
+            width: Some(8),
+            height: Some(8),
+            options: RunOptions { backend, ..options },
+            ..SimConfig::default()
+        };
+        let outputs = RunOptions {
+            render: true,
+            jsonl: Some("-".into()),
+            follow: true,
+            ..RunOptions::default()
+        };
+        assert!(config(Backend::Ecs, outputs.clone()).validate().is_ok());
+        let err = config(Backend::Soa, outputs).validate().unwrap_err();
+        assert!(matches!(err, GameOfLifeError::InvalidConfig(_)));
+        assert_eq!(
+            err.to_string(),
+            "--backend soa does not support --render, --follow, --jsonl"
+        );
+
+        let supported = RunOptions {
+            save_pbm: Some("out.pbm".into()),
+            bench_json: true,
+            deterministic: true,
+            ..RunOptions::default()
+        };
+        assert!(config(Backend::Soa, supported).validate().is_ok());
+    }
+}
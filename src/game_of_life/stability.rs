@@ -0,0 +1,300 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+use rayon::prelude::*;
+
+use super::objects::ash_signature;
+use super::{sort_cells, spawn_random_cells, Rules, Simulation};
+
+// Remembers every generation's live set (by hash) to notice when the grid returns
+// to an earlier state, i.e. has settled into a still life or oscillator.
+#[derive(Debug, Default)]
+pub struct PeriodDetector {
+    seen: HashMap<u64, u32>,
+}
+
+// A repeated state: it first appeared at generation `since` and recurs every `period`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub since: u32,
+    pub period: u32,
+}
+
+impl PeriodDetector {
+    // Records `live` as the state of `generation`, returning the cycle if it was seen before.
+    pub fn observe(&mut self, generation: u32, live: &HashSet<(i32, i32)>) -> Option<Cycle> {
+        let mut hasher = DefaultHasher::new();
+        sort_cells(live.iter().copied()).hash(&mut hasher);
+
+        let since = *self.seen.entry(hasher.finish()).or_insert(generation);
+        (since != generation).then_some(Cycle {
+            since,
+            period: generation - since,
+        })
+    }
+}
+
+// Steps a fresh `sim` until it repeats an earlier state or reaches generation `cap`.
+// Returns the cycle if one was found, and the live cells of the generation it stopped
+// at, which for a cycle is the first repeat of the state at `cycle.since`.
+pub fn run_until_periodic(sim: &mut Simulation, cap: u32) -> (Option<Cycle>, HashSet<(i32, i32)>) {
+    let mut detector = PeriodDetector::default();
+    let mut live = sim.live_cells();
+    detector.observe(0, &live);
+    for generation in 1..=cap {
+        sim.step();
+        live = sim.live_cells();
+        if let Some(cycle) = detector.observe(generation, &live) {
+            return (Some(cycle), live);
+        }
+    }
+    (None, live)
+}
+
+// The live cells of every generation in one period of a settled run, starting with
+// `live`, the state in which the cycle was detected. Steps `sim` on through the period.
+pub fn period_phases(
+    sim: &mut Simulation,
+    live: HashSet<(i32, i32)>,
+    cycle: Cycle,
+) -> Vec<HashSet<(i32, i32)>> {
+    let mut phases = vec![live];
+    for _ in 1..cycle.period {
+        sim.step();
+        phases.push(sim.live_cells());
+    }
+    phases
+}
+
+// Number of generations a random soup needs to become periodic (an empty grid counts
+// as a still life), or None if it is still changing after `cap` generations.
+pub fn generations_to_stabilize(
+    width: u32,
+    height: u32,
+    seed: u64,
+    density: f64,
+    cap: u32,
+) -> Option<u32> {
+    run_soup(width, height, seed, density, cap).stabilized_at
+}
+
+// Outcome of running one random soup until it became periodic or hit the cap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SoupResult {
+    pub seed: u64,
+    pub stabilized_at: Option<u32>,
+    pub population: usize,
+    // `ash_signature` of the final grid, once it has stabilized.
+    pub ash: Option<u32>,
+}
+
+pub fn run_soup(width: u32, height: u32, seed: u64, density: f64, cap: u32) -> SoupResult {
+    let mut sim = Simulation::new(width, height, Rules::default(), |world, w, h| {
+        spawn_random_cells(world, w, h, seed, density)
+    });
+    match run_until_periodic(&mut sim, cap) {
+        (Some(cycle), live) => SoupResult {
+            seed,
+            stabilized_at: Some(cycle.since),
+            population: live.len(),
+            ash: Some(ash_signature(&period_phases(&mut sim, live, cycle))),
+        },
+        (None, live) => SoupResult {
+            seed,
+            stabilized_at: None,
+            population: live.len(),
+            ash: None,
+        },
+    }
+}
+
+// Runs soups with seeds `0..soups` in parallel, returned in seed order.
+pub fn survey(width: u32, height: u32, density: f64, soups: u64, cap: u32) -> Vec<SoupResult> {
+    (0..soups)
+        .into_par_iter()
+        .map(|seed| run_soup(width, height, seed, density, cap))
+        .collect()
+}
+
+// Longest bar `format_histogram` draws; taller buckets are scaled down to fit.
+pub const HISTOGRAM_WIDTH: usize = 60;
+
+// Text histogram of `values` in `bins` equal-width buckets, one line per bucket.
+pub fn format_histogram(values: &[usize], bins: usize) -> String {
+    let Some(&max) = values.iter().max() else {
+        return "(no data)\n".to_string();
+    };
+    let bins = bins.max(1);
+    let width = (max + 1).div_ceil(bins);
+    let mut counts = vec![0; bins];
+    for &value in values {
+        counts[value / width] += 1;
+    }
+    let tallest = counts.iter().copied().max().unwrap_or(0);
+    // Rounded up, so no bucket with values in it is drawn empty.
+    let bar = |count: usize| {
+        if tallest <= HISTOGRAM_WIDTH {
+            count
+        } else {
+            (count * HISTOGRAM_WIDTH).div_ceil(tallest)
+        }
+    };
+
+    let mut output = String::new();
+    for (i, count) in counts.iter().enumerate() {
+        let low = i * width;
+        if low > max {
+            break;
+        }
+        output.push_str(&format!(
+            "{:>6}-{:<6} {:>5} {}\n",
+            low,
+            low + width - 1,
+            count,
+            "#".repeat(bar(*count))
+        ));
+    }
+    output
+}
+
+// One block character per value, from the lowest value (`▁`) to the highest (`█`).
+pub fn sparkline(values: &[usize]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let (Some(&min), Some(&max)) = (values.iter().min(), values.iter().max()) else {
+        return String::new();
+    };
+    let range = (max - min).max(1);
+    values
+        .iter()
+        .map(|&value| BLOCKS[(value - min) * (BLOCKS.len() - 1) / range])
+        .collect()
+}
+
+// True when the highest population in each of the last `windows` stretches of
+// `window` generations beats the one before, so the population keeps climbing instead
+// of settling into a bounded cycle. A window as long as an oscillator's period hides
+// its ups and downs; a glider gun whose gliders are never eaten grows every window.
+pub fn grows_unbounded(populations: &[usize], window: usize, windows: usize) -> bool {
+    if window == 0 || windows < 2 || populations.len() < window * windows {
+        return false;
+    }
+    let recent = &populations[populations.len() - window * windows..];
+    let maxima = recent
+        .chunks(window)
+        .map(|chunk| chunk.iter().max())
+        .collect::<Vec<_>>();
+    maxima.windows(2).all(|pair| pair[1] > pair[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_rle, spawn_pattern_cells, Rules, Simulation};
+    use super::*;
+
+    #[test]
+    fn test_same_seed_leaves_same_ash() {
+        let first = run_soup(24, 24, 11, 0.4, 1000);
+        let second = run_soup(24, 24, 11, 0.4, 1000);
+        assert!(first.stabilized_at.is_some());
+        assert!(first.ash.is_some());
+        assert_eq!(first.ash, second.ash);
+
+        // The same objects placed and turned differently share the ash; an extra
+        // object changes it.
+        let debris = parse_rle("2o4b3o$2o$5bo$5bo$5bo!").unwrap().cells;
+        let moved = debris.iter().map(|&(x, y)| (20 - y, x + 3)).collect();
+        let debris = debris.into_iter().collect::<HashSet<_>>();
+        assert_eq!(
+            ash_signature(std::slice::from_ref(&debris)),
+            ash_signature(&[moved])
+        );
+        let mut more = debris.clone();
+        more.extend([(10, 10), (11, 10), (10, 11), (11, 11)]);
+        assert_ne!(ash_signature(&[debris]), ash_signature(&[more]));
+    }
+
+    #[test]
+    fn test_full_3x3_soup_dies_in_two_generations() {
+        // Only the corners survive the first step, and they are isolated.
+        assert_eq!(generations_to_stabilize(3, 3, 7, 1.0, 10), Some(2));
+        assert_eq!(generations_to_stabilize(3, 3, 7, 1.0, 2), None);
+        assert_eq!(generations_to_stabilize(3, 3, 7, 0.0, 10), Some(0));
+    }
+
+    #[test]
+    fn test_survey_runs_every_soup() {
+        let results = survey(8, 8, 0.5, 5, 200);
+        assert_eq!(results.len(), 5);
+        assert_eq!(
+            results.iter().map(|r| r.seed).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4]
+        );
+        assert_eq!(results[3], run_soup(8, 8, 3, 0.5, 200));
+    }
+
+    #[test]
+    fn test_format_histogram() {
+        assert_eq!(
+            format_histogram(&[0, 1, 5, 9], 2),
+            "     0-4          2 ##\n     5-9          2 ##\n"
+        );
+        assert_eq!(format_histogram(&[], 4), "(no data)\n");
+
+        let values = [vec![0; 1000], vec![9; 3]].concat();
+        let lines = format_histogram(&values, 2)
+            .lines()
+            .map(|line| line.matches('#').count())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![HISTOGRAM_WIDTH, 1]);
+    }
+
+    #[test]
+    fn test_uneaten_gun_grows_unbounded() {
+        let populations = |rle: &[&str]| {
+            let live = rle
+                .iter()
+                .flat_map(|rle| parse_rle(rle).unwrap().cells)
+                .collect::<HashSet<_>>();
+            let mut sim = Simulation::new(60, 60, Rules::default(), move |world, w, h| {
+                spawn_pattern_cells(world, w, h, &live)
+            });
+            sim.iter()
+                .take(150)
+                .map(|live| live.len())
+                .collect::<Vec<_>>()
+        };
+        let gun = "24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+                   2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!";
+        let eater = "#P 24 10\n2o$obo$2bo$2b2o!";
+
+        assert!(grows_unbounded(&populations(&[gun]), 30, 4));
+        assert!(!grows_unbounded(&populations(&[gun, eater]), 30, 4));
+        assert!(!grows_unbounded(&[1, 2, 3], 30, 4));
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let rise_and_fall = [1, 2, 3, 4, 5, 6, 7, 8, 7, 6, 5, 4, 3, 2, 1];
+        assert_eq!(sparkline(&rise_and_fall), "▁▂▃▄▅▆▇█▇▆▅▄▃▂▁");
+        assert_eq!(sparkline(&[0, 50, 100]), "▁▄█");
+        assert_eq!(sparkline(&[3, 3]), "▁▁");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_detects_blinker_period() {
+        let mut detector = PeriodDetector::default();
+        let horizontal = HashSet::from([(0, 1), (1, 1), (2, 1)]);
+        let vertical = HashSet::from([(1, 0), (1, 1), (1, 2)]);
+        assert_eq!(detector.observe(0, &horizontal), None);
+        assert_eq!(detector.observe(1, &vertical), None);
+        assert_eq!(
+            detector.observe(2, &horizontal),
+            Some(Cycle {
+                since: 0,
+                period: 2
+            })
+        );
+    }
+}
@@ -1,25 +1,652 @@
-use clap::Parser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use ecs_without_bevy::game_of_life::{self, Rules};
+use ecs_without_bevy::status;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
-pub mod game_of_life;
+fn parse_offset(s: &str) -> Result<(i32, i32), String> {
+    let (x, y) = s
+        .split_once(',')
+        .ok_or_else(|| format!("expected X,Y, got {:?}", s))?;
+    let x = x.trim().parse().map_err(|_| format!("invalid x {:?}", x))?;
+    let y = y.trim().parse().map_err(|_| format!("invalid y {:?}", y))?;
+    Ok((x, y))
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("expected a non-negative number of seconds, got {:?}", s))
+}
+
+fn parse_speed(s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|speed| game_of_life::record::SPEED_RANGE.contains(speed))
+        .ok_or_else(|| {
+            let range = game_of_life::record::SPEED_RANGE;
+            format!(
+                "expected a speed multiplier from {} to {}, got {:?}",
+                range.start(),
+                range.end(),
+                s
+            )
+        })
+}
+
+fn parse_density(s: &str) -> Result<f64, String> {
+    s.parse::<f64>()
+        .ok()
+        .filter(|density| (0.0..=1.0).contains(density))
+        .ok_or_else(|| format!("expected a density from 0 to 1, got {:?}", s))
+}
+
+fn parse_range(s: &str) -> Result<RangeInclusive<u32>, String> {
+    let (start, end) = s
+        .split_once(':')
+        .and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)))
+        .ok_or_else(|| format!("expected A:B, got {:?}", s))?;
+    if start > end {
+        return Err(format!("range start {} is after its end {}", start, end));
+    }
+    Ok(start..=end)
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Run random soups with seeds 0..SOUPS until they stabilize and print histograms
+    /// of stabilization times and final populations
+    Survey {
+        #[clap(long, default_value = "100")]
+        soups: u64,
+        #[clap(long, default_value = "32")]
+        width: u32,
+        #[clap(long, default_value = "32")]
+        height: u32,
+        #[clap(long, default_value = "0.5", value_parser = parse_density)]
+        density: f64,
+        /// Give up on a soup that is still changing after this many generations
+        #[clap(long, default_value = "1000")]
+        cap: u32,
+        #[clap(long, default_value = "10")]
+        bins: usize,
+    },
+    /// Send two gliders at each other head-on and report the debris once it settles
+    Collision {
+        #[clap(long, default_value = "40")]
+        width: u32,
+        #[clap(long, default_value = "40")]
+        height: u32,
+        /// Give up if the grid is still changing after this many generations
+        #[clap(long, default_value = "1000")]
+        cap: u32,
+    },
+    /// Run every entry of a batch file (one `name=... width=... rule=...` line per run)
+    /// or every .rle pattern in a directory, and write a JSON report of the outcomes
+    Batch {
+        input: PathBuf,
+        /// Where to write the report
+        #[clap(long, short, default_value = "batch.json")]
+        output: PathBuf,
+        /// Generations for entries that do not give their own
+        #[clap(long, default_value = "100")]
+        generations: u32,
+        /// Dead cells around each pattern read from a directory
+        #[clap(long, default_value = "10")]
+        margin: u32,
+    },
+    /// Print the frames of a file written with --record without simulating them again
+    Replay {
+        path: PathBuf,
+        /// Pause between frames, in milliseconds
+        #[clap(long, default_value = "0")]
+        delay_ms: u64,
+        /// Playback speed multiplier applied to the delay, e.g. 0.25 for quarter speed
+        /// or 4 for four times as fast (0.01 to 100)
+        #[clap(long, default_value = "1", value_parser = parse_speed)]
+        speed: f64,
+        /// Redraw only the cells that changed since the previous frame
+        #[clap(long)]
+        incremental: bool,
+    },
+}
+
+// Ash signatures listed at the end of a survey.
+const ASH_SHOWN: usize = 5;
+
+fn run_survey(soups: u64, width: u32, height: u32, density: f64, cap: u32, bins: usize) {
+    let results = game_of_life::survey(width, height, density, soups, cap);
+    let times = results
+        .iter()
+        .filter_map(|result| result.stabilized_at)
+        .map(|generation| generation as usize)
+        .collect::<Vec<_>>();
+    let populations = results
+        .iter()
+        .map(|result| result.population)
+        .collect::<Vec<_>>();
+    println!(
+        "Surveyed {} soups of {}x{} at density {}: {} stabilized within {} generations",
+        soups,
+        width,
+        height,
+        density,
+        times.len(),
+        cap
+    );
+    println!("Stabilization times:");
+    print!(
+        "{}",
+        game_of_life::stability::format_histogram(&times, bins)
+    );
+    println!("Final populations:");
+    print!(
+        "{}",
+        game_of_life::stability::format_histogram(&populations, bins)
+    );
+    let mut ashes = std::collections::HashMap::new();
+    for ash in results.iter().filter_map(|result| result.ash) {
+        *ashes.entry(ash).or_insert(0) += 1;
+    }
+    let mut ashes = ashes.into_iter().collect::<Vec<_>>();
+    ashes.sort_by_key(|&(ash, count)| (std::cmp::Reverse(count), ash));
+    println!("Most common ash ({} distinct):", ashes.len());
+    for (ash, count) in ashes.iter().take(ASH_SHOWN) {
+        println!("{:08x} {}", ash, count);
+    }
+}
+
+fn run_collision(width: u32, height: u32, cap: u32) {
+    use game_of_life::objects;
+
+    let result = game_of_life::collision::run_collision(width, height, cap);
+    match result.stabilized_at {
+        Some(generation) => println!("Collision settled at generation {}", generation),
+        None => println!("Collision still changing after {} generations", cap),
+    }
+    let tally = objects::object_tally(&result.live, objects::Connectivity::Eight);
+    println!(
+        "Debris: {} (ash {:08x})",
+        objects::format_tally(&tally),
+        result.ash
+    );
+}
+
+fn run_batch(input: &std::path::Path, output: &std::path::Path, generations: u32, margin: u32) {
+    use game_of_life::batch;
+
+    let entries = if input.is_dir() {
+        batch::load_batch_dir(input, generations, margin)
+    } else {
+        std::fs::read_to_string(input)
+            .map_err(|err| game_of_life::GameOfLifeError::io(input, err))
+            .and_then(|text| batch::parse_batch(&text, generations))
+    };
+    let outcomes = entries
+        .map(|entries| batch::run_batch(&entries))
+        .and_then(|outcomes| batch::write_report(&outcomes, output).map(|()| outcomes))
+        .unwrap_or_else(|err| {
+            eprintln!("Batch failed: {}", err);
+            process::exit(err.exit_code());
+        });
+    for outcome in &outcomes {
+        if let batch::BatchOutcome::Failed { name, error } = outcome {
+            eprintln!("Batch entry {} failed: {}", name, error);
+        }
+    }
+    status!("Wrote {} results to {}", outcomes.len(), output.display());
+}
+
+// Columns and rows left for an ASCII frame once its header and trailing blank line
+// are printed, or None when stdout is not a terminal.
+fn render_area() -> Option<(u32, u32)> {
+    game_of_life::terminal_size().map(|(columns, rows)| (columns, rows.saturating_sub(2)))
+}
+
+fn run_replay(path: &std::path::Path, delay_ms: u64, speed: f64, incremental: bool) {
+    use game_of_life::OutputSink;
+
+    let recording = game_of_life::Recording::read(path).unwrap_or_else(|err| {
+        eprintln!("Invalid recording: {}", err);
+        process::exit(err.exit_code());
+    });
+    let mut sink: Box<dyn OutputSink> = if incremental {
+        Box::new(game_of_life::output::IncrementalSink::new((0, 0)))
+    } else {
+        Box::new(game_of_life::output::AsciiSink {
+            view_offset: (0, 0),
+            crop: false,
+            origin: game_of_life::Origin::TopLeft,
+            max_size: render_area(),
+            border: false,
+            follow: None,
+        })
+    };
+    let delay = game_of_life::record::frame_delay(Duration::from_millis(delay_ms), speed);
+    let mut previous = None;
+    for (generation, snapshot) in recording.snapshots() {
+        let step = previous.as_ref().map_or_else(Default::default, |previous| {
+            game_of_life::StepResult::between(previous, &snapshot.live)
+        });
+        sink.on_generation(generation, &snapshot, &step);
+        previous = Some(snapshot.live);
+        std::thread::sleep(delay);
+    }
+    sink.finish();
+}
 
 #[derive(Debug, Parser)]
 struct Args {
-    #[clap(long, default_value = "600")]
-    width: u32,
-    #[clap(long, default_value = "400")]
-    height: u32,
+    #[clap(subcommand)]
+    command: Option<Command>,
+    /// Grid width. Defaults to the pattern width (plus margins) when seeding from an
+    /// RLE pattern, otherwise 600
+    #[clap(long)]
+    width: Option<u32>,
+    /// Grid height. Defaults to the pattern height (plus margins) when seeding from an
+    /// RLE pattern, otherwise 400
+    #[clap(long)]
+    height: Option<u32>,
+    /// Dead cells to pad an RLE pattern with on every side
+    #[clap(long, default_value = "0")]
+    margin: u32,
     #[clap(short, long, default_value = "100")]
     generations: u32,
+    /// Rulestring such as B36/S23, or a preset: conway, seeds, lwd, daynight. Defaults to the
+    /// rule in the RLE header when seeding from a pattern, otherwise conway
+    #[clap(long)]
+    rule: Option<Rules>,
+    /// Run under a random B/S rule generated from this seed; the rule is printed
+    #[clap(long, value_name = "SEED", conflicts_with_all = ["rule", "hensel_rule"])]
+    random_rule: Option<u64>,
+    /// Non-totalistic rule in Hensel notation such as B2c3/S23; overrides --rule
+    #[clap(long)]
+    hensel_rule: Option<game_of_life::HenselRules>,
+    /// Run a rectangle of the grid under its own rule, e.g. 30,0,30,40:B36/S23. Repeat
+    /// for several regions; the first listed wins where they overlap
+    #[clap(long, value_name = "X,Y,W,H:RULE", conflicts_with = "hensel_rule")]
+    rule_region: Vec<game_of_life::RuleRegion>,
+    /// Cells that count as neighbors: moore (all eight), von-neumann (the four
+    /// orthogonal ones) or diagonal (the four diagonal ones)
+    #[clap(long, default_value = "moore")]
+    neighborhood: game_of_life::Neighborhood,
+    /// Count a live cell as one of its own neighbors, for inner-totalistic rules
+    #[clap(long)]
+    include_self: bool,
+    /// Read settings from a JSON file with any of width, height, generations, rule,
+    /// boundary, seed, density and outputs (render, jsonl, record, apng, save_snapshot,
+    /// save_pbm). Flags given on the command line take precedence
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Print only rendered frames, requested reports and errors, without spawn
+    /// timings or the run summary
+    #[clap(long, short, global = true)]
+    quiet: bool,
+    /// Print the grid after every generation
+    #[clap(long)]
+    render: bool,
+    /// With --render, redraw only the cells that changed since the previous frame
+    #[clap(long)]
+    incremental: bool,
+    /// With --render, mark live cells about to die with `x` and cells about to be
+    /// born with `+`. Each frame is printed once the step after it has run
+    #[clap(long, requires = "render", conflicts_with = "incremental")]
+    preview: bool,
+    /// With --render, draw only the bounding box of the live cells
+    #[clap(long, requires = "render", conflicts_with_all = ["incremental", "preview"])]
+    crop: bool,
+    /// With --render and a wrapping boundary, draw a border around the grid to mark
+    /// the edges that wrap
+    #[clap(long, requires = "render", conflicts_with_all = ["crop", "incremental", "preview"])]
+    show_border: bool,
+    /// With --render, refuse to run when the grid is larger than the terminal instead
+    /// of drawing each character for a block of cells
+    #[clap(long, requires = "render")]
+    no_downsample: bool,
+    /// Also print the seeded state as generation 0 before the first step
+    #[clap(long)]
+    show_initial: bool,
+    /// Build the world and print the configuration without running any generations
+    #[clap(long)]
+    dry_run: bool,
+    /// Step through generations from stdin: Enter steps, g N jumps ahead, r steps
+    /// back, s [file] saves a snapshot, q quits
+    #[clap(long, conflicts_with = "dry_run")]
+    interactive: bool,
+    /// With --interactive, how many earlier generations r can step back through
+    /// [default: 100]
+    #[clap(long, value_name = "N", requires = "interactive")]
+    history: Option<usize>,
+    /// Show a progress bar with the generation count and population
+    #[clap(long)]
+    progress: bool,
+    /// File of timed mutations such as `10 set 5 5 alive`, applied at that generation
+    #[clap(long)]
+    script: Option<PathBuf>,
+    /// Plaintext mask of playable cells (`.` blocked, anything else playable)
+    #[clap(long)]
+    mask_file: Option<PathBuf>,
+    /// Resume from a snapshot file; its grid size and generation count take precedence
+    #[clap(long)]
+    load_snapshot: Option<PathBuf>,
+    /// Write a snapshot of the final state to this file
+    #[clap(long)]
+    save_snapshot: Option<PathBuf>,
+    /// Write the final state as a plain PBM (P1) bitmap to this file
+    #[clap(long)]
+    save_pbm: Option<PathBuf>,
+    /// Write a grayscale PNG of how many generations each cell was alive, brightest
+    /// for the cell alive most often
+    #[clap(long, value_name = "FILE")]
+    save_times_alive: Option<PathBuf>,
+    /// Print timing results as a JSON object instead of human-readable text. Implies
+    /// --quiet, so the object is the only status output
+    #[clap(long)]
+    bench_json: bool,
+    /// Write one JSON object per generation (gen, population, births, deaths) to
+    /// this file, or to stdout when no file is given
+    #[clap(long, value_name = "FILE", num_args = 0..=1, default_missing_value = "-")]
+    jsonl: Option<PathBuf>,
+    /// Print `gen W H`, the width and height of the live cells' bounding box, every
+    /// generation, and add bbox_width and bbox_height to --jsonl lines. With --jsonl
+    /// on stdout only the JSON lines carry them
+    #[clap(long)]
+    bbox_log: bool,
+    /// Record every generation's live cells to this file for the replay subcommand
+    #[clap(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+    /// Save every generation as a frame of an animated PNG, one pixel per cell
+    #[clap(long, value_name = "FILE")]
+    apng: Option<PathBuf>,
+    /// With --apng, how long each frame is shown
+    #[clap(long, value_name = "MS", default_value = "100", requires = "apng")]
+    apng_delay: u64,
+    /// Render and export only generations A through B, inclusive
+    #[clap(long, value_name = "A:B", value_parser = parse_range)]
+    export_range: Option<RangeInclusive<u32>>,
+    /// Seed the grid from an inline RLE body such as "bo$2bo$3o!"
+    #[clap(long, conflicts_with_all = ["rle_file", "image_file"])]
+    rle: Option<String>,
+    /// Seed the grid from an RLE pattern file
+    #[clap(long, conflicts_with = "image_file")]
+    rle_file: Option<PathBuf>,
+    /// Seed the grid from a PNG image; pixels brighter than --threshold start alive
+    #[clap(long)]
+    image_file: Option<PathBuf>,
+    /// With --image-file, the grayscale brightness (0-255) a pixel must exceed to be live
+    #[clap(long, default_value_t = game_of_life::png::DEFAULT_THRESHOLD, requires = "image_file")]
+    threshold: u8,
+    /// Start from a random soup generated with this seed instead of an all-live grid
+    #[clap(long, conflicts_with_all = ["rle", "rle_file", "image_file"])]
+    seed: Option<u64>,
+    /// With --seed, the chance of each cell starting alive
+    #[clap(long, default_value = "0.5", requires = "seed", value_parser = parse_density)]
+    density: f64,
+    /// Start from an all-dead grid. Without a pattern, snapshot or this flag every
+    /// cell starts alive
+    #[clap(long, conflicts_with_all = ["rle", "rle_file", "image_file", "seed"])]
+    empty: bool,
+    /// Write autosave_NNNNN.json snapshots every N generations and after the last one
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    autosave_every: Option<u32>,
+    /// Run grids larger than the dense spawning limit of 10 million cells anyway
+    #[clap(long)]
+    force: bool,
+    /// Despawn dead cell entities every N generations to save memory on shrinking patterns
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    compact_every: Option<u32>,
+    /// Only evaluate cells in SIZE x SIZE regions with recent changes nearby
+    #[clap(long, value_name = "SIZE", value_parser = clap::value_parser!(u32).range(1..))]
+    activity_regions: Option<u32>,
+    /// With --activity-regions, generations a region stays active after its last
+    /// change. Values below 1 miss births
+    #[clap(long, default_value = "1", requires = "activity_regions")]
+    keep_active: u32,
+    /// Adjacency used to group live cells into objects: 4 or 8
+    #[clap(long, default_value = "8")]
+    connectivity: game_of_life::Connectivity,
+    /// Track glider-shaped objects every generation and report how many are in flight,
+    /// adding the count to --jsonl lines as gliders
+    #[clap(long)]
+    count_gliders: bool,
+    /// Print a sparkline of the population over the run when it ends
+    #[clap(long)]
+    stats: bool,
+    /// Report when the live cells first form a single known still life or oscillator
+    #[clap(long)]
+    detect_attractors: bool,
+    /// What lies beyond the grid edges: dead, toroidal (opposite edges wrap), klein
+    /// (top and bottom wrap mirrored) or projective (both pairs wrap mirrored)
+    #[clap(long, default_value = "dead")]
+    boundary: game_of_life::BoundaryMode,
+    /// With a toroidal boundary, render the grid starting at column,row X,Y, wrapping around
+    #[clap(long, value_name = "X,Y", value_parser = parse_offset)]
+    view_offset: Option<(i32, i32)>,
+    /// With --render and a toroidal boundary, move the view every generation to keep
+    /// the live cells centred, e.g. to chase a spaceship. Starts from --view-offset
+    #[clap(long, requires = "render", conflicts_with_all = ["crop", "incremental", "preview"])]
+    follow: bool,
+    /// Corner the first row is drawn in: top-left (y grows downward) or bottom-left
+    /// (y grows upward). Not applied with --incremental
+    #[clap(long, default_value = "top-left", conflicts_with = "incremental")]
+    origin: game_of_life::Origin,
+    /// Print the time spent in each simulation system at the end of the run
+    #[clap(long)]
+    profile: bool,
+    /// Run single-threaded in a fixed order and print a digest of the final state
+    #[clap(long)]
+    deterministic: bool,
+    /// Surround the grid with N extra dead cells on every side so patterns near the
+    /// edges have room to grow. The extra cells are part of the simulated grid
+    #[clap(long, value_name = "N", default_value = "0", conflicts_with_all = ["script", "mask_file"])]
+    border_margin: u32,
+    /// Cell storage: ecs (one entity per cell) or soa (plain vectors, without
+    /// per-generation output, scripts, masks or other schedule features)
+    #[clap(long, default_value = "ecs")]
+    backend: game_of_life::Backend,
+    /// Stop after this many seconds of wall-clock time, keeping the generations done so far
+    #[clap(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
 }
 
 fn main() {
-    let args = Args::parse();
-    let width = args.width;
-    let height = args.height;
+    let matches = Args::command().get_matches();
+    let args = Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+    game_of_life::set_quiet(args.quiet || args.bench_json);
+    match args.command {
+        Some(Command::Survey {
+            soups,
+            width,
+            height,
+            density,
+            cap,
+            bins,
+        }) => return run_survey(soups, width, height, density, cap, bins),
+        Some(Command::Collision { width, height, cap }) => {
+            return run_collision(width, height, cap)
+        }
+        Some(Command::Batch {
+            input,
+            output,
+            generations,
+            margin,
+        }) => return run_batch(&input, &output, generations, margin),
+        Some(Command::Replay {
+            path,
+            delay_ms,
+            speed,
+            incremental,
+        }) => return run_replay(&path, delay_ms, speed, incremental),
+        None => (),
+    }
     let generations = args.generations;
-    println!(
-        "Running Game of Life with width: {:?}, height: {:?}, generations: {:?}",
-        width, height, generations
+    let config_file = args.config.as_deref().map(|path| {
+        game_of_life::config::load_config(path).unwrap_or_else(|err| {
+            eprintln!("Invalid config: {}", err);
+            process::exit(err.exit_code());
+        })
+    });
+    let script = args.script.as_deref().map(|path| {
+        game_of_life::load_script(path).unwrap_or_else(|err| {
+            eprintln!("Invalid script: {}", err);
+            process::exit(err.exit_code());
+        })
+    });
+    let mask = args.mask_file.as_deref().map(|path| {
+        game_of_life::load_mask(path).unwrap_or_else(|err| {
+            eprintln!("Invalid mask: {}", err);
+            process::exit(err.exit_code());
+        })
+    });
+    let snapshot = args.load_snapshot.as_deref().map(|path| {
+        game_of_life::Snapshot::read(path).unwrap_or_else(|err| {
+            eprintln!("Invalid snapshot: {}", err);
+            process::exit(err.exit_code());
+        })
+    });
+    let pattern = match (&args.rle, &args.rle_file, &args.image_file) {
+        (Some(rle), _, _) => Some(game_of_life::parse_rle(rle)),
+        (None, Some(path), _) => Some(game_of_life::load_rle(path)),
+        (None, None, Some(path)) => Some(game_of_life::png::load_png(path, args.threshold)),
+        (None, None, None) => None,
+    }
+    .map(|pattern| {
+        pattern.unwrap_or_else(|err| {
+            eprintln!("Invalid pattern: {}", err);
+            process::exit(err.exit_code());
+        })
+    })
+    .map(|pattern| pattern.with_margin(args.margin));
+    let rule = args.rule.clone().or(args.random_rule.map(Rules::random));
+    let interrupted = game_of_life::Interrupted::default();
+    let handler_flag = interrupted.clone();
+    if let Err(err) = ctrlc::set_handler(move || handler_flag.set()) {
+        eprintln!("Failed to install Ctrl-C handler: {}", err);
+    }
+    let options = game_of_life::RunOptions {
+        render: args.render,
+        incremental: args.incremental,
+        preview: args.preview,
+        crop: args.crop,
+        show_border: args.show_border,
+        max_render: None,
+        show_initial: args.show_initial,
+        dry_run: args.dry_run,
+        interactive: args.interactive,
+        history_limit: args.history,
+        progress: args.progress,
+        script,
+        hensel: args.hensel_rule,
+        rule_regions: args.rule_region,
+        neighborhood: args.neighborhood,
+        include_self: args.include_self,
+        mask,
+        snapshot,
+        pattern,
+        empty: args.empty,
+        save_snapshot: args.save_snapshot,
+        save_pbm: args.save_pbm,
+        save_times_alive: args.save_times_alive,
+        autosave_every: args.autosave_every,
+        compact_every: args.compact_every,
+        activity: args
+            .activity_regions
+            .map(|region_size| game_of_life::ActivityConfig {
+                region_size,
+                keep_active: args.keep_active,
+            }),
+        bench_json: args.bench_json,
+        jsonl: args.jsonl,
+        record: args.record,
+        apng: args.apng,
+        apng_delay_ms: args.apng_delay,
+        export_range: args.export_range,
+        force: args.force,
+        connectivity: args.connectivity,
+        count_gliders: args.count_gliders,
+        bbox_log: args.bbox_log,
+        stats: args.stats,
+        detect_attractors: args.detect_attractors,
+        boundary: args.boundary,
+        view_offset: args.view_offset.unwrap_or_default(),
+        follow: args.follow,
+        origin: args.origin,
+        profile: args.profile,
+        deterministic: args.deterministic,
+        time_limit: args.time_limit,
+        backend: args.backend,
+        border_margin: args.border_margin,
+        interrupted,
+        on_complete: None,
+    };
+    let mut config = game_of_life::SimConfig {
+        width: args.width,
+        height: args.height,
+        generations,
+        rule,
+        seed: args.seed,
+        density: args.density,
+        options,
+    };
+    if let Some(file) = &config_file {
+        let cli = game_of_life::config::ConfigFile::from_command_line(&config, from_cli);
+        config = game_of_life::config::merge(config, file, &cli);
+    }
+    let boundary = config.options.boundary;
+    if args.view_offset.is_some() && boundary != game_of_life::BoundaryMode::Toroidal {
+        eprintln!("--view-offset requires --boundary toroidal");
+        process::exit(2);
+    }
+    if args.follow && boundary != game_of_life::BoundaryMode::Toroidal {
+        eprintln!("--follow requires --boundary toroidal");
+        process::exit(2);
+    }
+    if args.show_border && boundary == game_of_life::BoundaryMode::Dead {
+        eprintln!("--show-border requires a wrapping --boundary");
+        process::exit(2);
+    }
+    if let Err(err) = config.validate() {
+        eprintln!("{}", err);
+        process::exit(err.exit_code());
+    }
+    let (width, height) = config.grid_size();
+    let max_render = render_area().filter(|_| config.options.render);
+    if let Some((columns, rows)) = max_render.filter(|_| args.no_downsample && !args.crop) {
+        // In u64: the padded side fits a u32 once validated, but the border can push
+        // it past.
+        let margin = u64::from(args.border_margin) * 2 + if args.show_border { 2 } else { 0 };
+        let (shown_width, shown_height) = (u64::from(width) + margin, u64::from(height) + margin);
+        if shown_width > u64::from(columns) || shown_height > u64::from(rows) {
+            eprintln!(
+                "A {}x{} grid does not fit in this {}x{} terminal; shrink the grid, enlarge the \
+                 terminal, use --crop or drop --no-downsample",
+                shown_width, shown_height, columns, rows
+            );
+            process::exit(2);
+        }
+    }
+    config.options.max_render = max_render.filter(|_| !args.no_downsample);
+    let rule = config.rules().unwrap_or_else(|err| {
+        eprintln!("Invalid RLE pattern: {}", err);
+        process::exit(err.exit_code());
+    });
+    if let Some(seed) = args.random_rule {
+        status!("Random rule for seed {}: {}", seed, rule);
+    }
+    status!(
+        "Running Game of Life with width: {:?}, height: {:?}, generations: {:?}, rule: {}",
+        width,
+        height,
+        config.generations,
+        rule
     );
-    game_of_life::initialize(width, height, generations);
+    if let Err(err) = game_of_life::initialize(config) {
+        eprintln!("{}", err);
+        process::exit(err.exit_code());
+    }
 }
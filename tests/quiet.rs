@@ -0,0 +1,103 @@
+use std::process::Command;
+
+fn run_bin(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ecs_without_bevy"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn run(extra: &[&str]) -> String {
+    let mut args = vec![
+        "--width",
+        "8",
+        "--height",
+        "8",
+        "--generations",
+        "2",
+        "--render",
+    ];
+    args.extend_from_slice(extra);
+    run_bin(&args)
+}
+
+#[test]
+fn test_quiet_keeps_only_rendered_frames() {
+    let loud = run(&[]);
+    assert!(loud.contains("Spawning cells took"));
+    assert!(loud.contains("generations took"));
+
+    let quiet = run(&["--quiet"]);
+    assert!(!quiet.contains("Spawning"));
+    assert!(!quiet.contains("took"));
+    assert!(!quiet.contains("Running Game of Life"));
+    assert_eq!(quiet.matches("Generation ").count(), 2);
+}
+
+#[test]
+fn test_bench_json_prints_only_the_report() {
+    let stdout = run_bin(&[
+        "--width",
+        "8",
+        "--height",
+        "8",
+        "--rule",
+        "lwd",
+        "--bench-json",
+    ]);
+    let report: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(report["generations"], 100);
+}
+
+#[test]
+fn test_quiet_applies_to_interactive_runs_and_subcommands() {
+    let interactive = run_bin(&["--width", "5", "--height", "5", "--interactive", "--quiet"]);
+    assert!(interactive.contains("Generation 0:"));
+    assert!(!interactive.contains("Completed"));
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("ecs_without_bevy_quiet_{}.txt", std::process::id()));
+    let output = dir.join(format!(
+        "ecs_without_bevy_quiet_{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&input, "name=small width=8 height=8 generations=4 seed=1\n").unwrap();
+    let batch = run_bin(&[
+        "batch",
+        input.to_str().unwrap(),
+        "--output",
+        output.to_str().unwrap(),
+        "--quiet",
+    ]);
+    std::fs::remove_file(&input).unwrap();
+    std::fs::remove_file(&output).unwrap();
+    assert_eq!(batch, "");
+}
+
+#[test]
+fn test_bbox_log_keeps_jsonl_on_stdout_parseable() {
+    let stdout = run_bin(&[
+        "--width",
+        "8",
+        "--height",
+        "8",
+        "--generations",
+        "3",
+        "--rule",
+        "lwd",
+        "--jsonl",
+        "--bbox-log",
+        "--quiet",
+    ]);
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{}", stdout);
+    for line in lines {
+        let stats: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(
+            (stats["bbox_width"].clone(), stats["bbox_height"].clone()),
+            (8.into(), 8.into())
+        );
+    }
+}